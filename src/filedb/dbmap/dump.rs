@@ -0,0 +1,280 @@
+use super::super::super::{DbMapKeyType, DbXxxObjectSafe};
+use super::super::inner::dbxxx::now_millis;
+use super::super::inner::VALUE_LEN_MAX;
+use super::FileDbMap;
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader, BufWriter, Read, Result, Write};
+
+/// The format of the dump, see `FileDbMap::export()` and `FileDbMap::import()`.
+///
+/// The dump does not depend on the `vf_*` encoding features, so it can move a map
+/// between machines or builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// Length-prefixed binary format.
+    ///
+    /// ```text
+    /// header: b"abysdbD\0", 8 bytes key type signature, entry count: u64 le
+    /// entry:  key length: u64 le, key, value length: u64 le, value
+    /// ```
+    Binary,
+    /// Line-oriented text format of `key<TAB>value`.
+    ///
+    /// The first line is the header of `# abyssiniandb dump<TAB>signature<TAB>count`.
+    /// A backslash, a tab, a line feed, a carriage return, the control codes and
+    /// the bytes that are not UTF-8 are escaped as `\\`, `\t`, `\n`, `\r` and `\xHH`.
+    /// On import, the header is optional, and a line without a tab is split at
+    /// the first space, so the `fixtures/test-*.txt` files can be imported.
+    Text,
+}
+
+const DUMP_SIGNATURE: [u8; 8] = [b'a', b'b', b'y', b's', b'd', b'b', b'D', 0u8];
+const TEXT_HEADER_PREFIX: &[u8] = b"# abyssiniandb dump\t";
+
+impl<KT: DbMapKeyType> FileDbMap<KT> {
+    /// Writes all key-value pairs into the writer in the dump format,
+    /// and returns the count of them. The expired keys are not written.
+    pub fn export<W: Write>(&self, writer: W, format: DumpFormat) -> Result<u64> {
+        let mut writer = BufWriter::new(writer);
+        let db_map_inner = RefCell::borrow(&self.0);
+        let now = now_millis();
+        let count = db_map_inner.count_unexpired(now)?;
+        match format {
+            DumpFormat::Binary => {
+                writer.write_all(&DUMP_SIGNATURE)?;
+                writer.write_all(&KT::signature())?;
+                writer.write_all(&count.to_le_bytes())?;
+                db_map_inner.for_each_unexpired(now, |key, value| {
+                    let key = key.as_bytes();
+                    writer.write_all(&(key.len() as u64).to_le_bytes())?;
+                    writer.write_all(key)?;
+                    writer.write_all(&(value.len() as u64).to_le_bytes())?;
+                    writer.write_all(&value)
+                })?;
+            }
+            DumpFormat::Text => {
+                let mut line = TEXT_HEADER_PREFIX.to_vec();
+                escape_into(&KT::signature(), &mut line);
+                line.extend_from_slice(format!("\t{count}\n").as_bytes());
+                writer.write_all(&line)?;
+                db_map_inner.for_each_unexpired(now, |key, value| {
+                    line.clear();
+                    escape_into(key.as_bytes(), &mut line);
+                    line.push(b'\t');
+                    escape_into(&value, &mut line);
+                    line.push(b'\n');
+                    writer.write_all(&line)
+                })?;
+            }
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+    /// Reads the key-value pairs from the reader in the dump format, and inserts them.
+    /// Returns the count of the read pairs.
+    ///
+    /// Returns an error of `InvalidData` if the dump is broken, or its key type
+    /// signature or its entry count does not match.
+    pub fn import<R: Read>(&mut self, reader: R, format: DumpFormat) -> Result<u64> {
+        let mut reader = BufReader::new(reader);
+        match format {
+            DumpFormat::Binary => self.import_binary(&mut reader),
+            DumpFormat::Text => self.import_text(&mut reader),
+        }
+    }
+    fn import_binary<R: Read>(&mut self, reader: &mut R) -> Result<u64> {
+        let mut buf8 = [0u8; 8];
+        reader.read_exact(&mut buf8)?;
+        if buf8 != DUMP_SIGNATURE {
+            return Err(invalid_data("invalid dump signature".to_string()));
+        }
+        reader.read_exact(&mut buf8)?;
+        check_key_signature::<KT>(&buf8)?;
+        reader.read_exact(&mut buf8)?;
+        let count = u64::from_le_bytes(buf8);
+        let mut key = Vec::new();
+        let mut value = Vec::new();
+        for _ in 0..count {
+            read_length_prefixed(reader, &mut key, u32::MAX as u64)?;
+            read_length_prefixed(reader, &mut value, VALUE_LEN_MAX as u64)?;
+            self.put_kt(&KT::from_bytes(&key), &value)?;
+        }
+        Ok(count)
+    }
+    fn import_text<R: BufRead>(&mut self, reader: &mut R) -> Result<u64> {
+        let mut line = Vec::new();
+        let mut key = Vec::new();
+        let mut value = Vec::new();
+        let mut expected_count = None;
+        let mut count = 0;
+        let mut line_no = 0;
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            line_no += 1;
+            if line.last() == Some(&b'\n') {
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+            }
+            if line_no == 1 && line.starts_with(TEXT_HEADER_PREFIX) {
+                expected_count = Some(parse_text_header::<KT>(&line)?);
+                continue;
+            }
+            let sep = line.iter().position(|&b| b == b'\t');
+            let sep = match sep {
+                Some(pos) => pos,
+                None => match line.iter().position(|&b| b == b' ') {
+                    Some(pos) if expected_count.is_none() => pos,
+                    _ => return Err(invalid_data(format!("no separator in the line: {line_no}"))),
+                },
+            };
+            unescape_into(&line[..sep], &mut key, line_no)?;
+            unescape_into(&line[(sep + 1)..], &mut value, line_no)?;
+            self.put_kt(&KT::from_bytes(&key), &value)?;
+            count += 1;
+        }
+        if let Some(expected) = expected_count {
+            if expected != count {
+                return Err(invalid_data(format!(
+                    "the entry count does not match: {count} != {expected}"
+                )));
+            }
+        }
+        Ok(count)
+    }
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+fn check_key_signature<KT: DbMapKeyType>(signature: &[u8]) -> Result<()> {
+    if signature != KT::signature() {
+        return Err(invalid_data(format!(
+            "the key type signature does not match: {signature:?}"
+        )));
+    }
+    Ok(())
+}
+
+/// reads the length and the bytes, the length over `max` is the `InvalidData` error.
+/// the buffer grows with the bytes read, so a broken length does not allocate it.
+fn read_length_prefixed<R: Read>(reader: &mut R, buf: &mut Vec<u8>, max: u64) -> Result<()> {
+    let mut buf8 = [0u8; 8];
+    reader.read_exact(&mut buf8)?;
+    let len = u64::from_le_bytes(buf8);
+    if len > max {
+        return Err(invalid_data(format!("too large length: {len} > {max}")));
+    }
+    buf.clear();
+    let read_len = reader.take(len).read_to_end(buf)?;
+    if (read_len as u64) < len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!("the dump is truncated: {read_len} < {len}"),
+        ));
+    }
+    Ok(())
+}
+
+/// parses the text header, and returns the entry count.
+fn parse_text_header<KT: DbMapKeyType>(line: &[u8]) -> Result<u64> {
+    let rest = &line[TEXT_HEADER_PREFIX.len()..];
+    let sep = match rest.iter().position(|&b| b == b'\t') {
+        Some(pos) => pos,
+        None => return Err(invalid_data("invalid text header".to_string())),
+    };
+    let mut signature = Vec::new();
+    unescape_into(&rest[..sep], &mut signature, 1)?;
+    check_key_signature::<KT>(&signature)?;
+    std::str::from_utf8(&rest[(sep + 1)..])
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .ok_or_else(|| invalid_data("invalid entry count in text header".to_string()))
+}
+
+/// escapes the bytes for the text format.
+fn escape_into(bytes: &[u8], out: &mut Vec<u8>) {
+    let mut rest = bytes;
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(s) => {
+                escape_str_into(s, out);
+                return;
+            }
+            Err(err) => {
+                let (valid, invalid) = rest.split_at(err.valid_up_to());
+                escape_str_into(std::str::from_utf8(valid).unwrap(), out);
+                let invalid_len = err.error_len().unwrap_or(invalid.len());
+                for &b in &invalid[..invalid_len] {
+                    out.extend_from_slice(format!("\\x{b:02x}").as_bytes());
+                }
+                rest = &invalid[invalid_len..];
+            }
+        }
+    }
+}
+
+fn escape_str_into(s: &str, out: &mut Vec<u8>) {
+    for c in s.chars() {
+        match c {
+            '\\' => out.extend_from_slice(b"\\\\"),
+            '\t' => out.extend_from_slice(b"\\t"),
+            '\n' => out.extend_from_slice(b"\\n"),
+            '\r' => out.extend_from_slice(b"\\r"),
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                out.extend_from_slice(format!("\\x{:02x}", c as u32).as_bytes())
+            }
+            c => {
+                let mut buf = [0u8; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+}
+
+/// unescapes the bytes of the text format.
+fn unescape_into(bytes: &[u8], out: &mut Vec<u8>, line_no: u64) -> Result<()> {
+    out.clear();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b != b'\\' {
+            out.push(b);
+            i += 1;
+            continue;
+        }
+        match bytes.get(i + 1) {
+            Some(b'\\') => out.push(b'\\'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'n') => out.push(b'\n'),
+            Some(b'r') => out.push(b'\r'),
+            Some(b'x') => {
+                let hex = bytes
+                    .get((i + 2)..(i + 4))
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(val) => out.push(val),
+                    None => {
+                        return Err(invalid_data(format!(
+                            "invalid escape in the line: {line_no}"
+                        )))
+                    }
+                }
+                i += 2;
+            }
+            _ => {
+                return Err(invalid_data(format!(
+                    "invalid escape in the line: {line_no}"
+                )))
+            }
+        }
+        i += 2;
+    }
+    Ok(())
+}
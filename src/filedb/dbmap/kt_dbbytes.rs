@@ -1,6 +1,8 @@
 use super::super::super::{DbMapKeyType, HashValue};
+use super::super::DbXxxPrefix;
 use super::FileDbMap;
 use std::fmt::{Display, Error, Formatter};
+use std::io::Result as IoResult;
 use std::ops::Deref;
 
 /// DbBytes Map in a file databse.
@@ -10,6 +12,18 @@ pub type FileDbMapDbBytes = FileDbMap<DbBytes>;
 #[derive(Debug, Default, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub struct DbBytes(Vec<u8>);
 
+impl FileDbMap<DbBytes> {
+    /// An iterator visiting key-value pairs whose key starts with the `prefix`, in key order.
+    /// this needs the ordered index, without it this returns the `Unsupported` error.
+    #[inline]
+    pub fn scan_prefix<P: AsRef<[u8]> + ?Sized>(
+        &self,
+        prefix: &P,
+    ) -> IoResult<DbXxxPrefix<DbBytes>> {
+        DbXxxPrefix::new(self.0.clone(), prefix.as_ref())
+    }
+}
+
 impl DbMapKeyType for DbBytes {
     #[inline]
     fn from_bytes(bytes: &[u8]) -> Self {
@@ -1,11 +1,17 @@
 use super::super::{DbMap, DbMapKeyType, DbXxx, DbXxxBase, DbXxxObjectSafe};
+use super::super::{EntryMeta, MergeOperator};
+use super::inner::dbxxx::now_millis;
 use super::{CheckFileDbMap, CountOfPerSize, KeysCountStats, LengthStats, RecordSizeStats};
-use super::{DbXxxIntoIter, DbXxxIter, DbXxxIterMut, DbXxxKeys, DbXxxValues};
-use super::{FileDbParams, FileDbXxxInner, Key, Value};
+use super::{DbXxxChanges, DbXxxIntoIter, DbXxxIter, DbXxxIterMut, DbXxxKeys, DbXxxValues};
+use super::{DbXxxMetrics, DbXxxPosition, DbXxxRange, Entry};
+use super::{DbXxxValueReader, DbXxxValueWriter};
+use super::{FileDbParams, FileDbXxxInner, Key, RepairReport, Value, VerifyReport};
 use std::cell::RefCell;
 use std::io::Result;
+use std::ops::RangeBounds;
 use std::path::Path;
 use std::rc::Rc;
+use std::time::Duration;
 
 pub mod kt_dbbytes;
 pub mod kt_dbstring;
@@ -20,6 +26,9 @@ pub use kt_dbu64::{DbU64, FileDbMapDbU64};
 pub mod kt_dbvu64;
 pub use kt_dbvu64::{DbVu64, FileDbMapDbVu64};
 
+mod dump;
+pub use dump::DumpFormat;
+
 /// DbMap in a file database.
 #[derive(Debug, Clone)]
 pub struct FileDbMap<KT: DbMapKeyType>(Rc<RefCell<FileDbXxxInner<KT>>>);
@@ -38,6 +47,290 @@ impl<KT: DbMapKeyType> FileDbMap<KT> {
     pub fn is_dirty(&self) -> bool {
         RefCell::borrow(&self.0).is_dirty()
     }
+    /// Gets the given key's corresponding entry in the map for in-place manipulation.
+    #[inline]
+    pub fn entry<'a, Q>(&mut self, key: &'a Q) -> Result<Entry<'_, KT>>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        Entry::new(RefCell::borrow_mut(&self.0), key_kt)
+    }
+    /// Reads the value into the buffer, and returns true if the key exists.
+    /// The buffer is cleared at first, and its allocation is reused.
+    #[inline]
+    pub fn get_into<'a, Q>(&self, key: &'a Q, buf: &mut Vec<u8>) -> Result<bool>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        RefCell::borrow_mut(&self.0).get_into_kt(&key_kt, buf)
+    }
+    /// Calls the closure with the value, and returns its result.
+    /// The value is borrowed straight from the buffer when it fits in the buffered chunk.
+    /// The closure must not access this map.
+    #[inline]
+    pub fn with_value<'a, Q, R, F>(&self, key: &'a Q, f: F) -> Result<Option<R>>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+        F: FnOnce(&[u8]) -> R,
+    {
+        let key_kt: KT = From::from(key);
+        RefCell::borrow_mut(&self.0).with_value_kt(&key_kt, f)
+    }
+    /// Returns the part of the value, from `offset` up to `len` bytes.
+    /// The returned bytes are short if the value ends before it.
+    #[inline]
+    pub fn get_range<'a, Q>(&self, key: &'a Q, offset: u64, len: usize) -> Result<Option<Vec<u8>>>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        RefCell::borrow_mut(&self.0).get_range_kt(&key_kt, offset, len)
+    }
+    /// Returns the reader over the value, that implements `Read` and `Seek`.
+    #[inline]
+    pub fn value_reader<'a, Q>(&self, key: &'a Q) -> Result<Option<DbXxxValueReader<KT>>>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        DbXxxValueReader::new(self.0.clone(), key_kt)
+    }
+    /// Returns the writer that streams the value of `len` bytes into the key.
+    /// The value is stored when `DbXxxValueWriter::finish()` is called.
+    #[inline]
+    pub fn value_writer<'a, Q>(&mut self, key: &'a Q, len: u64) -> Result<DbXxxValueWriter<KT>>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        DbXxxValueWriter::new(self.0.clone(), key_kt, len)
+    }
+    /// Registers the merge operator that is used by `merge()`.
+    #[inline]
+    pub fn set_merge_operator(&mut self, merge_operator: Rc<dyn MergeOperator>) {
+        RefCell::borrow_mut(&self.0).set_merge_operator(merge_operator)
+    }
+    /// Merges the operand into the value with the registered merge operator.
+    #[inline]
+    pub fn merge<'a, Q>(&mut self, key: &'a Q, operand: &[u8]) -> Result<()>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        RefCell::borrow_mut(&self.0).merge_kt(&key_kt, operand)
+    }
+    /// Inserts the key-value pair that expires after `ttl`.
+    ///
+    /// The expired key is invisible to the reads and the iterators,
+    /// but it is kept in the file and counted by `len()` until
+    /// it is overwritten, deleted or purged by `purge_expired()`.
+    /// `put()` clears the expiry, and `append()`, `merge()` and `fetch_add()` keep it.
+    #[inline]
+    pub fn put_with_ttl<'a, Q>(&mut self, key: &'a Q, value: &[u8], ttl: Duration) -> Result<()>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        let ttl_millis = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+        let expire_at = now_millis().saturating_add(ttl_millis);
+        RefCell::borrow_mut(&self.0).put_with_expire_kt(&key_kt, value, expire_at)
+    }
+    /// Deletes all expired keys, and returns the count of them.
+    #[inline]
+    pub fn purge_expired(&mut self) -> Result<u64> {
+        RefCell::borrow_mut(&self.0).purge_expired()
+    }
+    /// Builds this empty map from the key-value pairs, and returns the count of the stored pairs.
+    ///
+    /// This is faster than `put_from_iter()` for a large load. The values and the keys
+    /// are written sequentially, and then the hash buckets are made at once.
+    /// The buckets size is picked from the count of the pairs, instead of
+    /// `FileDbParams::buckets_size`. If the same keys are given, the last one wins.
+    /// This keeps about 40 bytes per pair in memory while building.
+    /// Returns an error of `InvalidInput` if the map is not empty.
+    #[inline]
+    pub fn build_from_iter<T>(&mut self, iter: T) -> Result<u64>
+    where
+        T: IntoIterator<Item = (KT, Vec<u8>)>,
+    {
+        RefCell::borrow_mut(&self.0).build_from_iter(iter.into_iter())
+    }
+    /// Repairs the map after a crash, from the key file and the value file.
+    ///
+    /// This scans the live key pieces and rebuilds the hash buckets, the bitmap
+    /// and the item count. The key pieces of the broken keys or the invalid
+    /// value offsets are dropped, and the free lists are rebuilt from the dead
+    /// pieces. The buckets size is picked from the count of the keys.
+    /// Check the map with `CheckFileDbMap::verify()` before and after this.
+    #[inline]
+    pub fn repair(&mut self) -> Result<RepairReport> {
+        RefCell::borrow_mut(&self.0).repair()
+    }
+    /// Retains only the key-value pairs specified by the predicate.
+    ///
+    /// This walks the buckets once and deletes the other pairs in place.
+    /// The expired keys are deleted too, without calling the predicate.
+    #[inline]
+    pub fn retain<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&KT, &[u8]) -> bool,
+    {
+        RefCell::borrow_mut(&self.0).delete_if(|k, v| !f(k, v), false)?;
+        Ok(())
+    }
+    /// Deletes the key-value pairs specified by the predicate, and returns them.
+    ///
+    /// This walks the buckets once and deletes the pairs in place.
+    #[inline]
+    pub fn extract_if<F>(&mut self, f: F) -> Result<Vec<(KT, Vec<u8>)>>
+    where
+        F: FnMut(&KT, &[u8]) -> bool,
+    {
+        RefCell::borrow_mut(&self.0).delete_if(f, true)
+    }
+    /// Deletes all key-value pairs, and returns them.
+    #[inline]
+    pub fn drain(&mut self) -> Result<Vec<(KT, Vec<u8>)>> {
+        RefCell::borrow_mut(&self.0).delete_if(|_k, _v| true, true)
+    }
+    /// An iterator resuming from the position that is got by `DbXxxIter::position()`.
+    /// see `DbXxxPosition` about the guarantees between pages.
+    /// The size hint of the resumed iterator is an upper bound.
+    #[inline]
+    pub fn iter_from(&self, position: &DbXxxPosition) -> Result<DbXxxIter<KT>> {
+        DbXxxIter::with_position(self.0.clone(), position)
+    }
+}
+
+/// ordered access. these need the ordered index, see `FileDbParams::ordered_index`.
+///
+/// the key order is the order of the key bytes, except that `DbU64`, `DbI64`
+/// and `DbVu64` keys are in the order of the integer value.
+impl<KT: DbMapKeyType> FileDbMap<KT> {
+    /// returns true if this map has the ordered index.
+    #[inline]
+    pub fn has_ordered_index(&self) -> bool {
+        RefCell::borrow(&self.0).has_ordered_index()
+    }
+    /// An iterator visiting key-value pairs in the range, in key order.
+    ///
+    /// `next()` panics if a key is inserted or deleted through another handle
+    /// of the map while iterating, `try_next()` returns it as the error.
+    #[inline]
+    pub fn range<R: RangeBounds<KT>>(&self, range: R) -> Result<DbXxxRange<KT>> {
+        DbXxxRange::new(self.0.clone(), range)
+    }
+    /// An iterator visiting all key-value pairs in key order.
+    #[inline]
+    pub fn iter_ordered(&self) -> Result<DbXxxRange<KT>> {
+        DbXxxRange::new(self.0.clone(), ..)
+    }
+    /// returns the key-value pair of the minimum key.
+    #[inline]
+    pub fn first(&self) -> Result<Option<(KT, Vec<u8>)>> {
+        Ok(self.iter_ordered()?.next())
+    }
+    /// returns the key-value pair of the maximum key.
+    #[inline]
+    pub fn last(&self) -> Result<Option<(KT, Vec<u8>)>> {
+        RefCell::borrow(&self.0).ordered_last()
+    }
+}
+
+/// change data capture. these need the change log, see `FileDbParams::change_log`.
+///
+/// every insertion, update and deletion of a key is recorded with a sequence number,
+/// including the ones by `append()`, `merge()`, the bulk operations and `purge_expired()`.
+/// the values are not recorded, and a key that just expires is not recorded until purged.
+impl<KT: DbMapKeyType> FileDbMap<KT> {
+    /// returns true if this map has the change log.
+    #[inline]
+    pub fn has_change_log(&self) -> bool {
+        RefCell::borrow(&self.0).has_change_log()
+    }
+    /// returns the sequence number of the last change, zero is no change.
+    #[inline]
+    pub fn last_change_seq(&self) -> Result<u64> {
+        RefCell::borrow(&self.0).last_change_seq()
+    }
+    /// An iterator visiting the changes after the sequence number `seq`, in sequence order.
+    /// `seq` of zero visits all changes in the log. The items are `Result`,
+    /// the error of reading the log is returned as an item.
+    ///
+    /// Returns an error of `InvalidInput` if the changes after `seq` are already truncated.
+    #[inline]
+    pub fn changes_since(&self, seq: u64) -> Result<DbXxxChanges<KT>> {
+        DbXxxChanges::new(self.0.clone(), seq)
+    }
+    /// removes the changes of the sequence number `seq` or less from the log,
+    /// and returns the count of them. the later sequence numbers are not reused.
+    #[inline]
+    pub fn truncate_changes(&mut self, seq: u64) -> Result<u64> {
+        RefCell::borrow_mut(&self.0).truncate_changes(seq)
+    }
+}
+
+/// change hooks.
+///
+/// the hooks are called synchronously after a change of a key is written,
+/// so a failed operation does not call them. they are called for the same changes
+/// as the change log, see `FileDbParams::change_log`. the hooks are shared by all
+/// handles of this map that are got from `FileDb`.
+///
+/// the map is borrowed while the hooks are called, so a hook must not access
+/// this map, it panics. a hook can change the other maps.
+impl<KT: DbMapKeyType> FileDbMap<KT> {
+    /// Registers the hook that is called with the key, the old value and the new value
+    /// after the key is inserted or its value is changed.
+    /// the old value is `None` if the key is new or expired.
+    #[inline]
+    pub fn on_put<F>(&mut self, hook: F)
+    where
+        F: FnMut(&KT, Option<&[u8]>, &[u8]) + 'static,
+    {
+        RefCell::borrow_mut(&self.0).add_put_hook(Box::new(hook))
+    }
+    /// Registers the hook that is called with the key and the old value
+    /// after the key is deleted. the old value is `None` if the key is expired.
+    #[inline]
+    pub fn on_delete<F>(&mut self, hook: F)
+    where
+        F: FnMut(&KT, Option<&[u8]>) + 'static,
+    {
+        RefCell::borrow_mut(&self.0).add_delete_hook(Box::new(hook))
+    }
+    /// Removes all registered hooks.
+    #[inline]
+    pub fn clear_hooks(&mut self) {
+        RefCell::borrow_mut(&self.0).clear_hooks()
+    }
+}
+
+/// runtime metrics
+///
+/// the metrics are counted always, and are shared by all handles of this map
+/// that are got from `FileDb`.
+impl<KT: DbMapKeyType> FileDbMap<KT> {
+    /// Returns the snapshot of the metrics since opening or the last `reset_metrics()`.
+    #[inline]
+    pub fn metrics(&self) -> DbXxxMetrics {
+        RefCell::borrow(&self.0).metrics()
+    }
+    /// Resets all metrics to zero.
+    #[inline]
+    pub fn reset_metrics(&mut self) {
+        RefCell::borrow_mut(&self.0).reset_metrics()
+    }
 }
 
 /// for debug
@@ -119,6 +412,9 @@ impl<KT: DbMapKeyType + std::fmt::Display> CheckFileDbMap for FileDbMap<KT> {
     fn htx_filling_rate_per_mill(&self) -> Result<(u64, u32)> {
         RefCell::borrow(&self.0).htx_filling_rate_per_mill()
     }
+    fn verify(&self) -> Result<VerifyReport> {
+        RefCell::borrow(&self.0).verify()
+    }
 }
 
 impl<KT: DbMapKeyType> DbXxxBase for FileDbMap<KT> {
@@ -161,6 +457,43 @@ impl<KT: DbMapKeyType> DbXxxObjectSafe<KT> for FileDbMap<KT> {
     fn includes_key_kt(&mut self, key: &KT) -> Result<bool> {
         RefCell::borrow_mut(&self.0).includes_key_kt(key)
     }
+    #[inline]
+    fn compare_and_swap_kt(
+        &mut self,
+        key: &KT,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool> {
+        RefCell::borrow_mut(&self.0).compare_and_swap_kt(key, expected, new)
+    }
+    #[inline]
+    fn fetch_add_kt(&mut self, key: &KT, delta: i64) -> Result<i64> {
+        RefCell::borrow_mut(&self.0).fetch_add_kt(key, delta)
+    }
+    #[inline]
+    fn append_kt(&mut self, key: &KT, bytes: &[u8]) -> Result<()> {
+        RefCell::borrow_mut(&self.0).append_kt(key, bytes)
+    }
+    #[inline]
+    fn value_len_kt(&mut self, key: &KT) -> Result<Option<u64>> {
+        RefCell::borrow_mut(&self.0).value_len_kt(key)
+    }
+    #[inline]
+    fn entry_meta_kt(&mut self, key: &KT) -> Result<Option<EntryMeta>> {
+        RefCell::borrow_mut(&self.0).entry_meta_kt(key)
+    }
+    #[inline]
+    fn bulk_get_kt(&mut self, keys: &[&KT]) -> Result<Vec<Option<Vec<u8>>>> {
+        RefCell::borrow_mut(&self.0).bulk_get_kt(keys)
+    }
+    #[inline]
+    fn bulk_put_kt(&mut self, bulk: &[(&KT, &[u8])]) -> Result<()> {
+        RefCell::borrow_mut(&self.0).bulk_put_kt(bulk)
+    }
+    #[inline]
+    fn bulk_delete_kt(&mut self, keys: &[&KT]) -> Result<Vec<Option<Vec<u8>>>> {
+        RefCell::borrow_mut(&self.0).bulk_delete_kt(keys)
+    }
 }
 
 impl<KT: DbMapKeyType> DbXxx<KT> for FileDbMap<KT> {}
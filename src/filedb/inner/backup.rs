@@ -0,0 +1,236 @@
+use super::FileDbInner;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Result, Write};
+use std::path::Path;
+
+/// The file name of the backup manifest.
+pub(crate) const MANIFEST_NAME: &str = "MANIFEST";
+const MANIFEST_HEADER: &str = "# abyssiniandb backup manifest";
+const DB_FILE_EXTENSIONS: [&str; 5] = ["key", "val", "htx", "idx", "cdc"];
+
+/// An entry of the backup manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ManifestEntry {
+    name: String,
+    size: u64,
+    crc32: u32,
+}
+
+impl FileDbInner {
+    /// flushes all maps, and copies the db files into the `dir` with the manifest.
+    pub fn backup_to(&self, dir: &Path) -> Result<()> {
+        let manifest_path = dir.join(MANIFEST_NAME);
+        if manifest_path.exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("backup already exists: {}", dir.display()),
+            ));
+        }
+        self.applay_all(|o| o.flush())?;
+        std::fs::create_dir_all(dir)?;
+        let mut entries = Vec::new();
+        for name in db_file_names(self.path())? {
+            let (size, crc32) = copy_with_crc32(&self.path().join(&name), &dir.join(&name))?;
+            entries.push(ManifestEntry { name, size, crc32 });
+        }
+        // the manifest is written at last, a backup without it is incomplete.
+        let mut manifest = String::new();
+        manifest.push_str(MANIFEST_HEADER);
+        manifest.push('\n');
+        for entry in &entries {
+            manifest.push_str(&format!(
+                "{}\t{}\t{:08x}\n",
+                entry.name, entry.size, entry.crc32
+            ));
+        }
+        let mut file = File::create(&manifest_path)?;
+        file.write_all(manifest.as_bytes())?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+/// verifies the backup in the `backup_dir`, and copies its db files into the `path`.
+pub(crate) fn restore_files(backup_dir: &Path, path: &Path) -> Result<()> {
+    let entries = read_manifest(&backup_dir.join(MANIFEST_NAME))?;
+    if path.is_dir() && !db_file_names(path)?.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("db files already exist: {}", path.display()),
+        ));
+    }
+    std::fs::create_dir_all(path)?;
+    // copies into the temporary files, and renames them after all are verified.
+    let mut tmp_paths = Vec::new();
+    let mut r = Ok(());
+    for entry in &entries {
+        let tmp_path = path.join(format!("{}.restoring", entry.name));
+        tmp_paths.push(tmp_path.clone());
+        r = copy_with_crc32(&backup_dir.join(&entry.name), &tmp_path).and_then(|(size, crc32)| {
+            if size != entry.size || crc32 != entry.crc32 {
+                Err(invalid_data(format!(
+                    "the file does not match the manifest: {}",
+                    entry.name
+                )))
+            } else {
+                Ok(())
+            }
+        });
+        if r.is_err() {
+            break;
+        }
+    }
+    if r.is_err() {
+        for tmp_path in &tmp_paths {
+            let _ = std::fs::remove_file(tmp_path);
+        }
+        return r;
+    }
+    for (entry, tmp_path) in entries.iter().zip(tmp_paths.iter()) {
+        std::fs::rename(tmp_path, path.join(&entry.name))?;
+    }
+    Ok(())
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+/// returns the sorted names of the db files in the `dir`.
+fn db_file_names(dir: &Path) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for dir_entry in std::fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        if !dir_entry.file_type()?.is_file() {
+            continue;
+        }
+        let path = dir_entry.path();
+        let is_db_file = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| DB_FILE_EXTENSIONS.contains(&ext))
+            .unwrap_or(false);
+        if let (true, Some(name)) = (is_db_file, dir_entry.file_name().to_str()) {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn read_manifest(manifest_path: &Path) -> Result<Vec<ManifestEntry>> {
+    let reader = BufReader::new(File::open(manifest_path)?);
+    let mut entries = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if i == 0 {
+            if line != MANIFEST_HEADER {
+                return Err(invalid_data("invalid manifest header".to_string()));
+            }
+            continue;
+        }
+        let mut fields = line.split('\t');
+        let entry = match (fields.next(), fields.next(), fields.next(), fields.next()) {
+            (Some(name), Some(size), Some(crc32), None) => {
+                match (size.parse::<u64>(), u32::from_str_radix(crc32, 16)) {
+                    (Ok(size), Ok(crc32)) if is_plain_file_name(name) => Some(ManifestEntry {
+                        name: name.to_string(),
+                        size,
+                        crc32,
+                    }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match entry {
+            Some(entry) => entries.push(entry),
+            None => return Err(invalid_data(format!("invalid manifest line: {}", i + 1))),
+        }
+    }
+    if entries.is_empty() {
+        return Err(invalid_data("no entries in the manifest".to_string()));
+    }
+    Ok(entries)
+}
+
+/// the name in the manifest must not point outside of the directory.
+fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+/// copies the file, and returns the size and the CRC-32 of it.
+fn copy_with_crc32(src: &Path, dst: &Path) -> Result<(u64, u32)> {
+    let mut src_file = File::open(src)?;
+    let mut dst_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dst)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    let mut crc = Crc32::new();
+    loop {
+        let n = src_file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        dst_file.write_all(&buf[..n])?;
+        crc.update(&buf[..n]);
+        size += n as u64;
+    }
+    dst_file.sync_all()?;
+    Ok((size, crc.finish()))
+}
+
+/// CRC-32 (IEEE 802.3), the same as of zip and png.
+struct Crc32(u32);
+
+const CRC32_TABLE: [u32; 256] = make_crc32_table();
+
+const fn make_crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+impl Crc32 {
+    fn new() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+    fn update(&mut self, bytes: &[u8]) {
+        let mut c = self.0;
+        for &b in bytes {
+            c = CRC32_TABLE[((c ^ b as u32) & 0xFF) as usize] ^ (c >> 8);
+        }
+        self.0 = c;
+    }
+    fn finish(&self) -> u32 {
+        self.0 ^ 0xFFFF_FFFF
+    }
+}
+
+#[cfg(test)]
+mod debug {
+    use super::Crc32;
+    #[test]
+    fn test_crc32() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+}
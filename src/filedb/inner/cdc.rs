@@ -0,0 +1,548 @@
+use super::super::super::DbMapKeyType;
+use super::super::FileDbParams;
+use super::dbxxx::FileDbXxxInner;
+use super::piece::PieceMgr;
+use super::semtype::*;
+use super::vfile::VarFile;
+#[cfg(feature = "rabuf_stats")]
+use super::vfile::VarFileCounters;
+use rabuf::{SmallRead, SmallWrite};
+use std::cell::RefCell;
+use std::fs::OpenOptions;
+use std::convert::TryInto;
+use std::io::{Read, Result, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+type HeaderSignature = [u8; 8];
+
+const CDC_HEADER_SZ: u64 = 128;
+const CDC_HEADER_SIGNATURE: HeaderSignature = [b'a', b'b', b'y', b's', b'd', b'b', b'C', 0u8];
+/// the byte size of a record without the key: seq, kind and key length.
+const CDC_RECORD_HEAD_SZ: u64 = 8 + 1 + 4;
+
+const CDC_SIZE_FREE_OFFSET: [u64; 0] = [];
+const CDC_SIZE_ARY: [u32; 0] = [];
+
+/// The kind of a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The key is inserted or its value is changed.
+    Put,
+    /// The key is deleted.
+    Delete,
+}
+
+impl ChangeKind {
+    fn to_u8(self) -> u8 {
+        match self {
+            ChangeKind::Put => 1,
+            ChangeKind::Delete => 2,
+        }
+    }
+    fn from_u8(val: u8) -> Result<Self> {
+        match val {
+            1 => Ok(ChangeKind::Put),
+            2 => Ok(ChangeKind::Delete),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid change kind: {val}"),
+            )),
+        }
+    }
+}
+
+/// A change of a key in the change log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbXxxChange<KT: DbMapKeyType> {
+    /// The sequence number, this increases monotonically from 1.
+    pub seq: u64,
+    /// The kind of the change.
+    pub kind: ChangeKind,
+    /// The changed key.
+    pub key: KT,
+}
+
+/// A record of the change log file.
+#[derive(Debug)]
+pub struct CdcRecord {
+    pub seq: u64,
+    pub kind: ChangeKind,
+    pub key: Vec<u8>,
+    /// offset of the next record.
+    pub next_offset: u64,
+}
+
+/// The change log file.
+///
+/// This is the records of the changed keys in sequence order,
+/// after the header. The records before the first sequence number
+/// are removed by truncating.
+///
+/// The next sequence number and the end of the records in the header
+/// are written on flush, the records after them are counted on open.
+#[derive(Debug)]
+pub struct VarFileCdcCache {
+    pub file: VarFile,
+    path: PathBuf,
+    sig2: HeaderSignature,
+    first_seq: u64,
+    next_seq: u64,
+    end_offset: u64,
+    /// the header is older than the records.
+    dirty: bool,
+    /// this is incremented when the records are moved by truncating.
+    epoch: u64,
+    /// the sequence number and the offset of the record that is found last,
+    /// the polling of `changes_since()` restarts the search from here.
+    found: (u64, u64),
+}
+
+#[derive(Debug, Clone)]
+pub struct CdcFile(pub Rc<RefCell<VarFileCdcCache>>);
+
+impl CdcFile {
+    /// returns true if the cdc file exists.
+    pub fn exists<P: AsRef<Path>>(path: P, ks_name: &str) -> bool {
+        let mut pb = path.as_ref().to_path_buf();
+        pb.push(format!("{ks_name}.cdc"));
+        pb.is_file()
+    }
+    pub fn open_with_params<P: AsRef<Path>>(
+        path: P,
+        ks_name: &str,
+        sig2: HeaderSignature,
+        _params: &FileDbParams,
+    ) -> Result<Self> {
+        let mut pb = path.as_ref().to_path_buf();
+        pb.push(format!("{ks_name}.cdc"));
+        // the new log of the truncating that is not renamed.
+        let _ = std::fs::remove_file(tmp_path(&pb));
+        let std_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&pb)?;
+        let mut file = new_cdc_file(std_file)?;
+        let file_length: NodePieceOffset = file.seek_to_end()?;
+        if file_length.is_zero() {
+            write_cdcf_init_header(&mut file, sig2)?;
+        } else {
+            check_cdcf_header(&mut file, sig2)?;
+        }
+        let first_seq = file.read_cdc_first_seq()?;
+        let (next_seq, end_offset) = recover_records(&mut file)?;
+        Ok(Self(Rc::new(RefCell::new(VarFileCdcCache {
+            file,
+            path: pb,
+            sig2,
+            first_seq,
+            next_seq,
+            end_offset,
+            dirty: false,
+            epoch: 0,
+            found: (first_seq, CDC_HEADER_SZ),
+        }))))
+    }
+    #[inline]
+    pub fn read_fill_buffer(&self) -> Result<()> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.file.read_fill_buffer()
+    }
+    #[inline]
+    pub fn flush(&self) -> Result<()> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.write_header_tail()?;
+        locked.file.flush()
+    }
+    #[inline]
+    pub fn sync_all(&self) -> Result<()> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.write_header_tail()?;
+        locked.file.sync_all()
+    }
+    #[inline]
+    pub fn sync_data(&self) -> Result<()> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.write_header_tail()?;
+        locked.file.sync_data()
+    }
+    #[cfg(feature = "rabuf_stats")]
+    #[inline]
+    pub fn counters(&self) -> VarFileCounters {
+        RefCell::borrow(&self.0).file.counters
+    }
+    /// returns the sequence number of the last change, zero is no change.
+    #[inline]
+    pub fn last_seq(&self) -> u64 {
+        RefCell::borrow(&self.0).next_seq - 1
+    }
+    #[inline]
+    pub fn first_seq(&self) -> u64 {
+        RefCell::borrow(&self.0).first_seq
+    }
+    #[inline]
+    pub fn epoch(&self) -> u64 {
+        RefCell::borrow(&self.0).epoch
+    }
+    /// appends the change of the key, and returns its sequence number.
+    pub fn append(&self, kind: ChangeKind, key: &[u8]) -> Result<u64> {
+        let key_len: u32 = key.len().try_into().map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("the key is too large for the change log: {}", key.len()),
+            )
+        })?;
+        let mut locked = RefCell::borrow_mut(&self.0);
+        let seq = locked.next_seq;
+        let offset = locked.end_offset;
+        let file = &mut locked.file;
+        file.seek_from_start(NodePieceOffset::new(offset))?;
+        file.write_u64_le(seq)?;
+        file.write_u8(kind.to_u8())?;
+        file.write_u32_le(key_len)?;
+        file.write_all(key)?;
+        locked.next_seq = seq + 1;
+        locked.end_offset = offset + CDC_RECORD_HEAD_SZ + key_len as u64;
+        locked.dirty = true;
+        Ok(seq)
+    }
+    /// reads the record at the offset. returns `None` at the end of the records.
+    pub fn read_record(&self, offset: u64) -> Result<Option<CdcRecord>> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        if offset >= locked.end_offset {
+            return Ok(None);
+        }
+        let file = &mut locked.file;
+        file.seek_from_start(NodePieceOffset::new(offset))?;
+        let seq = file.read_u64_le()?;
+        let kind = ChangeKind::from_u8(file.read_u8()?)?;
+        let key_len = file.read_u32_le()?;
+        let mut key = vec![0u8; key_len as usize];
+        file.read_exact(&mut key)?;
+        Ok(Some(CdcRecord {
+            seq,
+            kind,
+            key,
+            next_offset: offset + CDC_RECORD_HEAD_SZ + key_len as u64,
+        }))
+    }
+    /// returns the offset of the first record that has the sequence number `seq` or more.
+    pub fn find_offset(&self, seq: u64) -> Result<u64> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        // the records are in sequence order, the next record of the end is `next_seq`.
+        let (found_seq, found_offset) = locked.found;
+        let mut offset = if found_seq <= seq {
+            found_offset
+        } else {
+            CDC_HEADER_SZ
+        };
+        loop {
+            if offset >= locked.end_offset {
+                locked.found = (locked.next_seq, offset);
+                return Ok(offset);
+            }
+            let file = &mut locked.file;
+            file.seek_from_start(NodePieceOffset::new(offset))?;
+            let rec_seq = file.read_u64_le()?;
+            let _kind = file.read_u8()?;
+            let key_len = file.read_u32_le()?;
+            if rec_seq >= seq {
+                locked.found = (rec_seq, offset);
+                return Ok(offset);
+            }
+            offset += CDC_RECORD_HEAD_SZ + key_len as u64;
+        }
+    }
+    /// removes the records that have the sequence number `seq` or less,
+    /// and returns the count of them.
+    pub fn truncate(&self, seq: u64) -> Result<u64> {
+        let first_seq = self.first_seq();
+        let last_seq = self.last_seq();
+        if seq < first_seq || last_seq < first_seq {
+            return Ok(0);
+        }
+        let seq = seq.min(last_seq);
+        let src_offset = self.find_offset(seq + 1)?;
+        let mut locked = RefCell::borrow_mut(&self.0);
+        let locked = &mut *locked;
+        // the remaining records are written to the new log, and it is renamed
+        // over the old log, so a crash leaves the old or the new log.
+        let tmp_path = tmp_path(&locked.path);
+        let result = locked.write_truncated(&tmp_path, seq + 1, src_offset);
+        let (new_file, end_offset) = match result {
+            Ok(a) => a,
+            Err(err) => {
+                let _ = std::fs::remove_file(&tmp_path);
+                return Err(err);
+            }
+        };
+        std::fs::rename(&tmp_path, &locked.path)?;
+        locked.file = new_file;
+        locked.first_seq = seq + 1;
+        locked.end_offset = end_offset;
+        locked.dirty = false;
+        locked.epoch += 1;
+        locked.found = (seq + 1, CDC_HEADER_SZ);
+        Ok(seq + 1 - first_seq)
+    }
+}
+
+impl VarFileCdcCache {
+    /// writes the next sequence number and the end of the records to the header.
+    fn write_header_tail(&mut self) -> Result<()> {
+        if self.dirty {
+            self.file.write_cdc_next_seq(self.next_seq)?;
+            self.file.write_cdc_end_offset(self.end_offset)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+    /// writes the new log that has the records from `src_offset`,
+    /// and returns it and the end of the records.
+    fn write_truncated(
+        &mut self,
+        tmp_path: &Path,
+        first_seq: u64,
+        src_offset: u64,
+    ) -> Result<(VarFile, u64)> {
+        let std_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(tmp_path)?;
+        let mut new_file = new_cdc_file(std_file)?;
+        write_cdcf_init_header(&mut new_file, self.sig2)?;
+        let mut buf = vec![0u8; 4 * 1024];
+        let mut src = src_offset;
+        let mut dst = CDC_HEADER_SZ;
+        while src < self.end_offset {
+            let len = buf.len().min((self.end_offset - src) as usize);
+            self.file.seek_from_start(NodePieceOffset::new(src))?;
+            self.file.read_exact(&mut buf[..len])?;
+            new_file.seek_from_start(NodePieceOffset::new(dst))?;
+            new_file.write_all(&buf[..len])?;
+            src += len as u64;
+            dst += len as u64;
+        }
+        new_file.write_cdc_first_seq(first_seq)?;
+        new_file.write_cdc_next_seq(self.next_seq)?;
+        new_file.write_cdc_end_offset(dst)?;
+        new_file.flush()?;
+        new_file.sync_all()?;
+        Ok((new_file, dst))
+    }
+}
+
+fn new_cdc_file(std_file: std::fs::File) -> Result<VarFile> {
+    let piece_mgr = PieceMgr::new(&CDC_SIZE_FREE_OFFSET, &CDC_SIZE_ARY);
+    VarFile::new(piece_mgr, "cdc", std_file)
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+/// reads the records after the end in the header, they are appended after the last flush,
+/// and returns the next sequence number and the end of the records.
+/// the broken record at the end is removed.
+fn recover_records(file: &mut VarFile) -> Result<(u64, u64)> {
+    let mut next_seq = file.read_cdc_next_seq()?;
+    let mut offset = file.read_cdc_end_offset()?;
+    let file_length: NodePieceOffset = file.seek_to_end()?;
+    let file_length = file_length.as_value();
+    if offset < CDC_HEADER_SZ || offset > file_length {
+        return Err(invalid_data(format!("invalid end of the records: {offset}")));
+    }
+    while offset + CDC_RECORD_HEAD_SZ <= file_length {
+        file.seek_from_start(NodePieceOffset::new(offset))?;
+        let seq = file.read_u64_le()?;
+        let _kind = file.read_u8()?;
+        let key_len = file.read_u32_le()?;
+        let next_offset = offset + CDC_RECORD_HEAD_SZ + key_len as u64;
+        if seq != next_seq || next_offset > file_length {
+            break;
+        }
+        next_seq += 1;
+        offset = next_offset;
+    }
+    if offset < file_length {
+        file.set_file_length(NodePieceOffset::new(offset))?;
+    }
+    Ok((next_seq, offset))
+}
+
+/**
+write initiale header to file.
+
+## header map
+
+The cdc header size is 128 bytes.
+
+```text
++--------+-------+-------------+---------------------------+
+| offset | bytes | name        | comment                   |
++--------+-------+-------------+---------------------------+
+| 0      | 8     | signature1  | b"abysdbC\0"              |
+| 8      | 8     | signature2  | 8 bytes type signature    |
+| 16     | 8     | first seq   | seq of the first record   |
+| 24     | 8     | next seq    | seq of the next record    |
+| 32     | 8     | end         | offset of the end         |
+| 40     | 88    | reserve1    |                           |
++--------+-------+-------------+---------------------------+
+```
+
+- signature1: always fixed 8 bytes
+- signature2: 8 bytes type signature
+- next seq and end: these are written on flush
+
+## record
+
+```text
+seq: u64 le, kind: u8, key length: u32 le, key
+```
+
+*/
+const CDC_FIRST_SEQ_OFFSET: u64 = 16;
+const CDC_NEXT_SEQ_OFFSET: u64 = 24;
+const CDC_END_OFFSET: u64 = 32;
+
+fn write_cdcf_init_header(file: &mut VarFile, signature2: HeaderSignature) -> Result<()> {
+    file.seek_from_start(NodePieceOffset::new(0))?;
+    // signature1
+    file.write_all(&CDC_HEADER_SIGNATURE)?;
+    // signature2
+    file.write_all(&signature2)?;
+    // first seq
+    file.write_u64_le(1)?;
+    // next seq
+    file.write_u64_le(1)?;
+    // end
+    file.write_u64_le(CDC_HEADER_SZ)?;
+    // rserve1
+    file.write_all(&[0u8; 88])?;
+    //
+    Ok(())
+}
+
+fn check_cdcf_header(file: &mut VarFile, signature2: HeaderSignature) -> Result<()> {
+    file.seek_from_start(NodePieceOffset::new(0))?;
+    // signature1
+    let mut sig1 = [0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8];
+    file.read_exact(&mut sig1)?;
+    if sig1 != CDC_HEADER_SIGNATURE {
+        return Err(invalid_data("invalid header signature1".to_string()));
+    }
+    // signature2
+    let mut sig2 = [0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8];
+    file.read_exact(&mut sig2)?;
+    if sig2 != signature2 {
+        return Err(invalid_data(format!(
+            "invalid header signature2, type signature: {sig2:?}"
+        )));
+    }
+    //
+    Ok(())
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+impl VarFile {
+    fn read_cdc_first_seq(&mut self) -> Result<u64> {
+        self.seek_from_start(NodePieceOffset::new(CDC_FIRST_SEQ_OFFSET))?;
+        self.read_u64_le()
+    }
+    fn write_cdc_first_seq(&mut self, val: u64) -> Result<()> {
+        self.seek_from_start(NodePieceOffset::new(CDC_FIRST_SEQ_OFFSET))?;
+        self.write_u64_le(val)
+    }
+    fn read_cdc_next_seq(&mut self) -> Result<u64> {
+        self.seek_from_start(NodePieceOffset::new(CDC_NEXT_SEQ_OFFSET))?;
+        self.read_u64_le()
+    }
+    fn write_cdc_next_seq(&mut self, val: u64) -> Result<()> {
+        self.seek_from_start(NodePieceOffset::new(CDC_NEXT_SEQ_OFFSET))?;
+        self.write_u64_le(val)
+    }
+    fn read_cdc_end_offset(&mut self) -> Result<u64> {
+        self.seek_from_start(NodePieceOffset::new(CDC_END_OFFSET))?;
+        self.read_u64_le()
+    }
+    fn write_cdc_end_offset(&mut self, val: u64) -> Result<()> {
+        self.seek_from_start(NodePieceOffset::new(CDC_END_OFFSET))?;
+        self.write_u64_le(val)
+    }
+}
+
+/// An iterator over the changes in the change log, in sequence order.
+///
+/// This is constructed from the `changes_since()` method on `FileDbMap`.
+/// The error of reading the log is returned as an item.
+/// This returns `None` at the end of the log, and it can be polled again
+/// to tail the changes appended later. If the log is truncated beyond
+/// this position, this resumes from the first remaining change,
+/// and the gap is seen in `DbXxxChange::seq`.
+#[derive(Debug)]
+pub struct DbXxxChanges<KT: DbMapKeyType> {
+    db_map: Rc<RefCell<FileDbXxxInner<KT>>>,
+    next_seq: u64,
+    offset: u64,
+    epoch: u64,
+}
+
+impl<KT: DbMapKeyType> DbXxxChanges<KT> {
+    pub(crate) fn new(db_map: Rc<RefCell<FileDbXxxInner<KT>>>, seq: u64) -> Result<Self> {
+        let (offset, epoch) = {
+            let db_map_inner = RefCell::borrow(&db_map);
+            let cdc_file = db_map_inner.change_log()?;
+            if seq + 1 < cdc_file.first_seq() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("the changes since {seq} are truncated"),
+                ));
+            }
+            (cdc_file.find_offset(seq + 1)?, cdc_file.epoch())
+        };
+        Ok(Self {
+            db_map,
+            next_seq: seq + 1,
+            offset,
+            epoch,
+        })
+    }
+    fn next_change(&mut self) -> Result<Option<DbXxxChange<KT>>> {
+        let db_map_inner = RefCell::borrow(&self.db_map);
+        let cdc_file = db_map_inner.change_log()?;
+        if self.epoch != cdc_file.epoch() {
+            // the records are moved by truncating.
+            self.offset = cdc_file.find_offset(self.next_seq)?;
+            self.epoch = cdc_file.epoch();
+        }
+        match cdc_file.read_record(self.offset)? {
+            Some(record) => {
+                self.offset = record.next_offset;
+                self.next_seq = record.seq + 1;
+                Ok(Some(DbXxxChange {
+                    seq: record.seq,
+                    kind: record.kind,
+                    key: KT::from_bytes(&record.key),
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl<KT: DbMapKeyType> Iterator for DbXxxChanges<KT> {
+    type Item = Result<DbXxxChange<KT>>;
+    #[inline]
+    fn next(&mut self) -> Option<Result<DbXxxChange<KT>>> {
+        self.next_change().transpose()
+    }
+}
@@ -1,6 +1,7 @@
-use super::super::super::{DbMapKeyType, DbXxxBase, DbXxxObjectSafe};
+use super::super::super::{DbMapKeyType, DbXxxBase, DbXxxObjectSafe, EntryMeta, MergeOperator};
 use super::super::{
     CheckFileDbMap, CountOfPerSize, FileDbParams, KeysCountStats, LengthStats, RecordSizeStats,
+    RepairReport, VerifyReport,
 };
 use super::_cold;
 use super::key::KeyPieceOffsetIter;
@@ -8,10 +9,14 @@ use super::semtype::*;
 use super::val::ValuePieceOffsetIter;
 use super::{key, val};
 use std::cell::RefCell;
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashSet;
 use std::io::Result;
+use std::iter::FusedIterator;
+use std::ops::{Bound, RangeBounds};
 use std::path::Path;
 use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 //#[cfg(all(
 //    feature = "idx_find_uu",
@@ -19,19 +24,111 @@ use std::rc::Rc;
 //))]
 //use rabuf::SmallRead;
 
+use super::cdc::{self, ChangeKind};
 use super::htx;
+use super::idx;
 
 #[derive(Debug)]
 pub struct FileDbXxxInner<KT: DbMapKeyType> {
     dirty: bool,
+    generation: u64,
+    value_generation: u64,
     //
     key_file: key::KeyFile<KT>,
     val_file: val::ValueFile,
     htx_file: htx::HtxFile,
+    idx_file: Option<idx::IdxFile<KT>>,
+    cdc_file: Option<cdc::CdcFile>,
+    merge_operator: Option<MergeOperatorRc>,
+    hooks: Hooks<KT>,
+    metrics: DbXxxMetrics,
     //
     _phantom: std::marker::PhantomData<KT>,
 }
 
+/// the registered merge operator.
+#[derive(Clone)]
+struct MergeOperatorRc(Rc<dyn MergeOperator>);
+
+impl std::fmt::Debug for MergeOperatorRc {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("MergeOperator")
+    }
+}
+
+type PutHook<KT> = dyn FnMut(&KT, Option<&[u8]>, &[u8]);
+type DeleteHook<KT> = dyn FnMut(&KT, Option<&[u8]>);
+
+/// the registered change hooks.
+struct Hooks<KT: DbMapKeyType> {
+    put: Vec<RefCell<Box<PutHook<KT>>>>,
+    delete: Vec<RefCell<Box<DeleteHook<KT>>>>,
+}
+
+impl<KT: DbMapKeyType> Hooks<KT> {
+    fn new() -> Self {
+        Self {
+            put: Vec::new(),
+            delete: Vec::new(),
+        }
+    }
+    #[inline]
+    fn fire_put(&self, key: &KT, old: Option<&[u8]>, new: &[u8]) {
+        for hook in &self.put {
+            (RefCell::borrow_mut(hook))(key, old, new);
+        }
+    }
+    #[inline]
+    fn fire_delete(&self, key: &KT, old: Option<&[u8]>) {
+        for hook in &self.delete {
+            (RefCell::borrow_mut(hook))(key, old);
+        }
+    }
+}
+
+impl<KT: DbMapKeyType> std::fmt::Debug for Hooks<KT> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter
+            .debug_struct("Hooks")
+            .field("put", &self.put.len())
+            .field("delete", &self.delete.len())
+            .finish()
+    }
+}
+
+/// The runtime metrics of a map, see `FileDbMap::metrics()`.
+///
+/// The counters are kept in memory, and start from zero at opening.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DbXxxMetrics {
+    /// count of the key lookups.
+    pub get_count: u64,
+    /// count of the stored values.
+    pub put_count: u64,
+    /// count of the deleted keys.
+    pub delete_count: u64,
+    /// count of the created iterators.
+    pub iter_count: u64,
+    /// count of the walks on the bucket chain.
+    pub chain_walks: u64,
+    /// total count of the keys compared on the bucket chain.
+    pub chain_walk_steps: u64,
+    /// the longest walk on the bucket chain.
+    pub chain_walk_max: u64,
+    /// bytes read from the key file.
+    pub key_bytes_read: u64,
+    /// bytes written to the key file.
+    pub key_bytes_written: u64,
+    /// bytes read from the value file.
+    pub value_bytes_read: u64,
+    /// bytes written to the value file.
+    pub value_bytes_written: u64,
+    /// count of the pieces that are moved to grow.
+    pub relocations: u64,
+    /// count of the pieces that are allocated from the free list.
+    pub free_list_reuses: u64,
+}
+
 impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
     pub(crate) fn open_with_params<P: AsRef<Path>>(
         path: P,
@@ -41,19 +138,66 @@ impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
         let key_file = key::KeyFile::open_with_params(&path, ks_name, KT::signature(), &params)?;
         let val_file = val::ValueFile::open_with_params(&path, ks_name, KT::signature(), &params)?;
         let htx_file = htx::HtxFile::open_with_params(&path, ks_name, KT::signature(), &params)?;
+        let (idx_file, idx_is_new) =
+            if params.ordered_index || idx::IdxFile::<KT>::exists(&path, ks_name) {
+                let (idx_file, is_new) =
+                    idx::IdxFile::open_with_params(&path, ks_name, KT::signature(), &params)?;
+                (Some(idx_file), is_new)
+            } else {
+                (None, false)
+            };
+        let cdc_file = if params.change_log || cdc::CdcFile::exists(&path, ks_name) {
+            Some(cdc::CdcFile::open_with_params(
+                &path,
+                ks_name,
+                KT::signature(),
+                &params,
+            )?)
+        } else {
+            None
+        };
         //
-        Ok(Self {
+        let mut r = Self {
             key_file,
             val_file,
             htx_file,
+            idx_file,
+            cdc_file,
+            merge_operator: None,
+            hooks: Hooks::new(),
+            metrics: DbXxxMetrics::default(),
             dirty: false,
+            generation: 0,
+            value_generation: 0,
             _phantom: std::marker::PhantomData,
-        })
+        };
+        if idx_is_new && r.len()? > 0 {
+            // the map is existing, but the ordered index is created now.
+            r.rebuild_ordered_index()?;
+            r.dirty = true;
+        }
+        // the header accesses at opening are not counted.
+        r.reset_metrics();
+        Ok(r)
     }
     #[inline]
     pub fn is_dirty(&self) -> bool {
         self.dirty
     }
+    /// the modification generation, this is counted up when a key is inserted or deleted.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+    /// the value generation, this is counted up when a value is replaced or a key is deleted.
+    #[inline]
+    pub fn value_generation(&self) -> u64 {
+        self.value_generation
+    }
+    #[inline]
+    pub fn has_ordered_index(&self) -> bool {
+        self.idx_file.is_some()
+    }
     /*
     fn key_piece_offset_iter(&self) -> DbXxxKeyPieceOffsetIter<KT> {
         DbXxxKeyPieceOffsetIter::new(self).unwrap()
@@ -88,7 +232,7 @@ impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
     }
     //
     #[inline]
-    fn load_value(&self, piece_offset: KeyPieceOffset) -> Result<Vec<u8>> {
+    pub(crate) fn load_value(&self, piece_offset: KeyPieceOffset) -> Result<Vec<u8>> {
         debug_assert!(!piece_offset.is_zero());
         let value_offset = self.key_file.read_piece_only_value_offset(piece_offset)?;
         self.val_file.read_piece_only_value(value_offset)
@@ -117,15 +261,29 @@ impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
 
 // insert: NEW
 impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    /// stores the value of the key.
+    /// the expiry time is kept if `expire_at` is `None`, zero is never.
     #[inline]
     fn store_value_on_insert(
         &mut self,
         piece_offset: KeyPieceOffset,
         value: &[u8],
+        expire_at: Option<u64>,
     ) -> Result<KeyPieceOffset> {
+        self.dirty = true;
         let mut key_piece = self.key_file.read_piece(piece_offset)?;
         let mut val_piece = self.val_file.read_piece(key_piece.value_offset)?;
+        let old_value = if self.hooks.put.is_empty()
+            || (val_piece.expire_at != 0 && val_piece.expire_at <= now_millis())
+        {
+            None
+        } else {
+            Some(std::mem::take(&mut val_piece.value))
+        };
         val_piece.value = value.to_vec();
+        if let Some(expire_at) = expire_at {
+            val_piece.expire_at = expire_at;
+        }
         let new_value_piece = self.val_file.write_piece(val_piece)?;
         let new_key_piece = if key_piece.value_offset == new_value_piece.offset {
             key_piece
@@ -134,26 +292,119 @@ impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
             key_piece.value_offset = new_value_piece.offset;
             self.key_file.write_piece(key_piece)?
         };
+        self.metrics.put_count += 1;
+        self.log_change(ChangeKind::Put, &new_key_piece.key)?;
+        self.hooks
+            .fire_put(&new_key_piece.key, old_value.as_deref(), value);
+        self.value_generation += 1;
         Ok(new_key_piece.offset)
     }
+    /// stores the value of the key that must stay at `key_offset`.
+    /// the hash bucket chain and the ordered index are not relinked,
+    /// so the moved key piece is an error.
+    pub(crate) fn store_value_in_place(
+        &mut self,
+        key_offset: KeyPieceOffset,
+        value: &[u8],
+        expire_at: Option<u64>,
+    ) -> Result<()> {
+        let new_key_offset = self.store_value_on_insert(key_offset, value, expire_at)?;
+        check_key_in_place(key_offset, new_key_offset)
+    }
+    /// writes the key piece that must stay at its offset.
+    fn write_key_piece_in_place(&mut self, key_piece: key::KeyPiece<KT>) -> Result<()> {
+        let key_offset = key_piece.offset;
+        let new_key_piece = self.key_file.write_piece(key_piece)?;
+        check_key_in_place(key_offset, new_key_piece.offset)
+    }
 }
 
-// delete: NEW
-impl<KT: DbMapKeyType> FileDbXxxInner<KT> {}
+fn check_key_in_place(key_offset: KeyPieceOffset, new_key_offset: KeyPieceOffset) -> Result<()> {
+    if key_offset == new_key_offset {
+        Ok(())
+    } else {
+        _cold();
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!(
+                "the key piece moved: {} -> {}",
+                key_offset.as_value(),
+                new_key_offset.as_value()
+            ),
+        ))
+    }
+}
+
+// insert a new key
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    /// adds the new key at the head of the bucket chain.
+    pub(crate) fn insert_new_kt(
+        &mut self,
+        hash: HashValue,
+        key_kt: &KT,
+        value: &[u8],
+    ) -> Result<()> {
+        let new_val_piece = self.val_file.add_value_piece(value)?;
+        self.insert_new_kt_with_value_offset(hash, key_kt, new_val_piece.offset)
+    }
+    /// adds the new key that expires at `expire_at`.
+    fn insert_new_kt_with_expire(
+        &mut self,
+        hash: HashValue,
+        key_kt: &KT,
+        value: &[u8],
+        expire_at: u64,
+    ) -> Result<()> {
+        let new_val_piece = self
+            .val_file
+            .add_value_piece_with_expire(value, expire_at)?;
+        self.insert_new_kt_with_value_offset(hash, key_kt, new_val_piece.offset)
+    }
+    /// adds the new key that has the already written value piece.
+    fn insert_new_kt_with_value_offset(
+        &mut self,
+        hash: HashValue,
+        key_kt: &KT,
+        value_offset: ValuePieceOffset,
+    ) -> Result<()> {
+        self.dirty = true;
+        let bucket_next_offset = self.htx_file.read_key_piece_offset(hash)?;
+        let new_key_piece =
+            self.key_file
+                .add_key_piece(key_kt, value_offset, bucket_next_offset)?;
+        self.htx_file
+            .write_key_piece_offset(hash, new_key_piece.offset)?;
+        self.htx_file.write_item_count_up()?;
+        if let Some(idx_file) = &self.idx_file {
+            idx_file.insert(key_kt, new_key_piece.offset, &self.key_file)?;
+        }
+        self.metrics.put_count += 1;
+        self.log_change(ChangeKind::Put, key_kt)?;
+        if !self.hooks.put.is_empty() {
+            let value = self.val_file.read_piece_only_value(value_offset)?;
+            self.hooks.fire_put(key_kt, None, &value);
+        }
+        self.generation += 1;
+        Ok(())
+    }
+}
 
 // find: NEW
 impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
-    fn find_in_hash_buckets_kt(
+    pub(crate) fn find_in_hash_buckets_kt(
         &mut self,
         hash: HashValue,
         key_kt: &KT,
     ) -> Result<Option<(KeyPieceOffset, KeyPieceOffset)>> {
         let mut prev_key_offset = KeyPieceOffset::new(0);
         let mut key_offset = self.htx_file.read_key_piece_offset(hash)?;
+        let mut steps = 0;
+        let mut found = None;
         if !key_offset.is_zero() {
             let mut locked_key = self.key_file.0.borrow_mut();
             //
             while !key_offset.is_zero() {
+                steps += 1;
                 let flg = {
                     let key_string = locked_key.read_piece_only_key_maybeslice(key_offset)?;
                     match key_kt.cmp_u8(&key_string) {
@@ -163,19 +414,889 @@ impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
                     }
                 };
                 if flg {
-                    #[cfg(feature = "htx_print_hits")]
-                    self.htx_file.set_hits();
-                    return Ok(Some((key_offset, prev_key_offset)));
+                    found = Some((key_offset, prev_key_offset));
+                    break;
                 } else {
                     _cold();
-                    #[cfg(feature = "htx_print_hits")]
-                    self.htx_file.set_miss();
-                    //
                     prev_key_offset = key_offset;
                     key_offset = locked_key.read_piece_only_bucket_next_offset(key_offset)?;
                 }
             }
         }
+        self.metrics.chain_walks += 1;
+        self.metrics.chain_walk_steps += steps;
+        if self.metrics.chain_walk_max < steps {
+            self.metrics.chain_walk_max = steps;
+        }
+        Ok(found)
+    }
+    /// finds the key that is not expired.
+    pub(crate) fn find_unexpired_kt(
+        &mut self,
+        hash: HashValue,
+        key_kt: &KT,
+    ) -> Result<Option<(KeyPieceOffset, KeyPieceOffset)>> {
+        match self.find_in_hash_buckets_kt(hash, key_kt)? {
+            Some((key_offset, prev_key_offset)) => {
+                if self.is_expired(key_offset, now_millis())? {
+                    Ok(None)
+                } else {
+                    Ok(Some((key_offset, prev_key_offset)))
+                }
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+// expiration
+//
+// the expired keys are invisible, but they are not deleted until they are
+// overwritten or purged, so the reading does not modify the map.
+
+/// returns the current time in milliseconds since the unix epoch.
+#[inline]
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    /// returns true if the value of the key is expired at `now`.
+    pub(crate) fn is_expired(&self, key_offset: KeyPieceOffset, now: u64) -> Result<bool> {
+        let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+        let expire_at = self.val_file.read_piece_only_expire_at(value_offset)?;
+        Ok(expire_at != 0 && expire_at <= now)
+    }
+    /// stores the value that expires at `expire_at`.
+    pub(crate) fn put_with_expire_kt(
+        &mut self,
+        key_kt: &KT,
+        value: &[u8],
+        expire_at: u64,
+    ) -> Result<()> {
+        let hash = HashValue::new(key_kt.hash_value());
+        let opt = self.find_in_hash_buckets_kt(hash, key_kt)?;
+        if let Some((key_offset, _prev_key_offset)) = opt {
+            self.store_value_in_place(key_offset, value, Some(expire_at))?;
+        } else {
+            _cold();
+            self.insert_new_kt_with_expire(hash, key_kt, value, expire_at)?;
+        }
+        Ok(())
+    }
+    /// returns the count of the keys that are not expired at `now`.
+    pub(crate) fn count_unexpired(&self, now: u64) -> Result<u64> {
+        let mut count = 0;
+        for key_offset in DbXxxKeyPieceOffsetIter::new(self)? {
+            if !self.is_expired(key_offset, now)? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+    /// calls the function with the key-value pairs that are not expired at `now`,
+    /// in hash bucket order. the function must not access this map.
+    pub(crate) fn for_each_unexpired<F>(&self, now: u64, mut f: F) -> Result<()>
+    where
+        F: FnMut(KT, Vec<u8>) -> Result<()>,
+    {
+        for key_offset in DbXxxKeyPieceOffsetIter::new(self)? {
+            let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+            let opt_value = self
+                .val_file
+                .read_piece_only_value_unexpired(value_offset, now)?;
+            if let Some(value) = opt_value {
+                f(self.load_key_data(key_offset)?, value)?;
+            }
+        }
+        Ok(())
+    }
+    /// deletes the all expired keys, and returns the count of them.
+    pub(crate) fn purge_expired(&mut self) -> Result<u64> {
+        let count_before = self.htx_file.read_item_count()?;
+        self.delete_if(|_key, _value| false, false)?;
+        let count_after = self.htx_file.read_item_count()?;
+        Ok(count_before - count_after)
+    }
+}
+
+// delete
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    /// unlinks the key piece from the bucket chain, and deletes the pieces.
+    fn delete_key_piece(
+        &mut self,
+        hash: HashValue,
+        key_piece: key::KeyPiece<KT>,
+        prev_key_offset: KeyPieceOffset,
+    ) -> Result<()> {
+        self.dirty = true;
+        let key_offset = key_piece.offset;
+        if prev_key_offset.is_zero() {
+            self.htx_file
+                .write_key_piece_offset(hash, key_piece.bucket_next_offset)?;
+        } else {
+            _cold();
+            // changing link of bucket chain.
+            let mut prev_key_piece = self.key_file.read_piece(prev_key_offset)?;
+            prev_key_piece.bucket_next_offset = key_piece.bucket_next_offset;
+            let new_prev_key = self.key_file.write_piece(prev_key_piece)?;
+            if prev_key_offset != new_prev_key.offset {
+                _cold();
+                panic!("prev_key_offset != new_prev_key_offset : in delete_key_piece");
+            }
+        }
+        //
+        if let Some(idx_file) = &self.idx_file {
+            idx_file.remove(&key_piece.key, key_offset, &self.key_file)?;
+        }
+        let old_value = if self.hooks.delete.is_empty() {
+            None
+        } else {
+            self.val_file
+                .read_piece_only_value_unexpired(key_piece.value_offset, now_millis())?
+        };
+        self.val_file.delete_piece(key_piece.value_offset)?;
+        self.key_file.delete_piece(key_offset)?;
+        self.htx_file.write_item_count_down()?;
+        self.metrics.delete_count += 1;
+        self.log_change(ChangeKind::Delete, &key_piece.key)?;
+        self.hooks.fire_delete(&key_piece.key, old_value.as_deref());
+        self.generation += 1;
+        self.value_generation += 1;
+        Ok(())
+    }
+    /// deletes the key that is found by `find_in_hash_buckets_kt()`, and returns its value.
+    pub(crate) fn delete_found_kt(
+        &mut self,
+        hash: HashValue,
+        key_offset: KeyPieceOffset,
+        prev_key_offset: KeyPieceOffset,
+    ) -> Result<Vec<u8>> {
+        let key_piece = self.key_file.read_piece(key_offset)?;
+        let value = self
+            .val_file
+            .read_piece_only_value(key_piece.value_offset)?;
+        self.delete_key_piece(hash, key_piece, prev_key_offset)?;
+        Ok(value)
+    }
+    /// walks the buckets once, and deletes the key-value pairs
+    /// that the predicate returns true.
+    /// the deleted pairs are returned if `collect` is true.
+    /// the expired keys are always deleted without calling the predicate,
+    /// and they are not returned.
+    pub(crate) fn delete_if<F>(&mut self, mut pred: F, collect: bool) -> Result<Vec<(KT, Vec<u8>)>>
+    where
+        F: FnMut(&KT, &[u8]) -> bool,
+    {
+        let mut deleted = Vec::new();
+        let now = now_millis();
+        let buckets_size = self.htx_file.read_hash_buckets_size()?;
+        let mut buckets_idx = 0;
+        while buckets_idx < buckets_size {
+            let (next_idx, head_offset) = {
+                let mut htx_inner = RefCell::borrow_mut(&self.htx_file.0);
+                htx_inner
+                    .file
+                    .next_key_piece_offset(buckets_size, buckets_idx)?
+            };
+            buckets_idx = next_idx;
+            let mut prev_key_offset = KeyPieceOffset::new(0);
+            let mut key_offset = head_offset;
+            while !key_offset.is_zero() {
+                let key_piece = self.key_file.read_piece(key_offset)?;
+                let next_key_offset = key_piece.bucket_next_offset;
+                let opt_value = self
+                    .val_file
+                    .read_piece_only_value_unexpired(key_piece.value_offset, now)?;
+                let value = match opt_value {
+                    Some(value) => value,
+                    None => {
+                        let hash = HashValue::new(key_piece.key.hash_value());
+                        self.delete_key_piece(hash, key_piece, prev_key_offset)?;
+                        key_offset = next_key_offset;
+                        continue;
+                    }
+                };
+                if pred(&key_piece.key, &value) {
+                    let hash = HashValue::new(key_piece.key.hash_value());
+                    let key = if collect {
+                        Some(key_piece.key.clone())
+                    } else {
+                        None
+                    };
+                    self.delete_key_piece(hash, key_piece, prev_key_offset)?;
+                    if let Some(key) = key {
+                        deleted.push((key, value));
+                    }
+                } else {
+                    prev_key_offset = key_offset;
+                }
+                key_offset = next_key_offset;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// the location of the value data of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ValueLocation {
+    key_offset: KeyPieceOffset,
+    value_offset: ValuePieceOffset,
+    data_offset: ValuePieceOffset,
+    pub(crate) len: u64,
+}
+
+// partial value access
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    pub(crate) fn value_location_kt(&mut self, key_kt: &KT) -> Result<Option<ValueLocation>> {
+        self.metrics.get_count += 1;
+        let hash = HashValue::new(key_kt.hash_value());
+        let opt = self.find_unexpired_kt(hash, key_kt)?;
+        if let Some((key_offset, _prev_key_offset)) = opt {
+            let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+            let (data_offset, value_len) =
+                self.val_file.read_piece_only_value_location(value_offset)?;
+            Ok(Some(ValueLocation {
+                key_offset,
+                value_offset,
+                data_offset,
+                len: value_len.as_value() as u64,
+            }))
+        } else {
+            _cold();
+            Ok(None)
+        }
+    }
+    /// reads the value data from `pos` into the buffer.
+    pub(crate) fn read_value_at(
+        &self,
+        loc: &ValueLocation,
+        pos: u64,
+        buf: &mut [u8],
+    ) -> Result<()> {
+        debug_assert!(pos + buf.len() as u64 <= loc.len);
+        let data_offset = ValuePieceOffset::new(loc.data_offset.as_value() + pos);
+        self.val_file.read_value_at(data_offset, buf)
+    }
+    pub(crate) fn get_into_kt(&mut self, key_kt: &KT, buf: &mut Vec<u8>) -> Result<bool> {
+        self.metrics.get_count += 1;
+        let hash = HashValue::new(key_kt.hash_value());
+        let opt = self.find_unexpired_kt(hash, key_kt)?;
+        if let Some((key_offset, _prev_key_offset)) = opt {
+            let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+            self.val_file
+                .read_piece_only_value_into(value_offset, buf)?;
+            Ok(true)
+        } else {
+            _cold();
+            buf.clear();
+            Ok(false)
+        }
+    }
+    pub(crate) fn with_value_kt<R, F>(&mut self, key_kt: &KT, f: F) -> Result<Option<R>>
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        self.metrics.get_count += 1;
+        let hash = HashValue::new(key_kt.hash_value());
+        let opt = self.find_unexpired_kt(hash, key_kt)?;
+        if let Some((key_offset, _prev_key_offset)) = opt {
+            let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+            self.val_file
+                .with_piece_only_value(value_offset, f)
+                .map(Some)
+        } else {
+            _cold();
+            Ok(None)
+        }
+    }
+    pub(crate) fn get_range_kt(
+        &mut self,
+        key_kt: &KT,
+        offset: u64,
+        len: usize,
+    ) -> Result<Option<Vec<u8>>> {
+        let loc = match self.value_location_kt(key_kt)? {
+            Some(loc) => loc,
+            None => return Ok(None),
+        };
+        let start = offset.min(loc.len);
+        let end = start.saturating_add(len as u64).min(loc.len);
+        let mut buf = vec![0u8; (end - start) as usize];
+        self.read_value_at(&loc, start, &mut buf)?;
+        Ok(Some(buf))
+    }
+    /// allocates the value piece that is not linked to a key yet.
+    /// returns the offset of the piece and the offset of its value data.
+    pub(crate) fn alloc_value_piece(
+        &mut self,
+        len: u64,
+    ) -> Result<(ValuePieceOffset, ValuePieceOffset)> {
+        let value_len: u32 = len.try_into().map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("the value length is too large: {len}"),
+            )
+        })?;
+        let value_offset = self
+            .val_file
+            .add_value_piece_with_len(ValueLength::new(value_len))?;
+        let (data_offset, _value_len) =
+            self.val_file.read_piece_only_value_location(value_offset)?;
+        Ok((value_offset, data_offset))
+    }
+    pub(crate) fn write_value_at(&self, data_offset: ValuePieceOffset, buf: &[u8]) -> Result<()> {
+        self.val_file.write_value_at(data_offset, buf)
+    }
+    pub(crate) fn free_value_piece(&self, value_offset: ValuePieceOffset) -> Result<()> {
+        self.val_file.delete_piece(value_offset)?;
+        Ok(())
+    }
+    /// links the allocated value piece to the key, the old value is deleted.
+    pub(crate) fn link_value_piece_kt(
+        &mut self,
+        key_kt: &KT,
+        value_offset: ValuePieceOffset,
+    ) -> Result<()> {
+        self.dirty = true;
+        let hash = HashValue::new(key_kt.hash_value());
+        let opt = self.find_in_hash_buckets_kt(hash, key_kt)?;
+        if let Some((key_offset, _prev_key_offset)) = opt {
+            let mut key_piece = self.key_file.read_piece(key_offset)?;
+            let old_value_offset = key_piece.value_offset;
+            let old_value = if self.hooks.put.is_empty() {
+                None
+            } else {
+                self.val_file
+                    .read_piece_only_value_unexpired(old_value_offset, now_millis())?
+            };
+            key_piece.value_offset = value_offset;
+            self.write_key_piece_in_place(key_piece)?;
+            self.val_file.delete_piece(old_value_offset)?;
+            self.value_generation += 1;
+            self.metrics.put_count += 1;
+            self.log_change(ChangeKind::Put, key_kt)?;
+            if !self.hooks.put.is_empty() {
+                let value = self.val_file.read_piece_only_value(value_offset)?;
+                self.hooks.fire_put(key_kt, old_value.as_deref(), &value);
+            }
+        } else {
+            _cold();
+            self.insert_new_kt_with_value_offset(hash, key_kt, value_offset)?;
+        }
+        Ok(())
+    }
+}
+
+// append and merge
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    #[inline]
+    pub(crate) fn set_merge_operator(&mut self, merge_operator: Rc<dyn MergeOperator>) {
+        self.merge_operator = Some(MergeOperatorRc(merge_operator));
+    }
+    pub(crate) fn merge_kt(&mut self, key_kt: &KT, operand: &[u8]) -> Result<()> {
+        let merge_operator = match &self.merge_operator {
+            Some(op) => op.clone(),
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "the merge operator is not registered",
+                ))
+            }
+        };
+        let hash = HashValue::new(key_kt.hash_value());
+        let opt = self.find_in_hash_buckets_kt(hash, key_kt)?;
+        if let Some((key_offset, _prev_key_offset)) = opt {
+            let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+            let existing = self
+                .val_file
+                .read_piece_only_value_unexpired(value_offset, now_millis())?;
+            let (value, expire_at) = match existing {
+                Some(existing) => (
+                    merge_operator
+                        .0
+                        .merge(key_kt.as_bytes(), Some(&existing), operand),
+                    None,
+                ),
+                None => (
+                    merge_operator.0.merge(key_kt.as_bytes(), None, operand),
+                    Some(0),
+                ),
+            };
+            self.store_value_in_place(key_offset, &value, expire_at)?;
+        } else {
+            _cold();
+            let value = merge_operator.0.merge(key_kt.as_bytes(), None, operand);
+            self.insert_new_kt(hash, key_kt, &value)?;
+        }
+        Ok(())
+    }
+}
+
+// bulk operations
+//
+// the keys are processed in bucket order, so that the accesses to
+// the htx file and the key file are sequential as much as possible.
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    /// returns the hashes of the keys and the indices of them in bucket order.
+    fn bucket_ordered(&self, keys: &[&KT]) -> (Vec<HashValue>, Vec<usize>) {
+        let hashes: Vec<HashValue> = keys
+            .iter()
+            .map(|key| HashValue::new(key.hash_value()))
+            .collect();
+        let buckets: Vec<u64> = hashes
+            .iter()
+            .map(|&hash| self.htx_file.bucket_index(hash))
+            .collect();
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        // the stable sort keeps the caller's order of the same keys.
+        order.sort_by(|&a, &b| {
+            buckets[a]
+                .cmp(&buckets[b])
+                .then_with(|| keys[a].cmp(keys[b]))
+        });
+        (hashes, order)
+    }
+}
+
+/// the bucket index and the indices of the pieces in the chain.
+type BucketChain = (u64, Vec<usize>);
+
+/// the buckets size, the heads of the chains, the links in key file order
+/// and the indices of the duplicated keys.
+type BuildLinks = (
+    u64,
+    Vec<(u64, KeyPieceOffset)>,
+    Vec<(KeyPieceOffset, KeyPieceOffset)>,
+    Vec<usize>,
+);
+
+// bulk build
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    /// groups the pieces of the hash values and the key offsets into the bucket chains.
+    /// returns the chains in bucket order, and the indices of the duplicated keys.
+    ///
+    /// the chain is linked from the larger offset to the smaller offset,
+    /// so the next offset fits in the reserved room.
+    fn bucket_chains(
+        &self,
+        pieces: &[(u64, KeyPieceOffset)],
+        buckets_size: u64,
+    ) -> Result<(Vec<BucketChain>, Vec<usize>)> {
+        let mut order: Vec<usize> = (0..pieces.len()).collect();
+        order.sort_unstable_by_key(|&i| (pieces[i].0 % buckets_size, Reverse(pieces[i].1)));
+        let mut chains = Vec::new();
+        let mut duplicates = Vec::new();
+        let mut start = 0;
+        while start < order.len() {
+            let bucket = pieces[order[start]].0 % buckets_size;
+            let mut end = start + 1;
+            while end < order.len() && pieces[order[end]].0 % buckets_size == bucket {
+                end += 1;
+            }
+            let mut chain: Vec<usize> = Vec::with_capacity(end - start);
+            for &i in &order[start..end] {
+                // the same keys have the same hash value, the last written one wins.
+                let mut found = None;
+                for (n, &c) in chain.iter().enumerate() {
+                    if pieces[c].0 == pieces[i].0
+                        && self.load_key_data(pieces[c].1)? == self.load_key_data(pieces[i].1)?
+                    {
+                        found = Some(n);
+                        break;
+                    }
+                }
+                match found {
+                    Some(n) if chain[n] < i => {
+                        duplicates.push(chain[n]);
+                        chain[n] = i;
+                    }
+                    Some(_) => duplicates.push(i),
+                    None => chain.push(i),
+                }
+            }
+            chain.sort_unstable_by_key(|&i| Reverse(pieces[i].1));
+            chains.push((bucket, chain));
+            start = end;
+        }
+        Ok((chains, duplicates))
+    }
+    /// writes the value and key pieces, and pushes their offsets.
+    fn write_build_pieces<T>(
+        &mut self,
+        iter: T,
+        pieces: &mut Vec<(u64, KeyPieceOffset)>,
+        value_offsets: &mut Vec<ValuePieceOffset>,
+    ) -> Result<()>
+    where
+        T: Iterator<Item = (KT, Vec<u8>)>,
+    {
+        for (key, value) in iter {
+            let val_piece = self.val_file.add_value_piece(&value)?;
+            value_offsets.push(val_piece.offset);
+            let key_piece = self
+                .key_file
+                .add_key_piece_with_reserved_next(&key, val_piece.offset)?;
+            pieces.push((key.hash_value(), key_piece.offset));
+        }
+        Ok(())
+    }
+    /// links the bucket chains of the written pieces.
+    fn link_build_pieces(&mut self, pieces: &[(u64, KeyPieceOffset)]) -> Result<BuildLinks> {
+        let buckets_size = htx::capacity_to_buckets_size(pieces.len() as u64);
+        let (chains, duplicates) = self.bucket_chains(pieces, buckets_size)?;
+        let mut heads = Vec::with_capacity(chains.len());
+        let mut links = Vec::with_capacity(pieces.len());
+        for (bucket, chain) in &chains {
+            heads.push((*bucket, pieces[chain[0]].1));
+            for (n, &i) in chain.iter().enumerate() {
+                let next = match chain.get(n + 1) {
+                    Some(&j) => pieces[j].1,
+                    None => KeyPieceOffset::new(0),
+                };
+                links.push((pieces[i].1, next));
+            }
+        }
+        // writes the bucket next offsets in key file order.
+        links.sort_unstable();
+        for &(key_offset, next_offset) in &links {
+            let mut key_piece = self.key_file.read_piece(key_offset)?;
+            key_piece.bucket_next_offset = next_offset;
+            self.write_key_piece_in_place(key_piece)?;
+        }
+        Ok((buckets_size, heads, links, duplicates))
+    }
+    /// frees the pieces written before the error, and returns the error.
+    /// the map is kept empty, because the hash table is not written yet.
+    fn free_build_pieces(
+        &mut self,
+        pieces: &[(u64, KeyPieceOffset)],
+        value_offsets: &[ValuePieceOffset],
+        err: std::io::Error,
+    ) -> std::io::Error {
+        for &(_hash, key_offset) in pieces {
+            let _ = self.key_file.delete_piece(key_offset);
+        }
+        for &value_offset in value_offsets {
+            let _ = self.val_file.delete_piece(value_offset);
+        }
+        err
+    }
+    /// builds the empty map from the key-value pairs, and returns the count of them.
+    ///
+    /// the values and the keys are written sequentially, and then the bucket chains
+    /// are linked in key file order, and the hash table is written in one pass.
+    pub(crate) fn build_from_iter<T>(&mut self, iter: T) -> Result<u64>
+    where
+        T: Iterator<Item = (KT, Vec<u8>)>,
+    {
+        if self.htx_file.read_item_count()? != 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the map is not empty",
+            ));
+        }
+        self.dirty = true;
+        // the hash values and the key offsets in written order.
+        let mut pieces: Vec<(u64, KeyPieceOffset)> = Vec::new();
+        let mut value_offsets: Vec<ValuePieceOffset> = Vec::new();
+        if let Err(err) = self.write_build_pieces(iter, &mut pieces, &mut value_offsets) {
+            return Err(self.free_build_pieces(&pieces, &value_offsets, err));
+        }
+        if pieces.is_empty() {
+            return Ok(0);
+        }
+        let (buckets_size, heads, links, duplicates) = match self.link_build_pieces(&pieces) {
+            Ok(build_links) => build_links,
+            Err(err) => return Err(self.free_build_pieces(&pieces, &value_offsets, err)),
+        };
+        // the hash table is the commit point, the duplicated keys are freed after it.
+        let item_count = links.len() as u64;
+        self.htx_file.rebuild(buckets_size, &heads, item_count)?;
+        for i in duplicates {
+            self.key_file.delete_piece(pieces[i].1)?;
+            self.val_file.delete_piece(value_offsets[i])?;
+        }
+        if self.idx_file.is_some() {
+            self.rebuild_ordered_index()?;
+        }
+        self.metrics.put_count += item_count;
+        if self.cdc_file.is_some() || !self.hooks.put.is_empty() {
+            for &(key_offset, _next_offset) in &links {
+                let key = self.load_key_data(key_offset)?;
+                self.log_change(ChangeKind::Put, &key)?;
+                if !self.hooks.put.is_empty() {
+                    let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+                    let value = self.val_file.read_piece_only_value(value_offset)?;
+                    self.hooks.fire_put(&key, None, &value);
+                }
+            }
+        }
+        self.generation += 1;
+        self.value_generation += 1;
+        Ok(item_count)
+    }
+}
+
+// change log
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    #[inline]
+    pub fn has_change_log(&self) -> bool {
+        self.cdc_file.is_some()
+    }
+    pub(crate) fn change_log(&self) -> Result<&cdc::CdcFile> {
+        match &self.cdc_file {
+            Some(cdc_file) => Ok(cdc_file),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "the change log is not enabled on this map",
+            )),
+        }
+    }
+    /// appends the change of the key to the change log, if it is enabled.
+    #[inline]
+    fn log_change(&self, kind: ChangeKind, key_kt: &KT) -> Result<()> {
+        if let Some(cdc_file) = &self.cdc_file {
+            cdc_file.append(kind, key_kt.as_bytes())?;
+        }
+        Ok(())
+    }
+    pub(crate) fn last_change_seq(&self) -> Result<u64> {
+        Ok(self.change_log()?.last_seq())
+    }
+    pub(crate) fn truncate_changes(&mut self, seq: u64) -> Result<u64> {
+        self.dirty = true;
+        self.change_log()?.truncate(seq)
+    }
+}
+
+// change hooks
+//
+// the hooks are called after the change is written, in the same order as
+// the change log. the old value is `None` if the key is new or expired.
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    #[inline]
+    pub(crate) fn add_put_hook(&mut self, hook: Box<PutHook<KT>>) {
+        self.hooks.put.push(RefCell::new(hook));
+    }
+    #[inline]
+    pub(crate) fn add_delete_hook(&mut self, hook: Box<DeleteHook<KT>>) {
+        self.hooks.delete.push(RefCell::new(hook));
+    }
+    #[inline]
+    pub(crate) fn clear_hooks(&mut self) {
+        self.hooks = Hooks::new();
+    }
+}
+
+// repair
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    /// rebuilds the hash buckets, the bitmap, the item count and the free lists
+    /// by scanning the live key pieces in the key file.
+    ///
+    /// the key pieces of the invalid value offsets are dropped, and the dead pieces
+    /// are linked into the free lists. the broken tail of the files is truncated.
+    pub(crate) fn repair(&mut self) -> Result<RepairReport> {
+        self.dirty = true;
+        let mut notes = Vec::new();
+        let (key_pieces, _free_keys) = self.key_file.verify_pieces(&mut notes)?;
+        let (value_pieces, _free_values) = self.val_file.verify_pieces(&mut notes)?;
+        // only the truncated tails are worth noting, the old free lists are dropped.
+        notes.retain(|note| note.contains("invalid piece size"));
+        for note in notes.iter_mut() {
+            *note = format!("{note}, the tail is truncated");
+        }
+        let mut key_offsets: Vec<u64> = key_pieces.keys().copied().collect();
+        key_offsets.sort_unstable();
+        //
+        let mut dropped_keys = 0;
+        let mut pieces: Vec<(u64, KeyPieceOffset)> = Vec::new();
+        let mut value_offsets: Vec<u64> = Vec::new();
+        let mut linked_values = HashSet::new();
+        for offset in key_offsets {
+            let key_offset = KeyPieceOffset::new(offset);
+            if self
+                .key_file
+                .read_piece_only_key_length(key_offset)?
+                .is_zero()
+            {
+                // the free piece.
+                continue;
+            }
+            let key_piece = match self
+                .key_file
+                .read_valid_piece(key_offset, key_pieces[&offset])
+            {
+                Ok(Some(key_piece)) => key_piece,
+                _ => {
+                    notes.push(format!("key: the piece at {offset} is broken, dropped"));
+                    dropped_keys += 1;
+                    continue;
+                }
+            };
+            let value_offset = key_piece.value_offset.as_value();
+            let is_valid_value = match value_pieces.get(&value_offset) {
+                Some(&size) if !linked_values.contains(&value_offset) => self
+                    .val_file
+                    .is_valid_piece(key_piece.value_offset, size)
+                    .unwrap_or(false),
+                _ => false,
+            };
+            if !is_valid_value {
+                notes.push(format!(
+                    "key: the piece at {offset}: the value offset {value_offset} is invalid, dropped"
+                ));
+                dropped_keys += 1;
+                continue;
+            }
+            linked_values.insert(value_offset);
+            pieces.push((key_piece.key.hash_value(), key_offset));
+            value_offsets.push(value_offset);
+        }
+        //
+        let buckets_size = htx::capacity_to_buckets_size(pieces.len().max(1) as u64);
+        let (chains, duplicates) = self.bucket_chains(&pieces, buckets_size)?;
+        let duplicates: HashSet<usize> = duplicates.into_iter().collect();
+        let mut live_keys = HashSet::new();
+        let mut live_values = HashSet::new();
+        for (i, &(_hash, key_offset)) in pieces.iter().enumerate() {
+            if !duplicates.contains(&i) {
+                live_keys.insert(key_offset.as_value());
+                live_values.insert(value_offsets[i]);
+            }
+        }
+        self.key_file
+            .repair_free_piece_lists(&key_pieces, &live_keys)?;
+        self.val_file
+            .repair_free_piece_lists(&value_pieces, &live_values)?;
+        //
+        // links the chain from the tail, the piece may be moved by the longer next offset.
+        let mut heads = Vec::with_capacity(chains.len());
+        for (bucket, chain) in &chains {
+            let mut next_offset = KeyPieceOffset::new(0);
+            for &i in chain.iter().rev() {
+                let mut key_piece = self.key_file.read_piece(pieces[i].1)?;
+                if key_piece.bucket_next_offset != next_offset {
+                    key_piece.bucket_next_offset = next_offset;
+                    key_piece = self.key_file.write_piece(key_piece)?;
+                }
+                next_offset = key_piece.offset;
+            }
+            heads.push((*bucket, next_offset));
+        }
+        let item_count = live_keys.len() as u64;
+        self.htx_file.rebuild(buckets_size, &heads, item_count)?;
+        if self.idx_file.is_some() {
+            self.rebuild_ordered_index()?;
+        }
+        self.generation += 1;
+        self.value_generation += 1;
+        //
+        let free_key_pieces = self.key_file.count_of_free_key_piece()?;
+        let free_value_pieces = self.val_file.count_of_free_value_piece()?;
+        Ok(RepairReport {
+            item_count,
+            dropped_keys,
+            duplicate_keys: duplicates.len() as u64,
+            free_key_pieces: free_key_pieces.iter().map(|&(_, count)| count).sum(),
+            free_value_pieces: free_value_pieces.iter().map(|&(_, count)| count).sum(),
+            notes,
+        })
+    }
+}
+
+// metrics
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    /// returns the snapshot of the metrics with the file counters.
+    pub(crate) fn metrics(&self) -> DbXxxMetrics {
+        let key_counters = self.key_file.counters();
+        let val_counters = self.val_file.counters();
+        DbXxxMetrics {
+            key_bytes_read: key_counters.bytes_read,
+            key_bytes_written: key_counters.bytes_written,
+            value_bytes_read: val_counters.bytes_read,
+            value_bytes_written: val_counters.bytes_written,
+            relocations: key_counters.relocations + val_counters.relocations,
+            free_list_reuses: key_counters.free_list_reuses + val_counters.free_list_reuses,
+            ..self.metrics.clone()
+        }
+    }
+    pub(crate) fn reset_metrics(&mut self) {
+        self.metrics = DbXxxMetrics::default();
+        self.key_file.reset_counters();
+        self.val_file.reset_counters();
+    }
+}
+
+// ordered index
+impl<KT: DbMapKeyType> FileDbXxxInner<KT> {
+    fn ordered_index(&self) -> Result<&idx::IdxFile<KT>> {
+        match &self.idx_file {
+            Some(idx_file) => Ok(idx_file),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "the ordered index is not enabled on this map",
+            )),
+        }
+    }
+    /// rebuilds the ordered index from the all keys in the hash buckets.
+    pub(crate) fn rebuild_ordered_index(&self) -> Result<()> {
+        let mut sorted = Vec::new();
+        for key_offset in DbXxxKeyPieceOffsetIter::new(self)? {
+            let key = self.load_key_data(key_offset)?;
+            sorted.push((key, key_offset));
+        }
+        sorted.sort_unstable_by(|a, b| idx::key_cmp(&a.0, &b.0));
+        self.ordered_index()?.rebuild(sorted)
+    }
+    /// returns the start position of the range.
+    pub(crate) fn ordered_start(&self, start: Bound<&KT>) -> Result<(idx::IdxNode, usize)> {
+        let idx_file = self.ordered_index()?;
+        let (node_offset, pos) = match start {
+            Bound::Included(key) => idx_file.lower_bound(key, true, &self.key_file)?,
+            Bound::Excluded(key) => idx_file.lower_bound(key, false, &self.key_file)?,
+            Bound::Unbounded => (idx_file.head_node_offset()?, 0),
+        };
+        if node_offset.is_zero() {
+            Ok((idx::IdxNode::default(), 0))
+        } else {
+            Ok((idx_file.read_node(node_offset)?, pos))
+        }
+    }
+    pub(crate) fn ordered_next_node(&self, node: &idx::IdxNode) -> Result<Option<idx::IdxNode>> {
+        if node.next.is_zero() {
+            Ok(None)
+        } else {
+            self.ordered_index()?.read_node(node.next).map(Some)
+        }
+    }
+    pub(crate) fn ordered_last(&self) -> Result<Option<(KT, Vec<u8>)>> {
+        let idx_file = self.ordered_index()?;
+        let tail = idx_file.tail_node_offset()?;
+        if tail.is_zero() {
+            return Ok(None);
+        }
+        let now = now_millis();
+        let mut node_offset = tail;
+        while !node_offset.is_zero() {
+            let node = idx_file.read_node(node_offset)?;
+            // the expired keys are skipped from the end.
+            for &key_offset in node.slots.iter().rev() {
+                let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+                let opt = self
+                    .val_file
+                    .read_piece_only_value_unexpired(value_offset, now)?;
+                if let Some(value) = opt {
+                    let key = self.load_key_data(key_offset)?;
+                    return Ok(Some((key, value)));
+                }
+            }
+            node_offset = node.prev;
+        }
         Ok(None)
     }
 }
@@ -191,6 +1312,12 @@ impl<KT: DbMapKeyType> DbXxxBase for FileDbXxxInner<KT> {
         self.val_file.read_fill_buffer()?;
         self.key_file.read_fill_buffer()?;
         self.htx_file.read_fill_buffer()?;
+        if let Some(idx_file) = &self.idx_file {
+            idx_file.read_fill_buffer()?;
+        }
+        if let Some(cdc_file) = &self.cdc_file {
+            cdc_file.read_fill_buffer()?;
+        }
         Ok(())
     }
     #[inline]
@@ -200,6 +1327,12 @@ impl<KT: DbMapKeyType> DbXxxBase for FileDbXxxInner<KT> {
             self.val_file.flush()?;
             self.key_file.flush()?;
             self.htx_file.flush()?;
+            if let Some(idx_file) = &self.idx_file {
+                idx_file.flush()?;
+            }
+            if let Some(cdc_file) = &self.cdc_file {
+                cdc_file.flush()?;
+            }
             self.dirty = false;
         }
         Ok(())
@@ -211,109 +1344,289 @@ impl<KT: DbMapKeyType> DbXxxBase for FileDbXxxInner<KT> {
             self.val_file.sync_all()?;
             self.key_file.sync_all()?;
             self.htx_file.sync_all()?;
+            if let Some(idx_file) = &self.idx_file {
+                idx_file.sync_all()?;
+            }
+            if let Some(cdc_file) = &self.cdc_file {
+                cdc_file.sync_all()?;
+            }
+            self.dirty = false;
+        }
+        Ok(())
+    }
+    #[inline]
+    fn sync_data(&mut self) -> Result<()> {
+        if self.is_dirty() {
+            // save all data
+            self.val_file.sync_data()?;
+            self.key_file.sync_data()?;
+            self.htx_file.sync_data()?;
+            if let Some(idx_file) = &self.idx_file {
+                idx_file.sync_data()?;
+            }
+            if let Some(cdc_file) = &self.cdc_file {
+                cdc_file.sync_data()?;
+            }
             self.dirty = false;
         }
-        Ok(())
-    }
-    #[inline]
-    fn sync_data(&mut self) -> Result<()> {
-        if self.is_dirty() {
-            // save all data
-            self.val_file.sync_data()?;
-            self.key_file.sync_data()?;
-            self.htx_file.sync_data()?;
-            self.dirty = false;
+        Ok(())
+    }
+}
+
+// impl trait: DbXxxObjectSafe<KT>
+impl<KT: DbMapKeyType> DbXxxObjectSafe<KT> for FileDbXxxInner<KT> {
+    #[inline]
+    fn get_kt(&mut self, key_kt: &KT) -> Result<Option<Vec<u8>>> {
+        self.metrics.get_count += 1;
+        let hash = HashValue::new(key_kt.hash_value());
+        let opt = self.find_in_hash_buckets_kt(hash, key_kt)?;
+        if let Some((key_offset, _prev_key_offset)) = opt {
+            let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+            self.val_file
+                .read_piece_only_value_unexpired(value_offset, now_millis())
+        } else {
+            _cold();
+            Ok(None)
+        }
+    }
+    #[inline]
+    fn put_kt(&mut self, key_kt: &KT, value: &[u8]) -> Result<()> {
+        let hash = HashValue::new(key_kt.hash_value());
+        let opt = self.find_in_hash_buckets_kt(hash, key_kt)?;
+        if let Some((key_offset, _prev_key_offset)) = opt {
+            self.store_value_in_place(key_offset, value, Some(0))?;
+        } else {
+            _cold();
+            // adding
+            self.insert_new_kt(hash, key_kt, value)?;
+        }
+        Ok(())
+    }
+    #[inline]
+    fn del_kt(&mut self, key_kt: &KT) -> Result<Option<Vec<u8>>> {
+        let hash = HashValue::new(key_kt.hash_value());
+        let opt = self.find_in_hash_buckets_kt(hash, key_kt)?;
+        if let Some((key_offset, prev_key_offset)) = opt {
+            let is_expired = self.is_expired(key_offset, now_millis())?;
+            let value = self.delete_found_kt(hash, key_offset, prev_key_offset)?;
+            if is_expired {
+                Ok(None)
+            } else {
+                Ok(Some(value))
+            }
+        } else {
+            _cold();
+            Ok(None)
+        }
+    }
+    #[inline]
+    fn includes_key_kt(&mut self, key_kt: &KT) -> Result<bool> {
+        self.metrics.get_count += 1;
+        let hash = HashValue::new(key_kt.hash_value());
+        let opt = self.find_unexpired_kt(hash, key_kt)?;
+        if let Some((_key_offset, _prev_key_offset)) = opt {
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+    #[inline]
+    fn compare_and_swap_kt(
+        &mut self,
+        key_kt: &KT,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool> {
+        let hash = HashValue::new(key_kt.hash_value());
+        let opt = self.find_in_hash_buckets_kt(hash, key_kt)?;
+        let current = match opt {
+            Some((key_offset, _prev_key_offset)) => {
+                let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+                self.val_file
+                    .read_piece_only_value_unexpired(value_offset, now_millis())?
+            }
+            None => None,
+        };
+        if current.as_deref() != expected {
+            return Ok(false);
+        }
+        match (opt, new) {
+            (Some((key_offset, _prev_key_offset)), Some(value)) => {
+                self.store_value_in_place(key_offset, value, Some(0))?;
+            }
+            (Some((key_offset, prev_key_offset)), None) => {
+                self.delete_found_kt(hash, key_offset, prev_key_offset)?;
+            }
+            (None, Some(value)) => self.insert_new_kt(hash, key_kt, value)?,
+            (None, None) => (),
         }
-        Ok(())
+        Ok(true)
     }
-}
-
-// impl trait: DbXxxObjectSafe<KT>
-impl<KT: DbMapKeyType> DbXxxObjectSafe<KT> for FileDbXxxInner<KT> {
     #[inline]
-    fn get_kt(&mut self, key_kt: &KT) -> Result<Option<Vec<u8>>> {
+    fn fetch_add_kt(&mut self, key_kt: &KT, delta: i64) -> Result<i64> {
         let hash = HashValue::new(key_kt.hash_value());
         let opt = self.find_in_hash_buckets_kt(hash, key_kt)?;
         if let Some((key_offset, _prev_key_offset)) = opt {
-            self.load_value(key_offset).map(Some)
+            if self.is_expired(key_offset, now_millis())? {
+                _cold();
+                self.store_value_in_place(key_offset, &delta.to_le_bytes(), Some(0))?;
+                return Ok(0);
+            }
+            let value = self.load_value(key_offset)?;
+            let bytes: [u8; 8] = value.as_slice().try_into().map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("the value is not a 8-byte integer: {} bytes", value.len()),
+                )
+            })?;
+            let prev = i64::from_le_bytes(bytes);
+            let new_value = prev.wrapping_add(delta).to_le_bytes();
+            self.store_value_in_place(key_offset, &new_value, None)?;
+            Ok(prev)
         } else {
             _cold();
-            Ok(None)
+            self.insert_new_kt(hash, key_kt, &delta.to_le_bytes())?;
+            Ok(0)
         }
     }
     #[inline]
-    fn put_kt(&mut self, key_kt: &KT, value: &[u8]) -> Result<()> {
+    fn append_kt(&mut self, key_kt: &KT, bytes: &[u8]) -> Result<()> {
+        self.dirty = true;
         let hash = HashValue::new(key_kt.hash_value());
         let opt = self.find_in_hash_buckets_kt(hash, key_kt)?;
         if let Some((key_offset, _prev_key_offset)) = opt {
-            let new_key_offset = self.store_value_on_insert(key_offset, value)?;
-            if key_offset != new_key_offset {
-                unimplemented!("key_offset != new_key_offset : in put_kt");
+            if self.is_expired(key_offset, now_millis())? {
+                _cold();
+                self.store_value_in_place(key_offset, bytes, Some(0))?;
+                return Ok(());
+            }
+            let mut key_piece = self.key_file.read_piece(key_offset)?;
+            let old_value = if self.hooks.put.is_empty() {
+                None
+            } else {
+                Some(
+                    self.val_file
+                        .read_piece_only_value(key_piece.value_offset)?,
+                )
+            };
+            let value_offset = self.val_file.append_piece(key_piece.value_offset, bytes)?;
+            self.value_generation += 1;
+            if key_piece.value_offset != value_offset {
+                _cold();
+                key_piece.value_offset = value_offset;
+                self.write_key_piece_in_place(key_piece)?;
+            }
+            self.metrics.put_count += 1;
+            self.log_change(ChangeKind::Put, key_kt)?;
+            if !self.hooks.put.is_empty() {
+                let value = self.val_file.read_piece_only_value(value_offset)?;
+                self.hooks.fire_put(key_kt, old_value.as_deref(), &value);
             }
         } else {
             _cold();
-            // adding
-            let bucket_next_offset = self.htx_file.read_key_piece_offset(hash)?;
-            let new_val_piece = self.val_file.add_value_piece(value)?;
-            let new_key_piece =
-                self.key_file
-                    .add_key_piece(key_kt, new_val_piece.offset, bucket_next_offset)?;
-            self.htx_file
-                .write_key_piece_offset(hash, new_key_piece.offset)?;
-            self.htx_file.write_item_count_up()?;
+            self.insert_new_kt(hash, key_kt, bytes)?;
         }
         Ok(())
     }
     #[inline]
-    fn del_kt(&mut self, key_kt: &KT) -> Result<Option<Vec<u8>>> {
+    fn value_len_kt(&mut self, key_kt: &KT) -> Result<Option<u64>> {
+        self.metrics.get_count += 1;
         let hash = HashValue::new(key_kt.hash_value());
-        let opt = self.find_in_hash_buckets_kt(hash, key_kt)?;
+        let opt = self.find_unexpired_kt(hash, key_kt)?;
         if let Some((key_offset, _prev_key_offset)) = opt {
-            let key_piece = self.key_file.read_piece(key_offset)?;
-            let value = self
-                .val_file
-                .read_piece_only_value(key_piece.value_offset)?;
-            //
-            if _prev_key_offset.is_zero() {
-                self.htx_file
-                    .write_key_piece_offset(hash, key_piece.bucket_next_offset)?;
-            } else {
-                _cold();
-                // changing link of bucket chain.
-                let mut prev_key_piece = self.key_file.read_piece(_prev_key_offset)?;
-                prev_key_piece.bucket_next_offset = key_piece.bucket_next_offset;
-                let new_prev_key = self.key_file.write_piece(prev_key_piece)?;
-                if _prev_key_offset != new_prev_key.offset {
-                    _cold();
-                    panic!("_prev_key_offset != new_prev_key_offset : in del_kt");
-                }
-            }
-            //
-            self.val_file.delete_piece(key_piece.value_offset)?;
-            self.key_file.delete_piece(key_offset)?;
-            self.htx_file.write_item_count_down()?;
-            //
-            Ok(Some(value))
+            let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+            let value_len = self.load_value_length(value_offset)?;
+            Ok(Some(value_len.as_value() as u64))
         } else {
             _cold();
             Ok(None)
         }
     }
     #[inline]
-    fn includes_key_kt(&mut self, key_kt: &KT) -> Result<bool> {
+    fn entry_meta_kt(&mut self, key_kt: &KT) -> Result<Option<EntryMeta>> {
+        self.metrics.get_count += 1;
         let hash = HashValue::new(key_kt.hash_value());
-        let opt = self.find_in_hash_buckets_kt(hash, key_kt)?;
-        if let Some((_key_offset, _prev_key_offset)) = opt {
-            Ok(true)
+        let opt = self.find_unexpired_kt(hash, key_kt)?;
+        if let Some((key_offset, _prev_key_offset)) = opt {
+            let key_piece_size = self.load_key_piece_size(key_offset)?;
+            let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+            let value_piece_size = self.load_value_piece_size(value_offset)?;
+            let value_len = self.load_value_length(value_offset)?;
+            Ok(Some(EntryMeta {
+                value_len: value_len.as_value() as u64,
+                key_piece_offset: key_offset.as_value(),
+                key_piece_size: key_piece_size.as_value(),
+                value_piece_offset: value_offset.as_value(),
+                value_piece_size: value_piece_size.as_value(),
+            }))
         } else {
-            Ok(false)
+            _cold();
+            Ok(None)
+        }
+    }
+    fn bulk_get_kt(&mut self, keys: &[&KT]) -> Result<Vec<Option<Vec<u8>>>> {
+        self.metrics.get_count += keys.len() as u64;
+        let (hashes, order) = self.bucket_ordered(keys);
+        let mut found = Vec::with_capacity(keys.len());
+        for i in order {
+            if let Some((key_offset, _prev_key_offset)) =
+                self.find_in_hash_buckets_kt(hashes[i], keys[i])?
+            {
+                found.push((key_offset, i));
+            }
+        }
+        // the values are read in key piece order.
+        found.sort_unstable();
+        let now = now_millis();
+        let mut result = vec![None; keys.len()];
+        for (key_offset, i) in found {
+            let value_offset = self.key_file.read_piece_only_value_offset(key_offset)?;
+            result[i] = self
+                .val_file
+                .read_piece_only_value_unexpired(value_offset, now)?;
+        }
+        Ok(result)
+    }
+    fn bulk_put_kt(&mut self, bulk: &[(&KT, &[u8])]) -> Result<()> {
+        let keys: Vec<&KT> = bulk.iter().map(|kv| kv.0).collect();
+        let (hashes, order) = self.bucket_ordered(&keys);
+        let mut new_keys = Vec::new();
+        for (n, &i) in order.iter().enumerate() {
+            // only the last one of the same keys is stored.
+            if let Some(&next) = order.get(n + 1) {
+                if keys[next] == keys[i] {
+                    continue;
+                }
+            }
+            match self.find_in_hash_buckets_kt(hashes[i], keys[i])? {
+                Some((key_offset, _prev_key_offset)) => {
+                    self.store_value_in_place(key_offset, bulk[i].1, Some(0))?;
+                }
+                None => new_keys.push(i),
+            }
+        }
+        if !new_keys.is_empty() {
+            // the value pieces of the new keys are allocated in one go.
+            let values: Vec<&[u8]> = new_keys.iter().map(|&i| bulk[i].1).collect();
+            let value_offsets = self.val_file.add_value_pieces(&values)?;
+            for (&i, value_offset) in new_keys.iter().zip(value_offsets) {
+                self.insert_new_kt_with_value_offset(hashes[i], keys[i], value_offset)?;
+            }
         }
+        Ok(())
+    }
+    fn bulk_delete_kt(&mut self, keys: &[&KT]) -> Result<Vec<Option<Vec<u8>>>> {
+        let (_hashes, order) = self.bucket_ordered(keys);
+        let mut result = vec![None; keys.len()];
+        for i in order {
+            result[i] = self.del_kt(keys[i])?;
+        }
+        Ok(result)
     }
 }
 
 // for Iterator
 //
-#[allow(dead_code)]
 #[derive(Debug)]
 struct DbXxxKeyPieceOffsetIter<'a, KT: DbMapKeyType> {
     db_map_inner: &'a FileDbXxxInner<KT>,
@@ -324,7 +1637,7 @@ struct DbXxxKeyPieceOffsetIter<'a, KT: DbMapKeyType> {
 }
 
 impl<'a, KT: DbMapKeyType> DbXxxKeyPieceOffsetIter<'a, KT> {
-    fn _new(db_map_inner: &'a FileDbXxxInner<KT>) -> Result<Self> {
+    fn new(db_map_inner: &'a FileDbXxxInner<KT>) -> Result<Self> {
         let (buckets_size, remaining_item_count) = {
             //let db_map_inner = RefCell::borrow(&db_map);
             (
@@ -398,41 +1711,171 @@ impl<KT: DbMapKeyType> Iterator for DbXxxKeyPieceOffsetIter<'_, KT> {
 
 impl<KT: DbMapKeyType> ExactSizeIterator for DbXxxKeyPieceOffsetIter<'_, KT> {}
 
-//
+/// An iterator over the key-value pairs in hash bucket order.
+///
+/// The size is exact on the map that has never had the time-to-live keys.
+/// The expired keys are skipped, so `size_hint()` returns only the upper bound
+/// on the map that has had them, and so does it after resuming from a position
+/// or after the modification of the tolerant iterator.
+/// `len()` panics if the size is not exact.
 #[derive(Debug)]
 pub struct DbXxxIterMut<KT: DbMapKeyType> {
     db_map: Rc<RefCell<FileDbXxxInner<KT>>>,
     remaining_item_count: u64,
+    exact: bool,
     buckets_size: u64,
     buckets_idx: u64,
     key_offset: KeyPieceOffset,
+    chain_pos: u64,
+    generation: u64,
+    tolerant: bool,
 }
 
 impl<KT: DbMapKeyType> DbXxxIterMut<KT> {
     pub fn new(db_map: Rc<RefCell<FileDbXxxInner<KT>>>) -> Result<Self> {
-        let (buckets_size, remaining_item_count) = {
-            let db_map_inner = RefCell::borrow(&db_map);
+        let (buckets_size, remaining_item_count, exact, generation) = {
+            let mut db_map_inner = RefCell::borrow_mut(&db_map);
+            db_map_inner.metrics.iter_count += 1;
             (
                 db_map_inner.htx_file.read_hash_buckets_size()?,
                 db_map_inner.htx_file.read_item_count()?,
+                !db_map_inner.val_file.has_expire(),
+                db_map_inner.generation,
             )
         };
         Ok(Self {
             db_map,
             remaining_item_count,
+            exact,
             buckets_size,
             buckets_idx: 0,
             key_offset: KeyPieceOffset::new(0),
+            chain_pos: 0,
+            generation,
+            tolerant: false,
         })
     }
-    fn next_piece_offset(&mut self) -> Option<KeyPieceOffset> {
+    /// makes the iterator tolerant of the modification of the map.
+    ///
+    /// By default, the iterator fails when a key is inserted or deleted
+    /// while iterating: `try_next()` returns the error and `next()` panics.
+    /// The tolerant iterator continues with the same semantics
+    /// as resuming from `position()`, see `DbXxxPosition`.
+    pub fn tolerant(mut self) -> Self {
+        self.tolerant = true;
+        self
+    }
+    /// creates the iterator that resumes from the position.
+    pub fn with_position(
+        db_map: Rc<RefCell<FileDbXxxInner<KT>>>,
+        position: &DbXxxPosition,
+    ) -> Result<Self> {
+        let mut iter = Self::new(db_map)?;
+        // the count of the visited keys is unknown.
+        iter.exact = false;
+        if position.buckets_size != iter.buckets_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "the position is not for this map: buckets size: {} != {}",
+                    position.buckets_size, iter.buckets_size
+                ),
+            ));
+        }
+        if position.buckets_idx >= iter.buckets_size {
+            iter.buckets_idx = iter.buckets_size;
+            iter.remaining_item_count = 0;
+            return Ok(iter);
+        }
+        iter.buckets_idx = position.buckets_idx;
+        if position.chain_pos > 0 {
+            iter.seek_in_bucket(
+                position.buckets_idx,
+                position.key_offset,
+                position.chain_pos,
+            )?;
+        }
+        Ok(iter)
+    }
+    /// returns the position to resume this iteration with `FileDbMap::iter_from()`.
+    pub fn position(&self) -> DbXxxPosition {
+        let (buckets_idx, chain_pos, key_offset) = if self.remaining_item_count == 0 {
+            (self.buckets_size, 0, 0)
+        } else if self.key_offset.is_zero() {
+            (self.buckets_idx, 0, 0)
+        } else {
+            (
+                self.buckets_idx - 1,
+                self.chain_pos,
+                self.key_offset.as_value(),
+            )
+        };
+        DbXxxPosition {
+            buckets_size: self.buckets_size,
+            buckets_idx,
+            chain_pos,
+            key_offset,
+        }
+    }
+    /// skips the entries that are already visited in the bucket.
+    fn seek_in_bucket(&mut self, buckets_idx: u64, key_offset: u64, chain_pos: u64) -> Result<()> {
+        // the key piece of the position is searched first, because its offset
+        // is kept while the other keys in the bucket are inserted or deleted.
+        let db_map_inner = RefCell::borrow(&self.db_map);
+        let mut key_inner = RefCell::borrow_mut(&db_map_inner.key_file.0);
+        let head_offset = db_map_inner
+            .htx_file
+            .read_bucket_key_piece_offset(buckets_idx)?;
+        let mut offset = head_offset;
+        let mut pos = 1;
+        while !offset.is_zero() && offset.as_value() != key_offset {
+            offset = key_inner.read_piece_only_bucket_next_offset(offset)?;
+            pos += 1;
+        }
+        if offset.is_zero() {
+            // the key of the position was deleted, fall back to the chain position.
+            offset = head_offset;
+            pos = 1;
+            while !offset.is_zero() && pos < chain_pos {
+                offset = key_inner.read_piece_only_bucket_next_offset(offset)?;
+                pos += 1;
+            }
+        }
+        self.key_offset = offset;
+        self.chain_pos = pos;
+        self.buckets_idx = buckets_idx + 1;
+        Ok(())
+    }
+    /// checks the modification of the map.
+    fn check_generation(&mut self) -> Result<()> {
+        let generation = RefCell::borrow(&self.db_map).generation;
+        if generation == self.generation {
+            return Ok(());
+        }
+        _cold();
+        if !self.tolerant {
+            return Err(modified_error());
+        }
+        self.generation = generation;
+        if self.remaining_item_count == 0 {
+            return Ok(());
+        }
+        if !self.key_offset.is_zero() {
+            let key_offset = self.key_offset.as_value();
+            self.seek_in_bucket(self.buckets_idx - 1, key_offset, self.chain_pos)?;
+        }
+        self.remaining_item_count = RefCell::borrow(&self.db_map).htx_file.read_item_count()?;
+        self.exact = false;
+        Ok(())
+    }
+    fn next_piece_offset(&mut self) -> Result<Option<KeyPieceOffset>> {
+        self.check_generation()?;
         let db_map_inner = RefCell::borrow(&self.db_map);
         let mut key_inner = RefCell::borrow_mut(&db_map_inner.key_file.0);
         let mut htx_inner = RefCell::borrow_mut(&db_map_inner.htx_file.0);
         if !self.key_offset.is_zero() {
-            self.key_offset = key_inner
-                .read_piece_only_bucket_next_offset(self.key_offset)
-                .unwrap();
+            self.key_offset = key_inner.read_piece_only_bucket_next_offset(self.key_offset)?;
+            self.chain_pos += 1;
         } else {
             _cold();
         }
@@ -443,24 +1886,143 @@ impl<KT: DbMapKeyType> DbXxxIterMut<KT> {
             while key_offset.is_zero() && buckets_idx < buckets_size {
                 let (next_idx, offset) = htx_inner
                     .file
-                    .next_key_piece_offset(buckets_size, buckets_idx)
-                    .unwrap();
+                    .next_key_piece_offset(buckets_size, buckets_idx)?;
                 key_offset = offset;
                 buckets_idx = next_idx;
             }
             self.key_offset = key_offset;
             self.buckets_idx = buckets_idx;
+            self.chain_pos = 1;
         }
         //
         if self.key_offset.is_zero() || self.remaining_item_count == 0 {
             _cold();
-            None
+            Ok(None)
         } else {
             if self.remaining_item_count > 0 {
                 self.remaining_item_count -= 1;
             }
-            Some(self.key_offset)
+            Ok(Some(self.key_offset))
+        }
+    }
+    /// advances the iterator and returns the next key-value pair, or the error
+    /// of the modification of the map while iterating or of the file.
+    pub fn try_next(&mut self) -> Result<Option<(KT, Vec<u8>)>> {
+        let now = now_millis();
+        while let Some(key_offset) = self.next_piece_offset()? {
+            let db_map_inner = RefCell::borrow_mut(&self.db_map);
+            let value_offset = db_map_inner
+                .key_file
+                .read_piece_only_value_offset(key_offset)?;
+            let opt_value = db_map_inner
+                .val_file
+                .read_piece_only_value_unexpired(value_offset, now)?;
+            if let Some(value_vec) = opt_value {
+                let key = db_map_inner.load_key_data(key_offset)?;
+                return Ok(Some((key, value_vec)));
+            }
+            // the expired key is skipped.
+        }
+        _cold();
+        Ok(None)
+    }
+}
+
+// the error of the modification of the map while iterating.
+fn modified_error() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Other,
+        "the map was modified while iterating",
+    )
+}
+
+/// The position of the iteration in hash bucket order.
+///
+/// This is an opaque token that can be serialized with `to_bytes()` or `to_string()`,
+/// and restored with `from_bytes()` or `parse()`.
+/// The iteration is resumed with `FileDbMap::iter_from()`.
+///
+/// When keys are inserted or deleted between pages:
+///
+/// - the keys that are not changed are visited exactly once,
+///   unless the last visited key of the position is deleted.
+/// - if the last visited key is deleted, the position falls back to
+///   the count of the visited entries in its bucket, then some entries
+///   in that bucket can be visited again or skipped.
+/// - the inserted keys are visited only if their bucket is after the position.
+/// - the position is invalid on the other map that has another buckets size.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DbXxxPosition {
+    buckets_size: u64,
+    buckets_idx: u64,
+    chain_pos: u64,
+    key_offset: u64,
+}
+
+impl DbXxxPosition {
+    /// the byte size of the serialized position.
+    pub const BYTES_SIZE: usize = 32;
+    /// serializes the position.
+    pub fn to_bytes(&self) -> [u8; Self::BYTES_SIZE] {
+        let mut bytes = [0u8; Self::BYTES_SIZE];
+        bytes[0..8].copy_from_slice(&self.buckets_size.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.buckets_idx.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.chain_pos.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.key_offset.to_le_bytes());
+        bytes
+    }
+    /// deserializes the position.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != Self::BYTES_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid position length: {}", bytes.len()),
+            ));
+        }
+        let mut ary = [0u8; 8];
+        let mut read_u64 = |i: usize| {
+            ary.copy_from_slice(&bytes[i..i + 8]);
+            u64::from_le_bytes(ary)
+        };
+        Ok(Self {
+            buckets_size: read_u64(0),
+            buckets_idx: read_u64(8),
+            chain_pos: read_u64(16),
+            key_offset: read_u64(24),
+        })
+    }
+    /// returns true if the iteration is finished at this position.
+    pub fn is_end(&self) -> bool {
+        self.buckets_idx >= self.buckets_size
+    }
+}
+
+impl std::fmt::Display for DbXxxPosition {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for b in self.to_bytes() {
+            formatter.write_fmt(format_args!("{b:02x}"))?;
         }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for DbXxxPosition {
+    type Err = std::io::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let err = || {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid position string: {s}"),
+            )
+        };
+        if s.len() != Self::BYTES_SIZE * 2 || !s.is_ascii() {
+            return Err(err());
+        }
+        let mut bytes = [0u8; Self::BYTES_SIZE];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| err())?;
+        }
+        Self::from_bytes(&bytes)
     }
 }
 
@@ -469,22 +2031,16 @@ impl<KT: DbMapKeyType> Iterator for DbXxxIterMut<KT> {
     type Item = (KT, Vec<u8>);
     #[inline]
     fn next(&mut self) -> Option<(KT, Vec<u8>)> {
-        if let Some(key_offset) = self.next_piece_offset() {
-            let db_map_inner = RefCell::borrow_mut(&self.db_map);
-            let key = db_map_inner.load_key_data(key_offset).unwrap();
-            let value_vec = db_map_inner.load_value(key_offset).unwrap();
-            Some((key, value_vec))
-        } else {
-            _cold();
-            None
-        }
+        self.try_next().unwrap()
     }
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (
-            self.remaining_item_count as usize,
-            Some(self.remaining_item_count as usize),
-        )
+        let remaining = self.remaining_item_count as usize;
+        if self.exact {
+            (remaining, Some(remaining))
+        } else {
+            (0, Some(remaining))
+        }
     }
 }
 
@@ -503,6 +2059,33 @@ impl<KT: DbMapKeyType> DbXxxIter<KT> {
             iter: DbXxxIterMut::new(db_map)?,
         })
     }
+    /// makes the iterator tolerant of the modification of the map.
+    /// see `DbXxxIterMut::tolerant()`.
+    #[inline]
+    pub fn tolerant(self) -> Self {
+        Self {
+            iter: self.iter.tolerant(),
+        }
+    }
+    /// see `DbXxxIterMut::try_next()`.
+    #[inline]
+    pub fn try_next(&mut self) -> Result<Option<(KT, Vec<u8>)>> {
+        self.iter.try_next()
+    }
+    #[inline]
+    pub fn with_position(
+        db_map: Rc<RefCell<FileDbXxxInner<KT>>>,
+        position: &DbXxxPosition,
+    ) -> Result<Self> {
+        Ok(Self {
+            iter: DbXxxIterMut::with_position(db_map, position)?,
+        })
+    }
+    /// returns the position to resume this iteration with `FileDbMap::iter_from()`.
+    #[inline]
+    pub fn position(&self) -> DbXxxPosition {
+        self.iter.position()
+    }
 }
 
 // impl trait: Iterator
@@ -533,6 +2116,19 @@ impl<KT: DbMapKeyType> DbXxxIntoIter<KT> {
             iter: DbXxxIterMut::new(db_map)?,
         })
     }
+    /// makes the iterator tolerant of the modification of the map.
+    /// see `DbXxxIterMut::tolerant()`.
+    #[inline]
+    pub fn tolerant(self) -> Self {
+        Self {
+            iter: self.iter.tolerant(),
+        }
+    }
+    /// see `DbXxxIterMut::try_next()`.
+    #[inline]
+    pub fn try_next(&mut self) -> Result<Option<(KT, Vec<u8>)>> {
+        self.iter.try_next()
+    }
 }
 
 // impl trait: Iterator
@@ -563,6 +2159,19 @@ impl<KT: DbMapKeyType> DbXxxKeys<KT> {
             iter: DbXxxIterMut::new(db_map)?,
         })
     }
+    /// makes the iterator tolerant of the modification of the map.
+    /// see `DbXxxIterMut::tolerant()`.
+    #[inline]
+    pub fn tolerant(self) -> Self {
+        Self {
+            iter: self.iter.tolerant(),
+        }
+    }
+    /// see `DbXxxIterMut::try_next()`.
+    #[inline]
+    pub fn try_next(&mut self) -> Result<Option<KT>> {
+        Ok(self.iter.try_next()?.map(|(k, _v)| k))
+    }
 }
 
 // impl trait: Iterator
@@ -593,6 +2202,19 @@ impl<KT: DbMapKeyType> DbXxxValues<KT> {
             iter: DbXxxIterMut::new(db_map)?,
         })
     }
+    /// makes the iterator tolerant of the modification of the map.
+    /// see `DbXxxIterMut::tolerant()`.
+    #[inline]
+    pub fn tolerant(self) -> Self {
+        Self {
+            iter: self.iter.tolerant(),
+        }
+    }
+    /// see `DbXxxIterMut::try_next()`.
+    #[inline]
+    pub fn try_next(&mut self) -> Result<Option<Vec<u8>>> {
+        Ok(self.iter.try_next()?.map(|(_k, v)| v))
+    }
 }
 
 // impl trait: Iterator
@@ -610,6 +2232,204 @@ impl<KT: DbMapKeyType> Iterator for DbXxxValues<KT> {
 
 impl<KT: DbMapKeyType> ExactSizeIterator for DbXxxValues<KT> {}
 
+/// An iterator over the key-value pairs of a DbMap in key order.
+///
+/// This is made from the ordered index.
+#[derive(Debug)]
+pub struct DbXxxRange<KT: DbMapKeyType> {
+    db_map: Rc<RefCell<FileDbXxxInner<KT>>>,
+    node: idx::IdxNode,
+    pos: usize,
+    start: Bound<KT>,
+    end: Bound<KT>,
+    finished: bool,
+    generation: u64,
+    tolerant: bool,
+}
+
+impl<KT: DbMapKeyType> DbXxxRange<KT> {
+    pub fn new<R: RangeBounds<KT>>(
+        db_map: Rc<RefCell<FileDbXxxInner<KT>>>,
+        range: R,
+    ) -> Result<Self> {
+        let (node, pos, generation) = {
+            let mut db_map_inner = RefCell::borrow_mut(&db_map);
+            db_map_inner.metrics.iter_count += 1;
+            let (node, pos) = db_map_inner.ordered_start(range.start_bound())?;
+            (node, pos, db_map_inner.generation)
+        };
+        let start = match range.start_bound() {
+            Bound::Included(key) => Bound::Included(key.clone()),
+            Bound::Excluded(key) => Bound::Excluded(key.clone()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => Bound::Included(key.clone()),
+            Bound::Excluded(key) => Bound::Excluded(key.clone()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        Ok(Self {
+            db_map,
+            node,
+            pos,
+            start,
+            end,
+            finished: false,
+            generation,
+            tolerant: false,
+        })
+    }
+    /// makes the iterator tolerant of the modification of the map.
+    ///
+    /// By default, the iterator fails when a key is inserted or deleted
+    /// while iterating: `try_next()` returns the error and `next()` panics.
+    /// The tolerant iterator continues from the next key of the last returned
+    /// key in the current ordered index.
+    pub fn tolerant(mut self) -> Self {
+        self.tolerant = true;
+        self
+    }
+    /// checks the modification of the map.
+    fn check_generation(&mut self) -> Result<()> {
+        let db_map_inner = RefCell::borrow(&self.db_map);
+        if db_map_inner.generation == self.generation {
+            return Ok(());
+        }
+        _cold();
+        if !self.tolerant {
+            return Err(modified_error());
+        }
+        let (node, pos) = db_map_inner.ordered_start(self.start.as_ref())?;
+        self.generation = db_map_inner.generation;
+        self.node = node;
+        self.pos = pos;
+        Ok(())
+    }
+    fn next_piece_offset(&mut self) -> Result<Option<KeyPieceOffset>> {
+        self.check_generation()?;
+        while self.pos >= self.node.slots.len() {
+            let db_map_inner = RefCell::borrow(&self.db_map);
+            match db_map_inner.ordered_next_node(&self.node)? {
+                Some(node) => {
+                    self.node = node;
+                    self.pos = 0;
+                }
+                None => return Ok(None),
+            }
+        }
+        let key_offset = self.node.slots[self.pos];
+        self.pos += 1;
+        Ok(Some(key_offset))
+    }
+    fn is_over_end(&self, key: &KT) -> bool {
+        match &self.end {
+            Bound::Included(end) => idx::key_cmp(key, end) == Ordering::Greater,
+            Bound::Excluded(end) => idx::key_cmp(key, end) != Ordering::Less,
+            Bound::Unbounded => false,
+        }
+    }
+    /// advances the iterator and returns the next key-value pair, or the error
+    /// of the modification of the map while iterating or of the file.
+    pub fn try_next(&mut self) -> Result<Option<(KT, Vec<u8>)>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let now = now_millis();
+        while let Some(key_offset) = self.next_piece_offset()? {
+            let db_map_inner = RefCell::borrow(&self.db_map);
+            let key = db_map_inner.load_key_data(key_offset)?;
+            if self.is_over_end(&key) {
+                self.finished = true;
+                return Ok(None);
+            }
+            let value_offset = db_map_inner
+                .key_file
+                .read_piece_only_value_offset(key_offset)?;
+            let opt_value = db_map_inner
+                .val_file
+                .read_piece_only_value_unexpired(value_offset, now)?;
+            if self.tolerant {
+                self.start = Bound::Excluded(key.clone());
+            }
+            if let Some(value_vec) = opt_value {
+                return Ok(Some((key, value_vec)));
+            }
+            // the expired key is skipped.
+        }
+        _cold();
+        self.finished = true;
+        Ok(None)
+    }
+}
+
+// impl trait: Iterator
+impl<KT: DbMapKeyType> Iterator for DbXxxRange<KT> {
+    type Item = (KT, Vec<u8>);
+    #[inline]
+    fn next(&mut self) -> Option<(KT, Vec<u8>)> {
+        self.try_next().unwrap()
+    }
+}
+
+impl<KT: DbMapKeyType> FusedIterator for DbXxxRange<KT> {}
+
+/// An iterator over the key-value pairs whose key starts with the prefix.
+///
+/// This is made from the ordered index, the range ends at the first key
+/// past the prefix.
+#[derive(Debug)]
+pub struct DbXxxPrefix<KT: DbMapKeyType> {
+    iter: DbXxxRange<KT>,
+}
+
+impl<KT: DbMapKeyType> DbXxxPrefix<KT> {
+    pub fn new(db_map: Rc<RefCell<FileDbXxxInner<KT>>>, prefix: &[u8]) -> Result<Self> {
+        let start = Bound::Included(KT::from_bytes(prefix));
+        let end = match prefix_end(prefix) {
+            Some(end) => Bound::Excluded(KT::from_bytes(&end)),
+            None => Bound::Unbounded,
+        };
+        let iter = DbXxxRange::new(db_map, (start, end))?;
+        Ok(Self { iter })
+    }
+    /// makes the iterator tolerant of the modification of the map.
+    /// see `DbXxxRange::tolerant()`.
+    pub fn tolerant(self) -> Self {
+        Self {
+            iter: self.iter.tolerant(),
+        }
+    }
+    /// see `DbXxxRange::try_next()`.
+    #[inline]
+    pub fn try_next(&mut self) -> Result<Option<(KT, Vec<u8>)>> {
+        self.iter.try_next()
+    }
+}
+
+// the least key that is greater than all the keys with the prefix.
+// it is none if all the keys from the prefix have the prefix.
+fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+// impl trait: Iterator
+impl<KT: DbMapKeyType> Iterator for DbXxxPrefix<KT> {
+    type Item = (KT, Vec<u8>);
+    #[inline]
+    fn next(&mut self) -> Option<(KT, Vec<u8>)> {
+        self.iter.next()
+    }
+}
+
+impl<KT: DbMapKeyType> FusedIterator for DbXxxPrefix<KT> {}
+
 // for debug
 impl<KT: DbMapKeyType + std::fmt::Display> CheckFileDbMap for FileDbXxxInner<KT> {
     /*
@@ -628,9 +2448,30 @@ impl<KT: DbMapKeyType + std::fmt::Display> CheckFileDbMap for FileDbXxxInner<KT>
     /// buffer statistics
     #[cfg(feature = "rabuf_stats")]
     fn buf_stats(&self) -> Vec<(String, i64)> {
-        let mut vec = self.dat_file.buf_stats();
-        let mut vec2 = self.idx_file.buf_stats();
-        vec.append(&mut vec2);
+        let mut components = vec![
+            ("key", self.key_file.counters()),
+            ("val", self.val_file.counters()),
+            ("htx", self.htx_file.counters()),
+        ];
+        if let Some(idx_file) = &self.idx_file {
+            components.push(("idx", idx_file.counters()));
+        }
+        if let Some(cdc_file) = &self.cdc_file {
+            components.push(("cdc", cdc_file.counters()));
+        }
+        let mut vec = Vec::new();
+        for (name, counters) in components {
+            vec.push((format!("{name}.bytes_read"), counters.bytes_read as i64));
+            vec.push((
+                format!("{name}.bytes_written"),
+                counters.bytes_written as i64,
+            ));
+            vec.push((
+                format!("{name}.free_list_reuses"),
+                counters.free_list_reuses as i64,
+            ));
+            vec.push((format!("{name}.relocations"), counters.relocations as i64));
+        }
         vec
     }
     /// piece size statistics
@@ -686,13 +2527,111 @@ impl<KT: DbMapKeyType + std::fmt::Display> CheckFileDbMap for FileDbXxxInner<KT>
     }
     /// keys count statistics
     fn keys_count_stats(&self) -> Result<KeysCountStats> {
-        //self.idx_file.keys_count_stats()
-        Ok(KeysCountStats::new(Vec::new()))
+        let mut stats = KeysCountStats::default();
+        let buckets_size = self.htx_file.read_hash_buckets_size()?;
+        // the walk of a broken cyclic chain is stopped, as in `verify()`.
+        let max_steps = self.htx_file.read_item_count()? + 1;
+        for idx in 0..buckets_size {
+            let mut key_offset = self.htx_file.read_bucket_key_piece_offset(idx)?;
+            // the length is saturated, the longer chain is broken anyway.
+            let mut chain_length: u16 = 0;
+            let mut steps = 0;
+            while !key_offset.is_zero() && steps < max_steps {
+                steps += 1;
+                chain_length = chain_length.saturating_add(1);
+                key_offset = self
+                    .key_file
+                    .read_piece_only_bucket_next_offset(key_offset)?;
+            }
+            stats.touch_size(KeysCount::new(chain_length));
+        }
+        Ok(stats)
     }
     //#[cfg(feature = "htx")]
     fn htx_filling_rate_per_mill(&self) -> Result<(u64, u32)> {
         self.htx_file.htx_filling_rate_per_mill()
     }
+    fn verify(&self) -> Result<VerifyReport> {
+        let mut errors = Vec::new();
+        let (key_pieces, free_keys) = self.key_file.verify_pieces(&mut errors)?;
+        let (value_pieces, free_values) = self.val_file.verify_pieces(&mut errors)?;
+        let buckets_size = self.htx_file.read_hash_buckets_size()?;
+        let item_count = self.htx_file.read_item_count()?;
+        let mut reachable = HashSet::new();
+        let mut linked_values = HashSet::new();
+        if self.htx_file.verify_file_length(&mut errors)? {
+            for idx in 0..buckets_size {
+                let head = self.htx_file.read_bucket_key_piece_offset(idx)?;
+                if let Some(flag) = self.htx_file.read_bucket_bitmap_flag(idx)? {
+                    if flag == head.is_zero() {
+                        errors.push(format!("htx: bucket {idx}: the bitmap flag is {flag}"));
+                    }
+                }
+                let mut key_offset = head;
+                while !key_offset.is_zero() {
+                    let offset = key_offset.as_value();
+                    if !key_pieces.contains_key(&offset) {
+                        errors.push(format!("htx: bucket {idx}: {offset} is not a key piece"));
+                        break;
+                    }
+                    if free_keys.contains(&offset) {
+                        errors.push(format!("htx: bucket {idx}: the key piece {offset} is free"));
+                        break;
+                    }
+                    if !reachable.insert(offset) {
+                        errors.push(format!(
+                            "htx: bucket {idx}: the chain does not end, {offset} is reached again"
+                        ));
+                        break;
+                    }
+                    let key_piece = match self.key_file.read_piece(key_offset) {
+                        Ok(key_piece) => key_piece,
+                        Err(err) => {
+                            errors.push(format!("key: the piece at {offset} is broken: {err}"));
+                            break;
+                        }
+                    };
+                    let hash = HashValue::new(key_piece.key.hash_value());
+                    if self.htx_file.bucket_index(hash) != idx {
+                        errors.push(format!(
+                            "key: the piece at {offset} does not hash to the bucket {idx}"
+                        ));
+                    }
+                    let value_offset = key_piece.value_offset.as_value();
+                    if !value_pieces.contains_key(&value_offset) {
+                        errors.push(format!(
+                            "key: the piece at {offset}: {value_offset} is not a value piece"
+                        ));
+                    } else if free_values.contains(&value_offset) {
+                        errors.push(format!(
+                            "key: the piece at {offset}: the value piece {value_offset} is free"
+                        ));
+                    } else if !linked_values.insert(value_offset) {
+                        errors.push(format!(
+                            "key: the piece at {offset}: the value piece {value_offset} is shared"
+                        ));
+                    }
+                    key_offset = key_piece.bucket_next_offset;
+                }
+            }
+        }
+        let reachable_keys = reachable.len() as u64;
+        if item_count != reachable_keys {
+            errors.push(format!(
+                "htx: the item count {item_count} is not the reachable keys {reachable_keys}"
+            ));
+        }
+        Ok(VerifyReport {
+            buckets: buckets_size,
+            reachable_keys,
+            item_count,
+            key_pieces: key_pieces.len() as u64,
+            free_key_pieces: free_keys.len() as u64,
+            value_pieces: value_pieces.len() as u64,
+            free_value_pieces: free_values.len() as u64,
+            errors,
+        })
+    }
     /*
     /// convert the index node tree to graph string for debug.
     fn graph_string(&self) -> Result<String> {
@@ -0,0 +1,169 @@
+use super::super::super::DbMapKeyType;
+use super::dbxxx::{now_millis, FileDbXxxInner};
+use super::semtype::*;
+use std::cell::RefMut;
+use std::io::Result;
+
+/// A view into a single entry in a map, which may either be vacant or occupied.
+///
+/// This is constructed from the `entry()` method on `FileDbMap`.
+/// The key is looked up only once, and the found position is reused.
+#[derive(Debug)]
+pub enum Entry<'a, KT: DbMapKeyType> {
+    Occupied(OccupiedEntry<'a, KT>),
+    Vacant(VacantEntry<'a, KT>),
+}
+
+/// A view into an occupied entry in a map. It is part of the `Entry` enum.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, KT: DbMapKeyType> {
+    db_map: RefMut<'a, FileDbXxxInner<KT>>,
+    hash: HashValue,
+    key: KT,
+    key_offset: KeyPieceOffset,
+    prev_key_offset: KeyPieceOffset,
+}
+
+/// A view into a vacant entry in a map. It is part of the `Entry` enum.
+#[derive(Debug)]
+pub struct VacantEntry<'a, KT: DbMapKeyType> {
+    db_map: RefMut<'a, FileDbXxxInner<KT>>,
+    hash: HashValue,
+    key: KT,
+    /// the key piece of the expired key, it is overwritten on insert.
+    expired_key_offset: Option<KeyPieceOffset>,
+}
+
+impl<'a, KT: DbMapKeyType> Entry<'a, KT> {
+    pub(crate) fn new(mut db_map: RefMut<'a, FileDbXxxInner<KT>>, key: KT) -> Result<Self> {
+        let hash = HashValue::new(key.hash_value());
+        let opt = db_map.find_in_hash_buckets_kt(hash, &key)?;
+        Ok(match opt {
+            Some((key_offset, _prev_key_offset))
+                if db_map.is_expired(key_offset, now_millis())? =>
+            {
+                Entry::Vacant(VacantEntry {
+                    db_map,
+                    hash,
+                    key,
+                    expired_key_offset: Some(key_offset),
+                })
+            }
+            Some((key_offset, prev_key_offset)) => Entry::Occupied(OccupiedEntry {
+                db_map,
+                hash,
+                key,
+                key_offset,
+                prev_key_offset,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                db_map,
+                hash,
+                key,
+                expired_key_offset: None,
+            }),
+        })
+    }
+    /// Returns a reference to this entry's key.
+    #[inline]
+    pub fn key(&self) -> &KT {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+    /// Ensures a value is in the entry by inserting the default if empty,
+    /// and returns the value in the entry.
+    #[inline]
+    pub fn or_insert(self, default: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => {
+                entry.insert(default)?;
+                Ok(default.to_vec())
+            }
+        }
+    }
+    /// Ensures a value is in the entry by inserting the result of the default function
+    /// if empty, and returns the value in the entry.
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> Vec<u8>>(self, default: F) -> Result<Vec<u8>> {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => {
+                let value = default();
+                entry.insert(&value)?;
+                Ok(value)
+            }
+        }
+    }
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts into the map.
+    /// The time-to-live of the key is kept, as `append()`, `fetch_add()`
+    /// and `merge()` do.
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut Vec<u8>)>(self, f: F) -> Result<Self> {
+        match self {
+            Entry::Occupied(mut entry) => {
+                let mut value = entry.get()?;
+                f(&mut value);
+                entry.store(&value, None)?;
+                Ok(Entry::Occupied(entry))
+            }
+            Entry::Vacant(entry) => Ok(Entry::Vacant(entry)),
+        }
+    }
+}
+
+impl<KT: DbMapKeyType> OccupiedEntry<'_, KT> {
+    /// Gets a reference to the key in the entry.
+    #[inline]
+    pub fn key(&self) -> &KT {
+        &self.key
+    }
+    /// Gets the value in the entry.
+    #[inline]
+    pub fn get(&self) -> Result<Vec<u8>> {
+        self.db_map.load_value(self.key_offset)
+    }
+    /// Sets the value of the entry, and returns the entry's old value.
+    /// The time-to-live of the key is cleared, as `put()` does.
+    #[inline]
+    pub fn insert(&mut self, value: &[u8]) -> Result<Vec<u8>> {
+        let old_value = self.get()?;
+        self.store(value, Some(0))?;
+        Ok(old_value)
+    }
+    // the expiry time is kept if `expire_at` is `None`.
+    fn store(&mut self, value: &[u8], expire_at: Option<u64>) -> Result<()> {
+        self.db_map
+            .store_value_in_place(self.key_offset, value, expire_at)
+    }
+    /// Takes the value out of the entry, and returns it.
+    #[inline]
+    pub fn remove(mut self) -> Result<Vec<u8>> {
+        self.db_map
+            .delete_found_kt(self.hash, self.key_offset, self.prev_key_offset)
+    }
+}
+
+impl<KT: DbMapKeyType> VacantEntry<'_, KT> {
+    /// Gets a reference to the key that would be used when inserting a value.
+    #[inline]
+    pub fn key(&self) -> &KT {
+        &self.key
+    }
+    /// Take ownership of the key.
+    #[inline]
+    pub fn into_key(self) -> KT {
+        self.key
+    }
+    /// Sets the value of the entry with the VacantEntry's key.
+    #[inline]
+    pub fn insert(mut self, value: &[u8]) -> Result<()> {
+        match self.expired_key_offset {
+            Some(key_offset) => self.db_map.store_value_in_place(key_offset, value, Some(0)),
+            None => self.db_map.insert_new_kt(self.hash, &self.key, value),
+        }
+    }
+}
@@ -2,6 +2,8 @@ use super::super::{FileBufSizeParam, FileDbParams, HashBucketsParam};
 use super::piece::PieceMgr;
 use super::semtype::*;
 use super::vfile::VarFile;
+#[cfg(feature = "rabuf_stats")]
+use super::vfile::VarFileCounters;
 use rabuf::{SmallRead, SmallWrite};
 use std::cell::RefCell;
 use std::convert::TryInto;
@@ -21,10 +23,6 @@ const DEFAULT_HT_SIZE: u64 = 16 * 1024 * 1024;
 pub struct VarFileHtxCache {
     pub file: VarFile,
     buckets_size: u64,
-    #[cfg(feature = "htx_print_hits")]
-    hits: u64,
-    #[cfg(feature = "htx_print_hits")]
-    miss: u64,
 }
 
 impl VarFileHtxCache {
@@ -32,10 +30,6 @@ impl VarFileHtxCache {
         Self {
             file,
             buckets_size: 0,
-            #[cfg(feature = "htx_print_hits")]
-            hits: 0,
-            #[cfg(feature = "htx_print_hits")]
-            miss: 0,
         }
     }
 }
@@ -48,7 +42,7 @@ const HTX_SIZE_ARY: [u32; 0] = [];
 
 /// returns the number of buckets needed to hold the given number of items,
 /// taking the maximum load factor into account.
-fn capacity_to_buckets_size(cap: u64) -> u64 {
+pub fn capacity_to_buckets_size(cap: u64) -> u64 {
     if cap == 0 {
         panic!("capacity should NOT be zero.");
     }
@@ -152,9 +146,8 @@ impl HtxFile {
     }
     #[cfg(feature = "rabuf_stats")]
     #[inline]
-    pub fn buf_stats(&self) -> Vec<(String, i64)> {
-        let locked = RefCell::borrow(&self.0);
-        locked.file.buf_stats()
+    pub fn counters(&self) -> VarFileCounters {
+        RefCell::borrow(&self.0).file.counters
     }
     //
     #[inline]
@@ -169,6 +162,18 @@ impl HtxFile {
         let idx = hash.as_value() % buckets_size;
         locked.file.read_key_piece_offset(idx)
     }
+    /// returns the index of the bucket of the hash.
+    #[inline]
+    pub fn bucket_index(&self, hash: HashValue) -> u64 {
+        let locked = RefCell::borrow(&self.0);
+        hash.as_value() % locked.buckets_size
+    }
+    #[inline]
+    pub fn read_bucket_key_piece_offset(&self, idx: u64) -> Result<KeyPieceOffset> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        debug_assert!(idx < locked.buckets_size);
+        locked.file.read_key_piece_offset(idx)
+    }
     #[inline]
     pub fn write_key_piece_offset(&self, hash: HashValue, offset: KeyPieceOffset) -> Result<()> {
         let mut locked = RefCell::borrow_mut(&self.0);
@@ -178,17 +183,48 @@ impl HtxFile {
             .file
             .write_key_piece_offset(buckets_size, idx, offset)
     }
-    #[cfg(feature = "htx_print_hits")]
-    #[inline]
-    pub fn set_hits(&mut self) {
-        let mut locked = RefCell::borrow_mut(&self.0);
-        locked.hits += 1;
-    }
-    #[cfg(feature = "htx_print_hits")]
-    #[inline]
-    pub fn set_miss(&mut self) {
+    /// rebuilds the hash table with the buckets size.
+    /// the heads of the buckets must be in bucket order.
+    pub fn rebuild(
+        &self,
+        buckets_size: u64,
+        heads: &[(u64, KeyPieceOffset)],
+        item_count: u64,
+    ) -> Result<()> {
         let mut locked = RefCell::borrow_mut(&self.0);
-        locked.miss += 1;
+        let file = &mut locked.file;
+        // clears the old hash table.
+        file.set_file_length(NodePieceOffset::new(HTX_HEADER_SZ))?;
+        file.seek_from_start(NodePieceOffset::new(HTX_HT_SIZE_OFFSET))?;
+        file.write_u64_le(buckets_size)?;
+        #[cfg(feature = "htx_bitmap")]
+        let off = NodePieceOffset::new(HTX_HEADER_SZ + buckets_size * 8 + buckets_size / 8);
+        #[cfg(not(feature = "htx_bitmap"))]
+        let off = NodePieceOffset::new(HTX_HEADER_SZ + buckets_size * 8);
+        file.set_file_length(off)?;
+        let off = NodePieceOffset::new(off.as_value() - 8);
+        file.seek_from_start(off)?;
+        file.write_u64_le(0)?;
+        // store into buckets
+        for &(idx, offset) in heads {
+            debug_assert!(idx < buckets_size);
+            file.seek_from_start(NodePieceOffset::new(HTX_HEADER_SZ + 8 * idx))?;
+            file.write_u64_le(offset.into())?;
+        }
+        // write flags into bitmap
+        #[cfg(feature = "htx_bitmap")]
+        {
+            let mut bitmap = vec![0u8; (buckets_size / 8) as usize];
+            for &(idx, _offset) in heads {
+                bitmap[(idx / 8) as usize] |= 1 << (idx % 8);
+            }
+            let bimap_start = HTX_HEADER_SZ + buckets_size * 8;
+            file.seek_from_start(NodePieceOffset::new(bimap_start))?;
+            file.write_all(&bitmap)?;
+        }
+        file.write_item_count(item_count)?;
+        locked.buckets_size = buckets_size;
+        Ok(())
     }
     #[inline]
     pub fn read_item_count(&self) -> Result<u64> {
@@ -213,16 +249,42 @@ impl HtxFile {
     }
 }
 
-#[cfg(feature = "htx_print_hits")]
-impl Drop for HtxFile {
-    fn drop(&mut self) {
-        let (hits, miss) = {
-            let locked = RefCell::borrow_mut(&self.0);
-            (locked.hits, locked.miss)
-        };
-        let total = hits + miss;
-        let ratio = hits as f64 / total as f64;
-        eprintln!("htx hits: {}/{} [{:.2}%]", hits, total, 100.0 * ratio);
+// verify
+impl HtxFile {
+    /// returns false if the file is too short for the buckets.
+    pub fn verify_file_length(&self, errors: &mut Vec<String>) -> Result<bool> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        let buckets_size = locked.buckets_size;
+        #[cfg(feature = "htx_bitmap")]
+        let expected = HTX_HEADER_SZ + buckets_size * 8 + buckets_size / 8;
+        #[cfg(not(feature = "htx_bitmap"))]
+        let expected = HTX_HEADER_SZ + buckets_size * 8;
+        let file_length = locked.file.seek_to_end::<Node>()?.as_value();
+        if file_length < expected {
+            errors.push(format!(
+                "htx: the file length {file_length} is too short for {buckets_size} buckets"
+            ));
+            return Ok(false);
+        }
+        Ok(true)
+    }
+    /// returns the flag of the bucket in the bitmap, `None` if there is no bitmap.
+    pub fn read_bucket_bitmap_flag(&self, idx: u64) -> Result<Option<bool>> {
+        #[cfg(feature = "htx_bitmap")]
+        {
+            let mut locked = RefCell::borrow_mut(&self.0);
+            let bimap_start = HTX_HEADER_SZ + locked.buckets_size * 8;
+            locked
+                .file
+                .seek_from_start(NodePieceOffset::new(bimap_start + idx / 8))?;
+            let byte = locked.file.read_u8()?;
+            Ok(Some(byte & (1 << (idx % 8)) != 0))
+        }
+        #[cfg(not(feature = "htx_bitmap"))]
+        {
+            let _ = idx;
+            Ok(None)
+        }
     }
 }
 
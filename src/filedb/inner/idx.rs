@@ -0,0 +1,783 @@
+use super::super::super::{DbI64, DbMapKeyType, DbU64, DbVu64};
+use super::super::{FileBufSizeParam, FileDbParams};
+use super::key::KeyFile;
+use super::piece::PieceMgr;
+use super::semtype::*;
+use super::vfile::VarFile;
+#[cfg(feature = "rabuf_stats")]
+use super::vfile::VarFileCounters;
+use rabuf::{SmallRead, SmallWrite};
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::convert::TryInto;
+use std::fs::OpenOptions;
+use std::io::{Read, Result, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::rc::Rc;
+
+type HeaderSignature = [u8; 8];
+
+const CHUNK_SIZE: u32 = 32 * 4 * 1024;
+const IDX_HEADER_SZ: u64 = 128;
+const IDX_HEADER_SIGNATURE: HeaderSignature = [b'a', b'b', b'y', b's', b'd', b'b', b'I', 0u8];
+
+/// the number of key offsets in a leaf node.
+const IDX_NODE_SLOTS: usize = 61;
+/// the number of children in a branch node.
+const IDX_BRANCH_SLOTS: usize = 31;
+/// the byte size of a node: prev, next, count and slots of a leaf.
+/// a branch, count and children, fits in the same size.
+const IDX_NODE_SZ: u64 = 8 * 3 + 8 * IDX_NODE_SLOTS as u64;
+
+const IDX_SIZE_FREE_OFFSET: [u64; 0] = [];
+const IDX_SIZE_ARY: [u32; 0] = [];
+
+/// The ordered index file.
+///
+/// This is a B+tree. The leaf nodes hold the key piece offsets in key order
+/// and are linked for the ordered iteration. The branch nodes hold the child
+/// nodes with the first key of each child. An emptied node is freed, the
+/// nodes are not merged.
+#[derive(Debug)]
+pub struct VarFileIdxCache<KT: DbMapKeyType> {
+    pub file: VarFile,
+    _phantom: PhantomData<KT>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdxFile<KT: DbMapKeyType>(pub Rc<RefCell<VarFileIdxCache<KT>>>);
+
+/// A leaf node of the ordered index.
+#[derive(Debug, Default, Clone)]
+pub struct IdxNode {
+    /// offset of this node in idx-file.
+    pub offset: NodePieceOffset,
+    /// offset of the previous leaf node.
+    pub prev: NodePieceOffset,
+    /// offset of the next leaf node.
+    pub next: NodePieceOffset,
+    /// key piece offsets in key order.
+    pub slots: Vec<KeyPieceOffset>,
+}
+
+/// A branch node of the ordered index.
+#[derive(Debug, Default, Clone)]
+struct IdxBranch {
+    /// offset of this node in idx-file.
+    offset: NodePieceOffset,
+    /// child node offsets with the key piece offset of the first key in the child.
+    children: Vec<(NodePieceOffset, KeyPieceOffset)>,
+}
+
+// the branches from the root to a leaf, with the index of the child taken.
+type IdxPath = Vec<(IdxBranch, usize)>;
+
+/// compares the keys in the index order.
+///
+/// the integer keys are ordered by the value, not by the little-endian
+/// or vu64 bytes that their `Ord` compares.
+pub(crate) fn key_cmp<KT: DbMapKeyType>(a: &KT, b: &KT) -> Ordering {
+    let type_id = TypeId::of::<KT>();
+    let ord = if type_id == TypeId::of::<DbU64>() {
+        le_u64(a.as_bytes()).cmp(&le_u64(b.as_bytes()))
+    } else if type_id == TypeId::of::<DbI64>() {
+        (le_u64(a.as_bytes()) as i64).cmp(&(le_u64(b.as_bytes()) as i64))
+    } else if type_id == TypeId::of::<DbVu64>() {
+        match (vu64::decode(a.as_bytes()), vu64::decode(b.as_bytes())) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => Ordering::Equal,
+        }
+    } else {
+        Ordering::Equal
+    };
+    ord.then_with(|| a.cmp(b))
+}
+
+// the value of the little-endian bytes, as `u64::from(&DbU64)`.
+fn le_u64(bytes: &[u8]) -> u64 {
+    let mut a = [0u8; 8];
+    let len = bytes.len().min(8);
+    a[..len].copy_from_slice(&bytes[..len]);
+    u64::from_le_bytes(a)
+}
+
+impl<KT: DbMapKeyType> IdxFile<KT> {
+    /// returns true if the idx file exists.
+    pub fn exists<P: AsRef<Path>>(path: P, ks_name: &str) -> bool {
+        let mut pb = path.as_ref().to_path_buf();
+        pb.push(format!("{ks_name}.idx"));
+        pb.is_file()
+    }
+    /// opens the idx file. the second of result is true if the file is created now.
+    pub fn open_with_params<P: AsRef<Path>>(
+        path: P,
+        ks_name: &str,
+        sig2: HeaderSignature,
+        params: &FileDbParams,
+    ) -> Result<(Self, bool)> {
+        let piece_mgr = PieceMgr::new(&IDX_SIZE_FREE_OFFSET, &IDX_SIZE_ARY);
+        let mut pb = path.as_ref().to_path_buf();
+        pb.push(format!("{ks_name}.idx"));
+        let std_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(pb)?;
+        let mut file = match params.idx_buf_size {
+            FileBufSizeParam::Size(val) => {
+                let idx_buf_chunk_size = CHUNK_SIZE;
+                let idx_buf_num_chunks = val / idx_buf_chunk_size;
+                VarFile::with_capacity(
+                    piece_mgr,
+                    "idx",
+                    std_file,
+                    idx_buf_chunk_size,
+                    idx_buf_num_chunks.try_into().unwrap(),
+                )?
+            }
+            FileBufSizeParam::PerMille(val) => {
+                VarFile::with_per_mille(piece_mgr, "idx", std_file, CHUNK_SIZE, val)?
+            }
+            FileBufSizeParam::Auto => VarFile::new(piece_mgr, "idx", std_file)?,
+        };
+        let file_length: NodePieceOffset = file.seek_to_end()?;
+        let is_new = file_length.is_zero();
+        if is_new {
+            write_idxf_init_header(&mut file, sig2)?;
+        } else {
+            check_idxf_header(&mut file, sig2)?;
+        }
+        let file_nc = VarFileIdxCache {
+            file,
+            _phantom: PhantomData,
+        };
+        Ok((Self(Rc::new(RefCell::new(file_nc))), is_new))
+    }
+    #[inline]
+    pub fn read_fill_buffer(&self) -> Result<()> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.file.read_fill_buffer()
+    }
+    #[inline]
+    pub fn flush(&self) -> Result<()> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.file.flush()
+    }
+    #[inline]
+    pub fn sync_all(&self) -> Result<()> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.file.sync_all()
+    }
+    #[inline]
+    pub fn sync_data(&self) -> Result<()> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.file.sync_data()
+    }
+    #[cfg(feature = "rabuf_stats")]
+    #[inline]
+    pub fn counters(&self) -> VarFileCounters {
+        RefCell::borrow(&self.0).file.counters
+    }
+    //
+    #[inline]
+    pub fn read_node(&self, offset: NodePieceOffset) -> Result<IdxNode> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.file.read_idx_node(offset)
+    }
+    #[inline]
+    pub fn head_node_offset(&self) -> Result<NodePieceOffset> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.file.read_idx_head_offset()
+    }
+    #[inline]
+    pub fn tail_node_offset(&self) -> Result<NodePieceOffset> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.file.read_idx_tail_offset()
+    }
+    /// inserts the key piece offset at the key order.
+    #[inline]
+    pub fn insert(&self, key: &KT, offset: KeyPieceOffset, key_file: &KeyFile<KT>) -> Result<()> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.insert(key, offset, key_file)
+    }
+    /// removes the key piece offset.
+    #[inline]
+    pub fn remove(&self, key: &KT, offset: KeyPieceOffset, key_file: &KeyFile<KT>) -> Result<()> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.remove(key, offset, key_file)
+    }
+    /// rebuilds the tree from the sorted key piece offsets.
+    #[inline]
+    pub fn rebuild(&self, sorted: Vec<(KT, KeyPieceOffset)>) -> Result<()> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.rebuild(sorted)
+    }
+    /// returns the position of the first key that is not less than `key`,
+    /// or greater than `key` if `inclusive` is false.
+    #[inline]
+    pub fn lower_bound(
+        &self,
+        key: &KT,
+        inclusive: bool,
+        key_file: &KeyFile<KT>,
+    ) -> Result<(NodePieceOffset, usize)> {
+        let mut locked = RefCell::borrow_mut(&self.0);
+        locked.lower_bound(key, inclusive, key_file)
+    }
+}
+
+impl<KT: DbMapKeyType> VarFileIdxCache<KT> {
+    // the leaf where the key should be, and the branches on the way.
+    fn descend(&mut self, key: &KT, key_file: &KeyFile<KT>) -> Result<(IdxPath, NodePieceOffset)> {
+        let (mut node_offset, height) = self.file.read_idx_root()?;
+        let mut path = Vec::new();
+        for _ in 1..height {
+            let branch = self.file.read_idx_branch(node_offset)?;
+            let idx = Self::search_children(&branch.children, key, key_file)?;
+            node_offset = branch.children[idx].0;
+            path.push((branch, idx));
+        }
+        Ok((path, node_offset))
+    }
+    // the last child whose first key is not greater than the key, or the first child.
+    fn search_children(
+        children: &[(NodePieceOffset, KeyPieceOffset)],
+        key: &KT,
+        key_file: &KeyFile<KT>,
+    ) -> Result<usize> {
+        let mut lo = 0;
+        let mut hi = children.len();
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let mid_key = key_file.read_piece_only_key(children[mid].1)?;
+            if key_cmp(&mid_key, key) == Ordering::Greater {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        Ok(lo.saturating_sub(1))
+    }
+    // the slot position that is not less than the key.
+    fn search_slots(
+        slots: &[KeyPieceOffset],
+        key: &KT,
+        inclusive: bool,
+        key_file: &KeyFile<KT>,
+    ) -> Result<usize> {
+        let mut lo = 0;
+        let mut hi = slots.len();
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let mid_key = key_file.read_piece_only_key(slots[mid])?;
+            let go_right = match key_cmp(&mid_key, key) {
+                Ordering::Less => true,
+                Ordering::Equal => !inclusive,
+                Ordering::Greater => false,
+            };
+            if go_right {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        Ok(lo)
+    }
+    fn alloc_node(&mut self) -> Result<NodePieceOffset> {
+        let free_1st = self.file.read_idx_free_offset()?;
+        if !free_1st.is_zero() {
+            let node = self.file.read_idx_node(free_1st)?;
+            self.file.write_idx_free_offset(node.next)?;
+            Ok(free_1st)
+        } else {
+            self.file.seek_to_end()
+        }
+    }
+    fn free_node(&mut self, offset: NodePieceOffset) -> Result<()> {
+        let free_1st = self.file.read_idx_free_offset()?;
+        let node = IdxNode {
+            offset,
+            next: free_1st,
+            ..Default::default()
+        };
+        self.file.write_idx_node(&node)?;
+        self.file.write_idx_free_offset(offset)
+    }
+    fn set_prev_of(&mut self, offset: NodePieceOffset, prev: NodePieceOffset) -> Result<()> {
+        if offset.is_zero() {
+            self.file.write_idx_tail_offset(prev)
+        } else {
+            self.file.seek_from_start(offset)?;
+            self.file.write_u64_le(prev.into())
+        }
+    }
+    fn set_next_of(&mut self, offset: NodePieceOffset, next: NodePieceOffset) -> Result<()> {
+        if offset.is_zero() {
+            self.file.write_idx_head_offset(next)
+        } else {
+            self.file
+                .seek_from_start(NodePieceOffset::new(offset.as_value() + 8))?;
+            self.file.write_u64_le(next.into())
+        }
+    }
+    fn count_up(&mut self) -> Result<()> {
+        let val = self.file.read_idx_item_count()?;
+        self.file.write_idx_item_count(val + 1)
+    }
+    fn count_down(&mut self) -> Result<()> {
+        let val = self.file.read_idx_item_count()?;
+        if val > 0 {
+            self.file.write_idx_item_count(val - 1)
+        } else {
+            Ok(())
+        }
+    }
+    // sets the first key of the child on the path, up to the branch where
+    // the child is not the first one.
+    fn update_first_key(
+        &mut self,
+        path: &mut [(IdxBranch, usize)],
+        first: KeyPieceOffset,
+    ) -> Result<()> {
+        for (branch, idx) in path.iter_mut().rev() {
+            branch.children[*idx].1 = first;
+            self.file.write_idx_branch(branch)?;
+            if *idx != 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+    // inserts the right node next to the child on the path, splitting the
+    // full branches and growing the root.
+    fn insert_child(
+        &mut self,
+        mut path: IdxPath,
+        mut left: (NodePieceOffset, KeyPieceOffset),
+        mut right: (NodePieceOffset, KeyPieceOffset),
+    ) -> Result<()> {
+        while let Some((mut branch, idx)) = path.pop() {
+            branch.children.insert(idx + 1, right);
+            if branch.children.len() <= IDX_BRANCH_SLOTS {
+                return self.file.write_idx_branch(&branch);
+            }
+            // split the branch into two branches.
+            let new_offset = self.alloc_node()?;
+            let right_children = branch.children.split_off(branch.children.len() / 2);
+            right = (new_offset, right_children[0].1);
+            left = (branch.offset, branch.children[0].1);
+            self.file.write_idx_branch(&branch)?;
+            self.file.write_idx_branch(&IdxBranch {
+                offset: new_offset,
+                children: right_children,
+            })?;
+        }
+        let root_offset = self.alloc_node()?;
+        self.file.write_idx_branch(&IdxBranch {
+            offset: root_offset,
+            children: vec![left, right],
+        })?;
+        let (_, height) = self.file.read_idx_root()?;
+        self.file.write_idx_root(root_offset, height + 1)
+    }
+    // removes the child on the path, and the branches that become empty.
+    fn remove_child(&mut self, mut path: IdxPath) -> Result<()> {
+        while let Some((mut branch, idx)) = path.pop() {
+            branch.children.remove(idx);
+            if branch.children.is_empty() {
+                self.free_node(branch.offset)?;
+                continue;
+            }
+            self.file.write_idx_branch(&branch)?;
+            if idx == 0 {
+                let first = branch.children[0].1;
+                self.update_first_key(&mut path, first)?;
+            }
+            return self.shrink_root();
+        }
+        // the tree is empty.
+        self.file.write_idx_root(NodePieceOffset::new(0), 0)
+    }
+    // lowers the root while it is a branch with only one child.
+    fn shrink_root(&mut self) -> Result<()> {
+        let (mut root, mut height) = self.file.read_idx_root()?;
+        while height > 1 {
+            let branch = self.file.read_idx_branch(root)?;
+            if branch.children.len() > 1 {
+                break;
+            }
+            self.free_node(root)?;
+            root = branch.children[0].0;
+            height -= 1;
+        }
+        self.file.write_idx_root(root, height)
+    }
+    //
+    fn insert(&mut self, key: &KT, offset: KeyPieceOffset, key_file: &KeyFile<KT>) -> Result<()> {
+        let (root, _) = self.file.read_idx_root()?;
+        if root.is_zero() {
+            let node_offset = self.alloc_node()?;
+            let node = IdxNode {
+                offset: node_offset,
+                slots: vec![offset],
+                ..Default::default()
+            };
+            self.file.write_idx_node(&node)?;
+            self.file.write_idx_head_offset(node_offset)?;
+            self.file.write_idx_tail_offset(node_offset)?;
+            self.file.write_idx_root(node_offset, 1)?;
+            return self.count_up();
+        }
+        let (mut path, leaf_offset) = self.descend(key, key_file)?;
+        let mut node = self.file.read_idx_node(leaf_offset)?;
+        let pos = Self::search_slots(&node.slots, key, true, key_file)?;
+        node.slots.insert(pos, offset);
+        if pos == 0 {
+            self.update_first_key(&mut path, offset)?;
+        }
+        if node.slots.len() <= IDX_NODE_SLOTS {
+            self.file.write_idx_node(&node)?;
+        } else {
+            // split the leaf into two leaves.
+            let new_offset = self.alloc_node()?;
+            let right_slots = node.slots.split_off(node.slots.len() / 2);
+            let right = IdxNode {
+                offset: new_offset,
+                prev: node.offset,
+                next: node.next,
+                slots: right_slots,
+            };
+            self.set_prev_of(right.next, new_offset)?;
+            node.next = new_offset;
+            self.file.write_idx_node(&node)?;
+            self.file.write_idx_node(&right)?;
+            self.insert_child(
+                path,
+                (node.offset, node.slots[0]),
+                (right.offset, right.slots[0]),
+            )?;
+        }
+        self.count_up()
+    }
+    fn remove(&mut self, key: &KT, offset: KeyPieceOffset, key_file: &KeyFile<KT>) -> Result<()> {
+        let (root, _) = self.file.read_idx_root()?;
+        if root.is_zero() {
+            return Ok(());
+        }
+        let (mut path, leaf_offset) = self.descend(key, key_file)?;
+        let mut node = self.file.read_idx_node(leaf_offset)?;
+        let pos = match node.slots.iter().position(|&a| a == offset) {
+            Some(pos) => pos,
+            None => return Ok(()),
+        };
+        node.slots.remove(pos);
+        if node.slots.is_empty() {
+            // unlink the empty leaf.
+            self.set_next_of(node.prev, node.next)?;
+            self.set_prev_of(node.next, node.prev)?;
+            self.free_node(node.offset)?;
+            self.remove_child(path)?;
+        } else {
+            self.file.write_idx_node(&node)?;
+            if pos == 0 {
+                self.update_first_key(&mut path, node.slots[0])?;
+            }
+        }
+        self.count_down()
+    }
+    fn rebuild(&mut self, sorted: Vec<(KT, KeyPieceOffset)>) -> Result<()> {
+        let count = sorted.len() as u64;
+        self.file
+            .set_file_length(NodePieceOffset::new(IDX_HEADER_SZ))?;
+        self.file.write_idx_head_offset(NodePieceOffset::new(0))?;
+        self.file.write_idx_tail_offset(NodePieceOffset::new(0))?;
+        self.file.write_idx_free_offset(NodePieceOffset::new(0))?;
+        self.file.write_idx_root(NodePieceOffset::new(0), 0)?;
+        //
+        // the leaves, and the branches level by level up to the root.
+        let mut next_offset = IDX_HEADER_SZ;
+        let mut level = Vec::new();
+        let mut prev = NodePieceOffset::new(0);
+        let num_nodes = (sorted.len() + IDX_NODE_SLOTS - 1) / IDX_NODE_SLOTS;
+        for (i, chunk) in sorted.chunks(IDX_NODE_SLOTS).enumerate() {
+            let offset = NodePieceOffset::new(next_offset);
+            next_offset += IDX_NODE_SZ;
+            let next = if i + 1 < num_nodes {
+                NodePieceOffset::new(next_offset)
+            } else {
+                NodePieceOffset::new(0)
+            };
+            let node = IdxNode {
+                offset,
+                prev,
+                next,
+                slots: chunk.iter().map(|a| a.1).collect(),
+            };
+            self.file.write_idx_node(&node)?;
+            level.push((offset, chunk[0].1));
+            prev = offset;
+        }
+        if let (Some(head), Some(tail)) = (level.first(), level.last()) {
+            let (head, tail) = (head.0, tail.0);
+            self.file.write_idx_head_offset(head)?;
+            self.file.write_idx_tail_offset(tail)?;
+        }
+        let mut height = 1;
+        while level.len() > 1 {
+            let mut upper = Vec::new();
+            for chunk in level.chunks(IDX_BRANCH_SLOTS) {
+                let offset = NodePieceOffset::new(next_offset);
+                next_offset += IDX_NODE_SZ;
+                self.file.write_idx_branch(&IdxBranch {
+                    offset,
+                    children: chunk.to_vec(),
+                })?;
+                upper.push((offset, chunk[0].1));
+            }
+            level = upper;
+            height += 1;
+        }
+        if let Some(&(root, _)) = level.first() {
+            self.file.write_idx_root(root, height)?;
+        }
+        self.file.write_idx_item_count(count)
+    }
+    fn lower_bound(
+        &mut self,
+        key: &KT,
+        inclusive: bool,
+        key_file: &KeyFile<KT>,
+    ) -> Result<(NodePieceOffset, usize)> {
+        let (root, _) = self.file.read_idx_root()?;
+        if root.is_zero() {
+            return Ok((NodePieceOffset::new(0), 0));
+        }
+        let (_, leaf_offset) = self.descend(key, key_file)?;
+        let mut node = self.file.read_idx_node(leaf_offset)?;
+        loop {
+            let pos = Self::search_slots(&node.slots, key, inclusive, key_file)?;
+            if pos < node.slots.len() || node.next.is_zero() {
+                return Ok((node.offset, pos));
+            }
+            node = self.file.read_idx_node(node.next)?;
+        }
+    }
+}
+
+/**
+write initiale header to file.
+
+## header map
+
+The idx header size is 128 bytes.
+
+```text
++--------+-------+-------------+---------------------------+
+| offset | bytes | name        | comment                   |
++--------+-------+-------------+---------------------------+
+| 0      | 8     | signature1  | b"abysdbI\0"              |
+| 8      | 8     | signature2  | 8 bytes type signature    |
+| 16     | 8     | head        | offset of the first leaf  |
+| 24     | 8     | tail        | offset of the last leaf   |
+| 32     | 8     | free        | offset of free leaf list  |
+| 40     | 8     | count       | count of items            |
+| 48     | 8     | root        | offset of the root node   |
+| 56     | 8     | height      | height of the tree        |
+| 64     | 64    | reserve1    |                           |
++--------+-------+-------------+---------------------------+
+```
+
+- signature1: always fixed 8 bytes
+- signature2: 8 bytes type signature
+- height: 0 is the empty tree, 1 is the root of a leaf, and more is the root of a branch
+
+*/
+const IDX_HEAD_OFFSET: u64 = 16;
+const IDX_TAIL_OFFSET: u64 = 24;
+const IDX_FREE_OFFSET: u64 = 32;
+const IDX_ITEM_COUNT_OFFSET: u64 = 40;
+const IDX_ROOT_OFFSET: u64 = 48;
+
+fn write_idxf_init_header(file: &mut VarFile, signature2: HeaderSignature) -> Result<()> {
+    file.seek_from_start(NodePieceOffset::new(0))?;
+    // signature1
+    file.write_all(&IDX_HEADER_SIGNATURE)?;
+    // signature2
+    file.write_all(&signature2)?;
+    // head .. rserve1
+    file.write_all(&[0u8; 112])?;
+    //
+    Ok(())
+}
+
+fn check_idxf_header(file: &mut VarFile, signature2: HeaderSignature) -> Result<()> {
+    file.seek_from_start(NodePieceOffset::new(0))?;
+    // signature1
+    let mut sig1 = [0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8];
+    file.read_exact(&mut sig1)?;
+    if sig1 != IDX_HEADER_SIGNATURE {
+        return Err(invalid_data("invalid header signature1".to_string()));
+    }
+    // signature2
+    let mut sig2 = [0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8];
+    file.read_exact(&mut sig2)?;
+    if sig2 != signature2 {
+        return Err(invalid_data(format!(
+            "invalid header signature2, type signature: {sig2:?}"
+        )));
+    }
+    //
+    Ok(())
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+impl VarFile {
+    fn read_idx_head_offset(&mut self) -> Result<NodePieceOffset> {
+        self.seek_from_start(NodePieceOffset::new(IDX_HEAD_OFFSET))?;
+        self.read_u64_le().map(NodePieceOffset::new)
+    }
+    fn write_idx_head_offset(&mut self, offset: NodePieceOffset) -> Result<()> {
+        self.seek_from_start(NodePieceOffset::new(IDX_HEAD_OFFSET))?;
+        self.write_u64_le(offset.into())
+    }
+    fn read_idx_tail_offset(&mut self) -> Result<NodePieceOffset> {
+        self.seek_from_start(NodePieceOffset::new(IDX_TAIL_OFFSET))?;
+        self.read_u64_le().map(NodePieceOffset::new)
+    }
+    fn write_idx_tail_offset(&mut self, offset: NodePieceOffset) -> Result<()> {
+        self.seek_from_start(NodePieceOffset::new(IDX_TAIL_OFFSET))?;
+        self.write_u64_le(offset.into())
+    }
+    fn read_idx_free_offset(&mut self) -> Result<NodePieceOffset> {
+        self.seek_from_start(NodePieceOffset::new(IDX_FREE_OFFSET))?;
+        self.read_u64_le().map(NodePieceOffset::new)
+    }
+    fn write_idx_free_offset(&mut self, offset: NodePieceOffset) -> Result<()> {
+        self.seek_from_start(NodePieceOffset::new(IDX_FREE_OFFSET))?;
+        self.write_u64_le(offset.into())
+    }
+    fn read_idx_item_count(&mut self) -> Result<u64> {
+        self.seek_from_start(NodePieceOffset::new(IDX_ITEM_COUNT_OFFSET))?;
+        self.read_u64_le()
+    }
+    fn write_idx_item_count(&mut self, val: u64) -> Result<()> {
+        self.seek_from_start(NodePieceOffset::new(IDX_ITEM_COUNT_OFFSET))?;
+        self.write_u64_le(val)
+    }
+    fn read_idx_root(&mut self) -> Result<(NodePieceOffset, u64)> {
+        self.seek_from_start(NodePieceOffset::new(IDX_ROOT_OFFSET))?;
+        let root = NodePieceOffset::new(self.read_u64_le()?);
+        let height = self.read_u64_le()?;
+        if root.is_zero() != (height == 0) {
+            return Err(invalid_data(format!(
+                "invalid root node: {root:?}, height: {height}"
+            )));
+        }
+        Ok((root, height))
+    }
+    fn write_idx_root(&mut self, root: NodePieceOffset, height: u64) -> Result<()> {
+        self.seek_from_start(NodePieceOffset::new(IDX_ROOT_OFFSET))?;
+        self.write_u64_le(root.into())?;
+        self.write_u64_le(height)
+    }
+    fn read_idx_node(&mut self, offset: NodePieceOffset) -> Result<IdxNode> {
+        debug_assert!(!offset.is_zero());
+        self.seek_from_start(offset)?;
+        let prev = NodePieceOffset::new(self.read_u64_le()?);
+        let next = NodePieceOffset::new(self.read_u64_le()?);
+        let count = self.read_u64_le()? as usize;
+        if count > IDX_NODE_SLOTS {
+            return Err(invalid_data(format!(
+                "invalid leaf node: {offset:?}, count: {count}"
+            )));
+        }
+        let mut slots = Vec::with_capacity(count + 1);
+        for _ in 0..count {
+            slots.push(KeyPieceOffset::new(self.read_u64_le()?));
+        }
+        Ok(IdxNode {
+            offset,
+            prev,
+            next,
+            slots,
+        })
+    }
+    fn write_idx_node(&mut self, node: &IdxNode) -> Result<()> {
+        debug_assert!(!node.offset.is_zero());
+        debug_assert!(node.slots.len() <= IDX_NODE_SLOTS);
+        self.seek_from_start(node.offset)?;
+        self.write_u64_le(node.prev.into())?;
+        self.write_u64_le(node.next.into())?;
+        self.write_u64_le(node.slots.len() as u64)?;
+        for i in 0..IDX_NODE_SLOTS {
+            let val = node.slots.get(i).map(|&a| a.into()).unwrap_or(0);
+            self.write_u64_le(val)?;
+        }
+        Ok(())
+    }
+    fn read_idx_branch(&mut self, offset: NodePieceOffset) -> Result<IdxBranch> {
+        debug_assert!(!offset.is_zero());
+        self.seek_from_start(offset)?;
+        let count = self.read_u64_le()? as usize;
+        if count == 0 || count > IDX_BRANCH_SLOTS {
+            return Err(invalid_data(format!(
+                "invalid branch node: {offset:?}, count: {count}"
+            )));
+        }
+        let mut children = Vec::with_capacity(count + 1);
+        for _ in 0..count {
+            let child = NodePieceOffset::new(self.read_u64_le()?);
+            let first = KeyPieceOffset::new(self.read_u64_le()?);
+            children.push((child, first));
+        }
+        Ok(IdxBranch { offset, children })
+    }
+    fn write_idx_branch(&mut self, branch: &IdxBranch) -> Result<()> {
+        debug_assert!(!branch.offset.is_zero());
+        debug_assert!(!branch.children.is_empty());
+        debug_assert!(branch.children.len() <= IDX_BRANCH_SLOTS);
+        self.seek_from_start(branch.offset)?;
+        self.write_u64_le(branch.children.len() as u64)?;
+        for i in 0..IDX_BRANCH_SLOTS {
+            let (child, first) = branch.children.get(i).copied().unwrap_or_default();
+            self.write_u64_le(child.into())?;
+            self.write_u64_le(first.into())?;
+        }
+        Ok(())
+    }
+}
+
+/*
+```text
+leaf node:
++--------+-------+-------------+-----------------------------------+
+| offset | bytes | name        | comment                           |
++--------+-------+-------------+-----------------------------------+
+| 0      | 8     | prev        | previous leaf node offset         |
+| 8      | 8     | next        | next leaf node offset             |
+| 16     | 8     | count       | count of used slots               |
+| 24     | 8     | slot1       | key piece offset                  |
+| --     | --    | --          | --                                |
+| 504    | 8     | slot61      | key piece offset                  |
++--------+-------+-------------+-----------------------------------+
+
+branch node:
++--------+-------+-------------+-----------------------------------+
+| offset | bytes | name        | comment                           |
++--------+-------+-------------+-----------------------------------+
+| 0      | 8     | count       | count of used children            |
+| 8      | 8     | child1      | child node offset                 |
+| 16     | 8     | first1      | key piece offset of the first key |
+| --     | --    | --          | --                                |
+| 488    | 8     | child31     | child node offset                 |
+| 496    | 8     | first31     | key piece offset of the first key |
+| 504    | 8     | reserve     |                                   |
++--------+-------+-------------+-----------------------------------+
+```
+*/
@@ -2,9 +2,10 @@ use super::super::super::DbMapKeyType;
 use super::super::{FileBufSizeParam, FileDbParams};
 use super::piece::{PieceA, PieceMgr, PieceOffsetIter};
 use super::semtype::*;
-use super::vfile::VarFile;
+use super::vfile::{VarFile, VarFileCounters};
 use rabuf::{SmallRead, SmallWrite};
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::fs::OpenOptions;
 use std::io::{Read, Result, Write};
@@ -76,6 +77,14 @@ impl<KT: DbMapKeyType> KeyFile<KT> {
         locked.0.read_fill_buffer()
     }
     #[inline]
+    pub fn counters(&self) -> VarFileCounters {
+        RefCell::borrow(&self.0).0.counters
+    }
+    #[inline]
+    pub fn reset_counters(&self) {
+        RefCell::borrow_mut(&self.0).0.counters = VarFileCounters::default();
+    }
+    #[inline]
     pub fn flush(&self) -> Result<()> {
         let mut locked = self.0.borrow_mut();
         locked.0.flush()
@@ -90,13 +99,6 @@ impl<KT: DbMapKeyType> KeyFile<KT> {
         let mut locked = self.0.borrow_mut();
         locked.0.sync_data()
     }
-    #[cfg(feature = "rabuf_stats")]
-    #[inline]
-    pub fn buf_stats(&self) -> Vec<(String, i64)> {
-        let locked = self.0.borrow();
-        locked.0.buf_stats()
-    }
-    //
     pub(crate) fn piece_offset_iter(&self) -> KeyPieceOffsetIter {
         KeyPieceOffsetIter::new(self).unwrap()
     }
@@ -121,7 +123,6 @@ impl<KT: DbMapKeyType> KeyFile<KT> {
         let mut locked = self.0.borrow_mut();
         locked.read_piece_only_value_offset(offset)
     }
-    /*
     #[inline]
     pub fn read_piece_only_bucket_next_offset(
         &self,
@@ -130,7 +131,6 @@ impl<KT: DbMapKeyType> KeyFile<KT> {
         let mut locked = self.0.borrow_mut();
         locked.read_piece_only_bucket_next_offset(offset)
     }
-    */
     #[inline]
     pub fn read_piece(&self, offset: KeyPieceOffset) -> Result<KeyPiece<KT>> {
         let mut locked = self.0.borrow_mut();
@@ -156,6 +156,73 @@ impl<KT: DbMapKeyType> KeyFile<KT> {
         let mut locked = self.0.borrow_mut();
         locked.add_key_piece(key, value_offset, next_offset)
     }
+    /// adds the new key piece that has the room for the bucket next offset,
+    /// that is less than the offset of the piece. the next offset is written later.
+    #[inline]
+    pub fn add_key_piece_with_reserved_next(
+        &self,
+        key: &KT,
+        value_offset: ValuePieceOffset,
+    ) -> Result<KeyPiece<KT>> {
+        let mut locked = self.0.borrow_mut();
+        // the new piece is at the end of the file or in a free piece before it.
+        let end_offset: KeyPieceOffset = locked.0.seek_to_end()?;
+        locked.add_key_piece(key, value_offset, end_offset)
+    }
+}
+
+// verify
+impl<KT: DbMapKeyType> KeyFile<KT> {
+    /// scans the pieces and the free lists.
+    /// returns the piece sizes by the offset, and the offsets of the free pieces.
+    pub fn verify_pieces(
+        &self,
+        errors: &mut Vec<String>,
+    ) -> Result<(HashMap<u64, u32>, HashSet<u64>)> {
+        let mut locked = self.0.borrow_mut();
+        let pieces =
+            locked
+                .0
+                .verify_scan_pieces(KeyPieceOffset::new(DAT_HEADER_SZ), "key", errors)?;
+        let free = locked
+            .0
+            .verify_free_piece_lists::<Key>(&pieces, "key", errors)?;
+        Ok((pieces, free))
+    }
+}
+
+// repair
+impl<KT: DbMapKeyType> KeyFile<KT> {
+    /// rebuilds the free lists from the pieces that are not live.
+    /// returns the count of the free pieces.
+    pub fn repair_free_piece_lists(
+        &self,
+        pieces: &HashMap<u64, u32>,
+        live: &HashSet<u64>,
+    ) -> Result<u64> {
+        let mut locked = self.0.borrow_mut();
+        locked
+            .0
+            .repair_free_piece_lists(KeyPieceOffset::new(DAT_HEADER_SZ), pieces, live)
+    }
+    /// reads the piece, and returns `None` if the piece does not fit in the piece size.
+    pub fn read_valid_piece(
+        &self,
+        offset: KeyPieceOffset,
+        piece_size: u32,
+    ) -> Result<Option<KeyPiece<KT>>> {
+        let mut locked = self.0.borrow_mut();
+        let key_len = locked.read_piece_only_key_length(offset)?;
+        if key_len.as_value() >= piece_size {
+            return Ok(None);
+        }
+        let piece = locked.read_piece(offset)?;
+        let (encorded_piece_len, piece_len, _key_len) = piece.encoded_piece_size();
+        if encorded_piece_len as u64 + piece_len as u64 > piece_size as u64 {
+            return Ok(None);
+        }
+        Ok(Some(piece))
+    }
 }
 
 // for debug
@@ -471,6 +538,7 @@ impl<KT: DbMapKeyType> VarFileKeyCache<KT> {
                 // delete old and add new
                 // old
                 self.0.push_free_piece_list(piece.offset, old_piece_size)?;
+                self.0.counters.relocations += 1;
             }
         }
         // add new.
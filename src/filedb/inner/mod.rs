@@ -6,16 +6,22 @@ use std::io::Result;
 use std::path::{Path, PathBuf};
 
 pub(crate) mod dbxxx;
+pub(crate) mod entry;
 pub(crate) mod semtype;
+pub(crate) mod valio;
 
+pub(crate) mod backup;
+pub(crate) mod cdc;
 mod piece;
 //mod tr;
 
-//mod idx;
+mod idx;
 mod key;
 mod val;
 mod vfile;
 
+pub(crate) use val::VALUE_LEN_MAX;
+
 //#[cfg(feature = "htx")]
 mod htx;
 
@@ -68,6 +74,25 @@ impl FileDbInner {
     pub fn sync_data(&self) -> Result<()> {
         self.applay_all(|o| o.sync_data())
     }
+    /// sums the buffer statistics of all maps per component.
+    #[cfg(feature = "rabuf_stats")]
+    pub fn buf_stats(&self) -> Vec<(String, i64)> {
+        use super::CheckFileDbMap;
+        let mut all: Vec<Vec<(String, i64)>> = Vec::new();
+        all.extend(self.db_bytes_map.values().map(|m| m.buf_stats()));
+        all.extend(self.db_string_map.values().map(|m| m.buf_stats()));
+        all.extend(self.db_i64_map.values().map(|m| m.buf_stats()));
+        all.extend(self.db_u64_map.values().map(|m| m.buf_stats()));
+        all.extend(self.db_vu64_map.values().map(|m| m.buf_stats()));
+        let mut vec: Vec<(String, i64)> = Vec::new();
+        for (name, val) in all.into_iter().flatten() {
+            match vec.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, sum)) => *sum += val,
+                None => vec.push((name, val)),
+            }
+        }
+        vec
+    }
     fn applay_all<F>(&self, func: F) -> Result<()>
     where
         F: Fn(&mut dyn DbXxxBase) -> Result<()>,
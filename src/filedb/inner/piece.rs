@@ -1,6 +1,8 @@
 use super::semtype::*;
 use super::vfile::VarFile;
 use rabuf::{SmallRead, SmallWrite};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::io::Result;
 
 /// piece manager. managing free piece list.
@@ -35,6 +37,20 @@ impl PieceMgr {
         );
         self.free_list_offset[self.free_list_offset.len() - 1]
     }
+    /// returns the index of the free list of the piece size, or `None` if the size is invalid.
+    /// this does not panic, for verifying the broken file.
+    pub fn free_list_index(&self, piece_size: u32) -> Option<usize> {
+        let last = self.size_ary.len() - 1;
+        if piece_size == 0 {
+            None
+        } else if let Some(i) = self.size_ary.iter().position(|&sz| sz == piece_size) {
+            Some(i)
+        } else if piece_size > self.size_ary[last - 1] {
+            Some(last)
+        } else {
+            None
+        }
+    }
     pub fn is_large_piece_size<T>(&self, piece_size: PieceSize<T>) -> bool {
         let piece_size = piece_size.as_value();
         piece_size >= self.size_ary[self.size_ary.len() - 1]
@@ -155,6 +171,7 @@ impl VarFile {
         let free_1st = self.read_free_piece_offset_on_header(new_piece_size)?;
         if !new_piece_size.is_large_piece_size(&self.piece_mgr) {
             if !free_1st.is_zero() {
+                self.counters.free_list_reuses += 1;
                 let free_next = {
                     let (piece_size, free_next) = self.read_free_piece_size_next(free_1st)?;
                     self.write_piece_clear(free_1st, piece_size)?;
@@ -164,7 +181,11 @@ impl VarFile {
             }
             Ok(free_1st)
         } else {
-            self.pop_free_piece_list_large(new_piece_size, free_1st)
+            let piece_offset = self.pop_free_piece_list_large(new_piece_size, free_1st)?;
+            if !piece_offset.is_zero() {
+                self.counters.free_list_reuses += 1;
+            }
+            Ok(piece_offset)
         }
     }
 
@@ -217,6 +238,117 @@ impl VarFile {
     }
 }
 
+// verify
+impl VarFile {
+    /// scans the all pieces from the `start` to the end of the file.
+    /// returns the piece sizes by the offset, the scan stops at the invalid piece size.
+    pub fn verify_scan_pieces<T: Copy + PartialEq>(
+        &mut self,
+        start: PieceOffset<T>,
+        name: &str,
+        errors: &mut Vec<String>,
+    ) -> Result<HashMap<u64, u32>> {
+        let end = self.seek_to_end::<T>()?.as_value();
+        let mut pieces = HashMap::new();
+        let mut offset = start.as_value();
+        while offset < end {
+            self.seek_from_start(PieceOffset::<T>::new(offset))?;
+            let piece_size: PieceSize<T> = self.read_piece_size()?;
+            let size = piece_size.as_value();
+            if self.piece_mgr.free_list_index(size).is_none() || offset + size as u64 > end {
+                errors.push(format!("{name}: invalid piece size {size} at {offset}"));
+                break;
+            }
+            pieces.insert(offset, size);
+            offset += size as u64;
+        }
+        Ok(pieces)
+    }
+    /// walks the all free piece lists, and returns the offsets of the free pieces.
+    pub fn verify_free_piece_lists<T: Copy + PartialEq>(
+        &mut self,
+        pieces: &HashMap<u64, u32>,
+        name: &str,
+        errors: &mut Vec<String>,
+    ) -> Result<HashSet<u64>> {
+        let mut free = HashSet::new();
+        let free_list_offset = self.piece_mgr.free_list_offset;
+        for (i, &list_offset) in free_list_offset.iter().enumerate() {
+            self.seek_from_start(PieceOffset::<T>::new(list_offset))?;
+            let mut offset = self.read_u64_le()?;
+            while offset != 0 {
+                let size = match pieces.get(&offset) {
+                    Some(&size) => size,
+                    None => {
+                        errors.push(format!("{name}: free list {i}: {offset} is not a piece"));
+                        break;
+                    }
+                };
+                if !free.insert(offset) {
+                    errors.push(format!(
+                        "{name}: free list {i}: the list does not end, {offset} is reached again"
+                    ));
+                    break;
+                }
+                if self.piece_mgr.free_list_index(size) != Some(i) {
+                    errors.push(format!(
+                        "{name}: free list {i}: the piece size {size} at {offset} is of another list"
+                    ));
+                }
+                self.seek_from_start(PieceOffset::<T>::new(offset))?;
+                let _piece_size: PieceSize<T> = self.read_piece_size()?;
+                let len = self.read_key_len()?;
+                if !len.is_zero() {
+                    errors.push(format!(
+                        "{name}: free list {i}: the piece at {offset} is not free"
+                    ));
+                }
+                let next: PieceOffset<T> = self.read_free_piece_offset()?;
+                offset = next.as_value();
+            }
+        }
+        Ok(free)
+    }
+}
+
+// repair
+impl VarFile {
+    /// truncates the broken tail after the scanned pieces, and rebuilds the all free
+    /// piece lists from the pieces that are not live. returns the count of the free pieces.
+    pub fn repair_free_piece_lists<T: Copy + PartialEq + PartialOrd>(
+        &mut self,
+        start: PieceOffset<T>,
+        pieces: &HashMap<u64, u32>,
+        live: &HashSet<u64>,
+    ) -> Result<u64> {
+        let end = pieces
+            .iter()
+            .map(|(&offset, &size)| offset + size as u64)
+            .max()
+            .unwrap_or(start.as_value());
+        self.set_file_length(PieceOffset::<T>::new(end))?;
+        let free_list_offset = self.piece_mgr.free_list_offset;
+        for &list_offset in free_list_offset {
+            self.seek_from_start(PieceOffset::<T>::new(list_offset))?;
+            self.write_u64_le(0)?;
+        }
+        let mut dead: Vec<u64> = pieces
+            .keys()
+            .filter(|offset| !live.contains(offset))
+            .copied()
+            .collect();
+        // pushes from the end, so the lists start from the smaller offset.
+        dead.sort_unstable_by_key(|&offset| Reverse(offset));
+        for &offset in &dead {
+            self.push_free_piece_list(
+                PieceOffset::<T>::new(offset),
+                PieceSize::<T>::new(pieces[&offset]),
+            )?;
+        }
+        Ok(dead.len() as u64)
+    }
+}
+
 pub(crate) trait PieceA<T> {
     fn piece_offset_start(&self) -> Result<PieceOffset<T>>;
     fn piece_offset_end(&self) -> Result<PieceOffset<T>>;
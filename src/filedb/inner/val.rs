@@ -1,9 +1,10 @@
 use super::super::{FileBufSizeParam, FileDbParams};
 use super::piece::{PieceA, PieceMgr, PieceOffsetIter};
 use super::semtype::*;
-use super::vfile::VarFile;
+use super::vfile::{VarFile, VarFileCounters};
 use rabuf::{SmallRead, SmallWrite};
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::fs::OpenOptions;
 use std::io::{Read, Result, Write};
@@ -15,11 +16,16 @@ type HeaderSignature = [u8; 8];
 const CHUNK_SIZE: u32 = 32 * 4 * 1024;
 const DAT_HEADER_SZ: u64 = 192;
 const DAT_HEADER_SIGNATURE: HeaderSignature = [b'a', b'b', b'y', b's', b'd', b'b', b'V', 0u8];
+/// the version of the value file format, 1 has the expiry time in the value piece.
+const DAT_HEADER_VERSION: u64 = 1;
+const DAT_HEADER_FLAGS_OFFSET: u64 = 24;
+/// the flag in the header, some value pieces have had the expiry time.
+const DAT_FLAG_HAS_EXPIRE: u64 = 0x01;
 
 use std::marker::PhantomData;
 
 #[derive(Debug)]
-struct VarFileValueCache(VarFile, PhantomData<i32>);
+struct VarFileValueCache(VarFile, PhantomData<i32>, u64);
 
 #[derive(Debug, Clone)]
 pub struct ValueFile(Rc<RefCell<VarFileValueCache>>);
@@ -58,13 +64,14 @@ impl ValueFile {
             FileBufSizeParam::Auto => VarFile::new(piece_mgr, "val", std_file)?,
         };
         let file_length: ValuePieceOffset = file.seek_to_end()?;
-        if file_length.is_zero() {
+        let flags = if file_length.is_zero() {
             write_valrecf_init_header(&mut file, sig2)?;
+            0
         } else {
-            check_valrecf_header(&mut file, sig2)?;
-        }
+            check_valrecf_header(&mut file, sig2)?
+        };
         //
-        let file_rc = VarFileValueCache(file, PhantomData);
+        let file_rc = VarFileValueCache(file, PhantomData, flags);
         //
         Ok(Self(Rc::new(RefCell::new(file_rc))))
     }
@@ -74,6 +81,14 @@ impl ValueFile {
         locked.0.read_fill_buffer()
     }
     #[inline]
+    pub fn counters(&self) -> VarFileCounters {
+        RefCell::borrow(&self.0).0.counters
+    }
+    #[inline]
+    pub fn reset_counters(&self) {
+        RefCell::borrow_mut(&self.0).0.counters = VarFileCounters::default();
+    }
+    #[inline]
     pub fn flush(&self) -> Result<()> {
         let mut locked = self.0.borrow_mut();
         locked.0.flush()
@@ -88,13 +103,6 @@ impl ValueFile {
         let mut locked = self.0.borrow_mut();
         locked.0.sync_data()
     }
-    #[cfg(feature = "rabuf_stats")]
-    #[inline]
-    pub fn buf_stats(&self) -> Vec<(String, i64)> {
-        let locked = self.0.borrow();
-        locked.0.buf_stats()
-    }
-    //
     pub(crate) fn piece_offset_iter(&self) -> ValuePieceOffsetIter {
         ValuePieceOffsetIter::new(self).unwrap()
     }
@@ -115,6 +123,23 @@ impl ValueFile {
         locked.read_piece_only_value(offset)
     }
     #[inline]
+    pub fn read_piece_only_value_into(
+        &self,
+        offset: ValuePieceOffset,
+        buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        let mut locked = self.0.borrow_mut();
+        locked.read_piece_only_value_into(offset, buf)
+    }
+    #[inline]
+    pub fn with_piece_only_value<R, F>(&self, offset: ValuePieceOffset, f: F) -> Result<R>
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        let mut locked = self.0.borrow_mut();
+        locked.with_piece_only_value(offset, f)
+    }
+    #[inline]
     pub fn read_piece(&self, offset: ValuePieceOffset) -> Result<ValuePiece> {
         let mut locked = self.0.borrow_mut();
         locked.read_piece(offset)
@@ -134,6 +159,115 @@ impl ValueFile {
         let mut locked = self.0.borrow_mut();
         locked.add_value_piece(value)
     }
+    #[inline]
+    pub fn add_value_pieces(&self, values: &[&[u8]]) -> Result<Vec<ValuePieceOffset>> {
+        let mut locked = self.0.borrow_mut();
+        locked.add_value_pieces(values)
+    }
+    #[inline]
+    pub fn add_value_piece_with_expire(&self, value: &[u8], expire_at: u64) -> Result<ValuePiece> {
+        let mut locked = self.0.borrow_mut();
+        let mut piece = ValuePiece::with_value(value);
+        piece.expire_at = expire_at;
+        locked.write_piece(piece, true)
+    }
+    /// returns true if some value pieces have had the expiry time.
+    /// this is kept once it is set.
+    #[inline]
+    pub fn has_expire(&self) -> bool {
+        let locked = self.0.borrow();
+        locked.2 & DAT_FLAG_HAS_EXPIRE != 0
+    }
+    #[inline]
+    pub fn read_piece_only_expire_at(&self, offset: ValuePieceOffset) -> Result<u64> {
+        let mut locked = self.0.borrow_mut();
+        locked.read_piece_only_expire_at(offset)
+    }
+    #[inline]
+    pub fn read_piece_only_value_unexpired(
+        &self,
+        offset: ValuePieceOffset,
+        now: u64,
+    ) -> Result<Option<Vec<u8>>> {
+        let mut locked = self.0.borrow_mut();
+        locked.read_piece_only_value_unexpired(offset, now)
+    }
+    #[inline]
+    pub fn read_piece_only_value_location(
+        &self,
+        offset: ValuePieceOffset,
+    ) -> Result<(ValuePieceOffset, ValueLength)> {
+        let mut locked = self.0.borrow_mut();
+        locked.read_piece_only_value_location(offset)
+    }
+    #[inline]
+    pub fn read_value_at(&self, data_offset: ValuePieceOffset, buf: &mut [u8]) -> Result<()> {
+        let mut locked = self.0.borrow_mut();
+        locked.0.seek_from_start(data_offset)?;
+        locked.0.read_exact(buf)
+    }
+    #[inline]
+    pub fn write_value_at(&self, data_offset: ValuePieceOffset, buf: &[u8]) -> Result<()> {
+        let mut locked = self.0.borrow_mut();
+        locked.0.seek_from_start(data_offset)?;
+        locked.0.write_all(buf)
+    }
+    #[inline]
+    pub fn add_value_piece_with_len(&self, value_len: ValueLength) -> Result<ValuePieceOffset> {
+        let mut locked = self.0.borrow_mut();
+        locked.add_value_piece_with_len(value_len)
+    }
+    #[inline]
+    pub fn append_piece(&self, offset: ValuePieceOffset, bytes: &[u8]) -> Result<ValuePieceOffset> {
+        let mut locked = self.0.borrow_mut();
+        locked.append_piece(offset, bytes)
+    }
+}
+
+// verify
+impl ValueFile {
+    /// scans the pieces and the free lists.
+    /// returns the piece sizes by the offset, and the offsets of the free pieces.
+    pub fn verify_pieces(
+        &self,
+        errors: &mut Vec<String>,
+    ) -> Result<(HashMap<u64, u32>, HashSet<u64>)> {
+        let mut locked = self.0.borrow_mut();
+        let pieces =
+            locked
+                .0
+                .verify_scan_pieces(ValuePieceOffset::new(DAT_HEADER_SZ), "val", errors)?;
+        let free = locked
+            .0
+            .verify_free_piece_lists::<Value>(&pieces, "val", errors)?;
+        Ok((pieces, free))
+    }
+}
+
+// repair
+impl ValueFile {
+    /// rebuilds the free lists from the pieces that are not live.
+    /// returns the count of the free pieces.
+    pub fn repair_free_piece_lists(
+        &self,
+        pieces: &HashMap<u64, u32>,
+        live: &HashSet<u64>,
+    ) -> Result<u64> {
+        let mut locked = self.0.borrow_mut();
+        locked
+            .0
+            .repair_free_piece_lists(ValuePieceOffset::new(DAT_HEADER_SZ), pieces, live)
+    }
+    /// returns true if the value of the piece fits in the piece size.
+    pub fn is_valid_piece(&self, offset: ValuePieceOffset, piece_size: u32) -> Result<bool> {
+        let mut locked = self.0.borrow_mut();
+        let file = &mut locked.0;
+        file.seek_from_start(offset)?;
+        let _piece_size: ValuePieceSize = file.read_piece_size()?;
+        let (val_len, expire_at) = read_value_header(file)?;
+        let (encorded_piece_len, piece_len) = encoded_piece_size_of(val_len, expire_at);
+        Ok(encorded_piece_len as u64 + piece_len as u64 <= piece_size as u64)
+    }
 }
 
 // for debug
@@ -166,8 +300,8 @@ The db data header size is 192 bytes.
 +--------+-------+-------------+---------------------------+
 | 0      | 8     | signature1  | b"siamdbV\0"              |
 | 8      | 8     | signature2  | 8 bytes type signature    |
-| 16     | 8     | reserve0    |                           |
-| 24     | 8     | reserve1    |                           |
+| 16     | 8     | version     | format version: 1         |
+| 24     | 8     | flags       | 0x01: has expiry times    |
 | 32     | 8     | free1 off   | offset of free 1st list   |
 | ...    | ...   | ...         | ...                       |
 | 152    | 8     | free16 off  | offset of free 16th list  |
@@ -185,9 +319,9 @@ fn write_valrecf_init_header(file: &mut VarFile, signature2: HeaderSignature) ->
     file.write_all(&DAT_HEADER_SIGNATURE)?;
     // signature2
     file.write_all(&signature2)?;
-    // reserve0
-    file.write_u64_le(0)?;
-    // reserve1
+    // version
+    file.write_u64_le(DAT_HEADER_VERSION)?;
+    // flags
     file.write_u64_le(0)?;
     // free1 .. reserve2
     file.write_all(&[0u8; 160])?;
@@ -195,24 +329,36 @@ fn write_valrecf_init_header(file: &mut VarFile, signature2: HeaderSignature) ->
     Ok(())
 }
 
-fn check_valrecf_header(file: &mut VarFile, signature2: HeaderSignature) -> Result<()> {
+/// checks the header, and returns the flags.
+fn check_valrecf_header(file: &mut VarFile, signature2: HeaderSignature) -> Result<u64> {
     file.seek_from_start(ValuePieceOffset::new(0))?;
     // signature1
     let mut sig1 = [0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8];
     file.read_exact(&mut sig1)?;
-    assert!(sig1 == DAT_HEADER_SIGNATURE, "invalid header signature1");
+    if sig1 != DAT_HEADER_SIGNATURE {
+        return Err(invalid_data("invalid header signature1".to_string()));
+    }
     // signature2
     let mut sig2 = [0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8];
     file.read_exact(&mut sig2)?;
-    assert!(
-        sig2 == signature2,
-        "invalid header signature2, type signature: {sig2:?}",
-    );
-    // reserve0
-    let _reserve0 = file.read_u64_le()?;
-    assert!(_reserve0 == 0, "invalid reserve0");
-    //
-    Ok(())
+    if sig2 != signature2 {
+        return Err(invalid_data(format!(
+            "invalid header signature2, type signature: {sig2:?}"
+        )));
+    }
+    // version
+    let version = file.read_u64_le()?;
+    if version != DAT_HEADER_VERSION {
+        return Err(invalid_data(format!(
+            "unsupported value file version: {version}, expected: {DAT_HEADER_VERSION}"
+        )));
+    }
+    // flags
+    file.read_u64_le()
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
 }
 
 const REC_SIZE_FREE_OFFSET_1ST: u64 = 32;
@@ -285,6 +431,8 @@ pub struct ValuePiece {
     pub size: ValuePieceSize,
     /// value data.
     pub value: Vec<u8>,
+    /// expiry time in milliseconds since the unix epoch, zero is never.
+    pub expire_at: u64,
 }
 
 impl ValuePiece {
@@ -294,6 +442,7 @@ impl ValuePiece {
             offset,
             size,
             value,
+            expire_at: 0,
         }
     }
     #[inline]
@@ -304,42 +453,23 @@ impl ValuePiece {
         }
     }
     //
-    fn encoded_piece_size(&self) -> (u32, u32, ValueLength) {
-        #[cfg(feature = "abyssiniandb_debug")]
-        let value_len = ValueLength::new(self.value.len().try_into().unwrap());
-        #[cfg(not(feature = "abyssiniandb_debug"))]
-        let value_len = ValueLength::new(self.value.len() as u32);
-        //
-        #[cfg(any(feature = "vf_u32u32", feature = "vf_u64u64"))]
-        let (encorded_piece_len, piece_len) = {
-            let enc_val_len = 4;
-            let piece_len: u32 = enc_val_len + value_len.as_value();
-            let encorded_piece_len = 4;
-            (encorded_piece_len, piece_len)
-        };
-        #[cfg(feature = "vf_vu64")]
-        let (encorded_piece_len, piece_len) = {
-            let enc_val_len = vu64::encoded_len(value_len.as_value() as u64) as u32;
-            let piece_len: u32 = enc_val_len + value_len.as_value();
-            let encorded_piece_len = vu64::encoded_len((piece_len as u64 + 7) / 8) as u32;
-            (encorded_piece_len, piece_len)
-        };
+    fn encoded_piece_size(&self) -> Result<(u32, u32, ValueLength)> {
+        let value_len = check_value_len(self.value.len() as u64)?;
+        //
+        let (encorded_piece_len, piece_len) = encoded_piece_size_of(value_len, self.expire_at);
         //
-        (encorded_piece_len, piece_len, value_len)
+        Ok((encorded_piece_len, piece_len, value_len))
     }
     //
     pub(crate) fn dat_write_piece_one(&self, file: &mut VarFile) -> Result<()> {
         assert!(!self.size.is_zero());
         //
         let value = &self.value;
-        #[cfg(feature = "abyssiniandb_debug")]
-        let value_len = ValueLength::new(value.len().try_into().unwrap());
-        #[cfg(not(feature = "abyssiniandb_debug"))]
-        let value_len = ValueLength::new(value.len() as u32);
+        let value_len = check_value_len(value.len() as u64)?;
         //
         file.seek_from_start(self.offset)?;
         file.write_piece_size(self.size)?;
-        file.write_value_len(value_len)?;
+        write_value_header(file, value_len, self.expire_at)?;
         file.write_all_small(value)?;
         file.write_zero_to_offset(self.offset + self.size)?;
         //
@@ -347,7 +477,99 @@ impl ValuePiece {
     }
 }
 
+/// returns the encoded size of the value length.
+#[inline]
+fn encoded_value_len_size(value_len: ValueLength) -> u32 {
+    #[cfg(any(feature = "vf_u32u32", feature = "vf_u64u64"))]
+    let enc_val_len = {
+        let _ = value_len;
+        4
+    };
+    #[cfg(feature = "vf_vu64")]
+    let enc_val_len = vu64::encoded_len(value_len.as_value() as u64) as u32;
+    //
+    enc_val_len
+}
+
+/// the flag in the value length, the expiry time follows the value length if it is set.
+const VALUE_LEN_EXPIRE_FLAG: u32 = 0x8000_0000;
+
+/// the max length of a value, the top bit of the value length is the expire flag.
+pub(crate) const VALUE_LEN_MAX: u32 = VALUE_LEN_EXPIRE_FLAG - 1;
+
+/// returns the value length, or the `InvalidInput` error if it is too large.
+#[inline]
+fn check_value_len(len: u64) -> Result<ValueLength> {
+    if len > VALUE_LEN_MAX as u64 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("the value is too large: {len}"),
+        ));
+    }
+    Ok(ValueLength::new(len as u32))
+}
+
+/// returns the encoded size of the value length and the expiry time.
+#[inline]
+fn encoded_value_header_size(value_len: ValueLength, expire_at: u64) -> u32 {
+    if expire_at == 0 {
+        encoded_value_len_size(value_len)
+    } else {
+        let flagged_len = ValueLength::new(value_len.as_value() | VALUE_LEN_EXPIRE_FLAG);
+        encoded_value_len_size(flagged_len) + 8
+    }
+}
+
+/// reads the value length and the expiry time, zero is never.
+#[inline]
+fn read_value_header(file: &mut VarFile) -> Result<(ValueLength, u64)> {
+    let val_len = file.read_value_len()?.as_value();
+    if val_len & VALUE_LEN_EXPIRE_FLAG == 0 {
+        Ok((ValueLength::new(val_len), 0))
+    } else {
+        let expire_at = file.read_u64_le()?;
+        Ok((
+            ValueLength::new(val_len & !VALUE_LEN_EXPIRE_FLAG),
+            expire_at,
+        ))
+    }
+}
+
+/// writes the value length and the expiry time, zero is never.
+#[inline]
+fn write_value_header(file: &mut VarFile, value_len: ValueLength, expire_at: u64) -> Result<()> {
+    let value_len = check_value_len(value_len.as_value() as u64)?;
+    if expire_at == 0 {
+        file.write_value_len(value_len)
+    } else {
+        file.write_value_len(ValueLength::new(
+            value_len.as_value() | VALUE_LEN_EXPIRE_FLAG,
+        ))?;
+        file.write_u64_le(expire_at)
+    }
+}
+
+/// returns the encoded size of the piece size and the piece length without it.
+#[inline]
+fn encoded_piece_size_of(value_len: ValueLength, expire_at: u64) -> (u32, u32) {
+    let enc_val_len = encoded_value_header_size(value_len, expire_at);
+    let piece_len: u32 = enc_val_len + value_len.as_value();
+    #[cfg(any(feature = "vf_u32u32", feature = "vf_u64u64"))]
+    let encorded_piece_len = 4;
+    #[cfg(feature = "vf_vu64")]
+    let encorded_piece_len = vu64::encoded_len((piece_len as u64 + 7) / 8) as u32;
+    //
+    (encorded_piece_len, piece_len)
+}
+
 impl VarFileValueCache {
+    fn write_flags(&mut self, flags: u64) -> Result<()> {
+        self.0
+            .seek_from_start(ValuePieceOffset::new(DAT_HEADER_FLAGS_OFFSET))?;
+        self.0.write_u64_le(flags)?;
+        self.2 = flags;
+        Ok(())
+    }
     fn delete_piece(&mut self, offset: ValuePieceOffset) -> Result<ValuePieceSize> {
         let old_piece_size = {
             self.0.seek_from_start(offset)?;
@@ -363,10 +585,95 @@ impl VarFileValueCache {
         self.write_piece(ValuePiece::with_value(value), true)
     }
 
+    /// adds the new pieces of the values, and returns their offsets.
+    /// the pieces that are not in the free list are written continuously
+    /// at the end of the file, that is extended at once.
+    fn add_value_pieces(&mut self, values: &[&[u8]]) -> Result<Vec<ValuePieceOffset>> {
+        for value in values {
+            check_value_len(value.len() as u64)?;
+        }
+        let mut offsets = Vec::with_capacity(values.len());
+        let mut tail_pieces = Vec::new();
+        let mut tail_size = 0u64;
+        for (i, value) in values.iter().enumerate() {
+            let mut piece = ValuePiece::with_value(value);
+            let (encorded_piece_len, piece_len, _value_len) = piece.encoded_piece_size()?;
+            piece.size = self
+                .0
+                .piece_mgr
+                .roundup(ValuePieceSize::new(encorded_piece_len + piece_len));
+            debug_assert!(piece.size.is_valid_value());
+            let free_piece_offset = self.0.pop_free_piece_list(piece.size)?;
+            if !free_piece_offset.is_zero() {
+                piece.offset = free_piece_offset;
+                piece.dat_write_piece_one(&mut self.0)?;
+                offsets.push(free_piece_offset);
+            } else {
+                tail_size += piece.size.as_value() as u64;
+                offsets.push(ValuePieceOffset::new(0));
+                tail_pieces.push((i, piece));
+            }
+        }
+        if !tail_pieces.is_empty() {
+            let end_offset: ValuePieceOffset = self.0.seek_to_end()?;
+            self.0
+                .set_file_length(ValuePieceOffset::new(end_offset.as_value() + tail_size))?;
+            let mut offset = end_offset;
+            for (i, mut piece) in tail_pieces {
+                piece.offset = offset;
+                if let Err(err) = piece.dat_write_piece_one(&mut self.0) {
+                    // recover on error
+                    let _ = self.0.set_file_length(end_offset);
+                    return Err(err);
+                }
+                offsets[i] = offset;
+                offset = offset + piece.size;
+            }
+        }
+        Ok(offsets)
+    }
+
+    /// adds the new piece that has the zero-filled value of the length,
+    /// without the value on memory.
+    fn add_value_piece_with_len(&mut self, value_len: ValueLength) -> Result<ValuePieceOffset> {
+        let value_len = check_value_len(value_len.as_value() as u64)?;
+        let (encorded_piece_len, piece_len) = encoded_piece_size_of(value_len, 0);
+        let new_piece_size = self
+            .0
+            .piece_mgr
+            .roundup(ValuePieceSize::new(encorded_piece_len + piece_len));
+        let free_piece_offset = self.0.pop_free_piece_list(new_piece_size)?;
+        let new_piece_offset = if !free_piece_offset.is_zero() {
+            self.0.seek_from_start(free_piece_offset)?;
+            free_piece_offset
+        } else {
+            self.0.seek_to_end()?
+        };
+        debug_assert!(new_piece_size.is_valid_value());
+        let r = (|| {
+            self.0.seek_from_start(new_piece_offset)?;
+            self.0.write_piece_size(new_piece_size)?;
+            write_value_header(&mut self.0, value_len, 0)?;
+            self.0
+                .write_zero_to_offset(new_piece_offset + new_piece_size)
+        })();
+        if let Err(err) = r {
+            // recover on error
+            if free_piece_offset.is_zero() {
+                let _ = self.0.set_file_length(new_piece_offset);
+            }
+            return Err(err);
+        }
+        Ok(new_piece_offset)
+    }
+
     fn write_piece(&mut self, mut piece: ValuePiece, is_new: bool) -> Result<ValuePiece> {
         debug_assert!(is_new || !piece.offset.is_zero());
+        if piece.expire_at != 0 && self.2 & DAT_FLAG_HAS_EXPIRE == 0 {
+            self.write_flags(self.2 | DAT_FLAG_HAS_EXPIRE)?;
+        }
         //
-        let (encorded_piece_len, piece_len, _value_len) = piece.encoded_piece_size();
+        let (encorded_piece_len, piece_len, _value_len) = piece.encoded_piece_size()?;
         let new_piece_size = self
             .0
             .piece_mgr
@@ -388,6 +695,7 @@ impl VarFileValueCache {
                 // delete old and add new
                 // old
                 self.0.push_free_piece_list(piece.offset, old_piece_size)?;
+                self.0.counters.relocations += 1;
             }
         }
         // add new.
@@ -416,6 +724,47 @@ impl VarFileValueCache {
         Ok(piece)
     }
 
+    /// appends the bytes to the value of the piece.
+    /// the piece is extended in place if its size has the slack,
+    /// otherwise it is relocated. returns the offset of the piece.
+    fn append_piece(&mut self, offset: ValuePieceOffset, bytes: &[u8]) -> Result<ValuePieceOffset> {
+        debug_assert!(!offset.is_zero());
+        //
+        self.0.seek_from_start(offset)?;
+        let piece_size = self.0.read_piece_size()?;
+        debug_assert!(piece_size.is_valid_value());
+        let len_pos: ValuePieceOffset = self.0.seek_position()?;
+        let (old_len, expire_at) = read_value_header(&mut self.0)?;
+        let data_pos: ValuePieceOffset = self.0.seek_position()?;
+        //
+        let new_len = check_value_len(old_len.as_value() as u64 + bytes.len() as u64)?;
+        let (encorded_piece_len, piece_len) = encoded_piece_size_of(new_len, expire_at);
+        let new_piece_size = self
+            .0
+            .piece_mgr
+            .roundup(ValuePieceSize::new(encorded_piece_len + piece_len));
+        //
+        let old_enc_len = encoded_value_header_size(old_len, expire_at);
+        if new_piece_size <= piece_size
+            && data_pos.as_value() - len_pos.as_value() == old_enc_len as u64
+            && encoded_value_header_size(new_len, expire_at) == old_enc_len
+        {
+            // extends in place.
+            self.0.seek_from_start(len_pos)?;
+            write_value_header(&mut self.0, new_len, expire_at)?;
+            self.0.seek_from_start(ValuePieceOffset::new(
+                data_pos.as_value() + old_len.as_value() as u64,
+            ))?;
+            self.0.write_all_small(bytes)?;
+            return Ok(offset);
+        }
+        // relocates.
+        let mut piece = self.read_piece(offset)?;
+        piece.value.extend_from_slice(bytes);
+        let piece = self.write_piece(piece, false)?;
+        Ok(piece.offset)
+    }
+
     fn read_piece(&mut self, offset: ValuePieceOffset) -> Result<ValuePiece> {
         debug_assert!(!offset.is_zero());
         //
@@ -423,11 +772,12 @@ impl VarFileValueCache {
         let piece_size = self.0.read_piece_size()?;
         debug_assert!(piece_size.is_valid_value());
         //
-        let val_len = self.0.read_value_len()?;
+        let (val_len, expire_at) = read_value_header(&mut self.0)?;
         let maybe_slice = self.0.read_exact_maybeslice(val_len.into())?;
         let value = maybe_slice.to_vec();
         //
-        let piece = ValuePiece::with(offset, piece_size, value);
+        let mut piece = ValuePiece::with(offset, piece_size, value);
+        piece.expire_at = expire_at;
         //
         Ok(piece)
     }
@@ -447,18 +797,96 @@ impl VarFileValueCache {
         debug_assert!(!offset.is_zero());
         //
         self.0.seek_skip_to_piece_value(offset)?;
-        let val_len = self.0.read_value_len()?;
+        let (val_len, _expire_at) = read_value_header(&mut self.0)?;
         //
         Ok(val_len)
     }
 
+    /// returns the expiry time, zero is never.
+    #[inline]
+    fn read_piece_only_expire_at(&mut self, offset: ValuePieceOffset) -> Result<u64> {
+        debug_assert!(!offset.is_zero());
+        //
+        self.0.seek_skip_to_piece_value(offset)?;
+        let (_val_len, expire_at) = read_value_header(&mut self.0)?;
+        //
+        Ok(expire_at)
+    }
+
+    /// returns the value if it is not expired at `now`.
+    #[inline]
+    fn read_piece_only_value_unexpired(
+        &mut self,
+        offset: ValuePieceOffset,
+        now: u64,
+    ) -> Result<Option<Vec<u8>>> {
+        debug_assert!(!offset.is_zero());
+        //
+        self.0.seek_skip_to_piece_value(offset)?;
+        let (val_len, expire_at) = read_value_header(&mut self.0)?;
+        if expire_at != 0 && expire_at <= now {
+            return Ok(None);
+        }
+        let maybe_slice = self.0.read_exact_maybeslice(val_len.into())?;
+        //
+        Ok(Some(maybe_slice.into_vec()))
+    }
+
+    /// returns the offset of the value data and the value length.
+    #[inline]
+    fn read_piece_only_value_location(
+        &mut self,
+        offset: ValuePieceOffset,
+    ) -> Result<(ValuePieceOffset, ValueLength)> {
+        debug_assert!(!offset.is_zero());
+        //
+        self.0.seek_skip_to_piece_value(offset)?;
+        let (val_len, _expire_at) = read_value_header(&mut self.0)?;
+        let data_offset = self.0.seek_position()?;
+        //
+        Ok((data_offset, val_len))
+    }
+
+    /// reads the value into the buffer, the allocated buffer is reused.
+    #[inline]
+    fn read_piece_only_value_into(
+        &mut self,
+        offset: ValuePieceOffset,
+        buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        debug_assert!(!offset.is_zero());
+        //
+        self.0.seek_skip_to_piece_value(offset)?;
+        //
+        let (val_len, _expire_at) = read_value_header(&mut self.0)?;
+        buf.clear();
+        buf.resize(val_len.as_value() as usize, 0);
+        self.0.read_exact(buf)
+    }
+
+    /// calls the closure with the value, that is borrowed from the buffer
+    /// if it fits in the buffered chunk.
+    #[inline]
+    fn with_piece_only_value<R, F>(&mut self, offset: ValuePieceOffset, f: F) -> Result<R>
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        debug_assert!(!offset.is_zero());
+        //
+        self.0.seek_skip_to_piece_value(offset)?;
+        //
+        let (val_len, _expire_at) = read_value_header(&mut self.0)?;
+        let maybe_slice = self.0.read_exact_maybeslice(val_len.into())?;
+        Ok(f(&maybe_slice))
+    }
+
     #[inline]
     fn read_piece_only_value(&mut self, offset: ValuePieceOffset) -> Result<Vec<u8>> {
         debug_assert!(!offset.is_zero());
         //
         self.0.seek_skip_to_piece_value(offset)?;
         //
-        let val_len = self.0.read_value_len()?;
+        let (val_len, _expire_at) = read_value_header(&mut self.0)?;
         let maybe_slice = self.0.read_exact_maybeslice(val_len.into())?;
         let value = maybe_slice.into_vec();
         //
@@ -525,6 +953,7 @@ used piece:
 +--------+-------+-------------+-----------------------------------+
 | 0      | 1..5  | piece size  | size in bytes of this piece: u32  |
 | --     | 1..5  | val len     | a byte length of value            |
+| --     | 0,8   | expire at   | if the highest bit of val len     |
 | --     | --    | val data    | raw value data                    |
 | --     | --    | reserve     | reserved free space               |
 +--------+-------+-------------+-----------------------------------+
@@ -0,0 +1,164 @@
+use super::super::super::DbMapKeyType;
+use super::dbxxx::{FileDbXxxInner, ValueLocation};
+use super::semtype::*;
+use std::cell::RefCell;
+use std::io::{Read, Result, Seek, SeekFrom, Write};
+use std::rc::Rc;
+
+/// A reader over the value of a key, without loading the whole value.
+///
+/// This is constructed from the `value_reader()` method on `FileDbMap`.
+/// If a value of the map is replaced or a key is deleted while reading,
+/// the read returns an error. Inserting new keys does not affect the reader.
+#[derive(Debug)]
+pub struct DbXxxValueReader<KT: DbMapKeyType> {
+    db_map: Rc<RefCell<FileDbXxxInner<KT>>>,
+    loc: ValueLocation,
+    pos: u64,
+    value_generation: u64,
+}
+
+impl<KT: DbMapKeyType> DbXxxValueReader<KT> {
+    pub(crate) fn new(db_map: Rc<RefCell<FileDbXxxInner<KT>>>, key: KT) -> Result<Option<Self>> {
+        let (opt, value_generation) = {
+            let mut db_map_inner = RefCell::borrow_mut(&db_map);
+            let opt = db_map_inner.value_location_kt(&key)?;
+            (opt, db_map_inner.value_generation())
+        };
+        Ok(opt.map(|loc| Self {
+            db_map,
+            loc,
+            pos: 0,
+            value_generation,
+        }))
+    }
+    /// returns the length of the value.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.loc.len
+    }
+    /// returns true if the value is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.loc.len == 0
+    }
+    /// checks that the value is not changed.
+    /// an insert never overwrites a live piece, so only the replaces and
+    /// the deletes are counted, including an overwrite in place.
+    fn check_value(&self) -> Result<()> {
+        if RefCell::borrow(&self.db_map).value_generation() != self.value_generation {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "the value was modified while reading",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<KT: DbMapKeyType> Read for DbXxxValueReader<KT> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.pos >= self.loc.len || buf.is_empty() {
+            return Ok(0);
+        }
+        self.check_value()?;
+        let n = (self.loc.len - self.pos).min(buf.len() as u64) as usize;
+        RefCell::borrow(&self.db_map).read_value_at(&self.loc, self.pos, &mut buf[..n])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<KT: DbMapKeyType> Seek for DbXxxValueReader<KT> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let (base, n) = match pos {
+            SeekFrom::Start(n) => (n, 0),
+            SeekFrom::End(n) => (self.loc.len, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        let new_pos = if n >= 0 {
+            base.checked_add(n as u64)
+        } else {
+            base.checked_sub(n.unsigned_abs())
+        };
+        match new_pos {
+            Some(n) => {
+                self.pos = n;
+                Ok(n)
+            }
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+/// A writer that streams a value of the fixed length into a key.
+///
+/// This is constructed from the `value_writer()` method on `FileDbMap`.
+/// The value is stored into the new piece, and it replaces the value
+/// of the key when `finish()` is called. If this is dropped without
+/// `finish()`, the written data is discarded.
+#[derive(Debug)]
+pub struct DbXxxValueWriter<KT: DbMapKeyType> {
+    db_map: Rc<RefCell<FileDbXxxInner<KT>>>,
+    key: KT,
+    value_offset: ValuePieceOffset,
+    data_offset: ValuePieceOffset,
+    len: u64,
+    pos: u64,
+    finished: bool,
+}
+
+impl<KT: DbMapKeyType> DbXxxValueWriter<KT> {
+    pub(crate) fn new(db_map: Rc<RefCell<FileDbXxxInner<KT>>>, key: KT, len: u64) -> Result<Self> {
+        let (value_offset, data_offset) = RefCell::borrow_mut(&db_map).alloc_value_piece(len)?;
+        Ok(Self {
+            db_map,
+            key,
+            value_offset,
+            data_offset,
+            len,
+            pos: 0,
+            finished: false,
+        })
+    }
+    /// stores the written value into the key.
+    /// this returns an error if the written length is not the length of the value.
+    pub fn finish(mut self) -> Result<()> {
+        if self.pos != self.len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("the written length is short: {} < {}", self.pos, self.len),
+            ));
+        }
+        RefCell::borrow_mut(&self.db_map).link_value_piece_kt(&self.key, self.value_offset)?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<KT: DbMapKeyType> Write for DbXxxValueWriter<KT> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = (self.len - self.pos).min(buf.len() as u64) as usize;
+        if n == 0 {
+            return Ok(0);
+        }
+        let data_offset = ValuePieceOffset::new(self.data_offset.as_value() + self.pos);
+        RefCell::borrow(&self.db_map).write_value_at(data_offset, &buf[..n])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<KT: DbMapKeyType> Drop for DbXxxValueWriter<KT> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = RefCell::borrow(&self.db_map).free_value_piece(self.value_offset);
+        }
+    }
+}
@@ -16,6 +16,20 @@ use vu64::io::{ReadVu64, WriteVu64};
 pub struct VarFile {
     buf_file: BufFile,
     pub(crate) piece_mgr: PieceMgr,
+    pub(crate) counters: VarFileCounters,
+}
+
+/// The access counters of a file, these are read by `FileDbMap::metrics()`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VarFileCounters {
+    /// bytes read through the buffer.
+    pub bytes_read: u64,
+    /// bytes written through the buffer.
+    pub bytes_written: u64,
+    /// count of the pieces that are allocated from the free list.
+    pub free_list_reuses: u64,
+    /// count of the pieces that are moved to grow.
+    pub relocations: u64,
 }
 
 impl VarFile {
@@ -25,6 +39,7 @@ impl VarFile {
         Ok(Self {
             buf_file: BufFile::new(name, file)?,
             piece_mgr,
+            counters: VarFileCounters::default(),
         })
     }
     /// Creates a new VarFile with the specified number of chunks.
@@ -41,6 +56,7 @@ impl VarFile {
         Ok(Self {
             buf_file: BufFile::with_capacity(name, file, chunk_size, max_num_chunks)?,
             piece_mgr,
+            counters: VarFileCounters::default(),
             //piece_mgr: PieceMgr::new(free_list_offset, size_ary),
         })
     }
@@ -58,6 +74,7 @@ impl VarFile {
         Ok(Self {
             buf_file: BufFile::with_per_mille(name, file, chunk_size, per_mille)?,
             piece_mgr,
+            counters: VarFileCounters::default(),
             //piece_mgr: PieceMgr::new(free_list_offset, size_ary),
         })
     }
@@ -77,11 +94,6 @@ impl VarFile {
         self.buf_file.clear()
     }
     //
-    #[cfg(feature = "rabuf_stats")]
-    pub fn buf_stats(&self) -> Vec<(String, i64)> {
-        self.buf_file.buf_stats()
-    }
-    //
     #[inline]
     pub fn prepare<T>(&mut self, offset: Offset<T>) -> Result<()> {
         self.buf_file.prepare(offset.into())
@@ -138,19 +150,19 @@ impl VarFile {
     //
     #[inline]
     pub fn _write_all_small(&mut self, buf: &[u8]) -> Result<()> {
-        self.buf_file.write_all_small(buf)
+        SmallWrite::write_all_small(self, buf)
     }
     //
     #[inline]
     pub fn _write_zero<T>(&mut self, size: Size<T>) -> Result<()> {
-        self.buf_file.write_zero(size.into())
+        SmallWrite::write_zero(self, size.into())
     }
     #[inline]
     pub fn write_zero_to_offset<T: PartialOrd>(&mut self, offset: Offset<T>) -> Result<()> {
         let start_offset = self.seek_position()?;
         if offset > start_offset {
             let size = offset - start_offset;
-            self.buf_file.write_zero(size.into())
+            SmallWrite::write_zero(self, size.into())
         } else {
             Ok(())
         }
@@ -205,14 +217,18 @@ impl VarFile {
 impl Read for VarFile {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        self.buf_file.read(buf)
+        let n = self.buf_file.read(buf)?;
+        self.counters.bytes_read += n as u64;
+        Ok(n)
     }
 }
 
 impl Write for VarFile {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
-        self.buf_file.write(buf)
+        let n = self.buf_file.write(buf)?;
+        self.counters.bytes_written += n as u64;
+        Ok(n)
     }
     #[inline]
     fn flush(&mut self) -> Result<()> {
@@ -230,30 +246,37 @@ impl Seek for VarFile {
 impl rabuf::SmallRead for VarFile {
     #[inline]
     fn read_u8(&mut self) -> Result<u8> {
+        self.counters.bytes_read += 1;
         self.buf_file.read_u8()
     }
     #[inline]
     fn read_u16_le(&mut self) -> Result<u16> {
+        self.counters.bytes_read += 2;
         self.buf_file.read_u16_le()
     }
     #[inline]
     fn read_u32_le(&mut self) -> Result<u32> {
+        self.counters.bytes_read += 4;
         self.buf_file.read_u32_le()
     }
     #[inline]
     fn read_u64_le(&mut self) -> Result<u64> {
+        self.counters.bytes_read += 8;
         self.buf_file.read_u64_le()
     }
     #[inline]
     fn read_max_8_bytes(&mut self, size: usize) -> Result<u64> {
+        self.counters.bytes_read += size as u64;
         self.buf_file.read_max_8_bytes(size)
     }
     #[inline]
     fn read_exact_small(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.counters.bytes_read += buf.len() as u64;
         self.buf_file.read_exact_small(buf)
     }
     #[inline]
     fn read_exact_maybeslice(&mut self, size: usize) -> Result<MaybeSlice<'_>> {
+        self.counters.bytes_read += size as u64;
         self.buf_file.read_exact_maybeslice(size)
     }
 }
@@ -261,34 +284,42 @@ impl rabuf::SmallRead for VarFile {
 impl rabuf::SmallWrite for VarFile {
     #[inline]
     fn write_u8(&mut self, val: u8) -> Result<()> {
+        self.counters.bytes_written += 1;
         self.buf_file.write_u8(val)
     }
     #[inline]
     fn write_u16_le(&mut self, val: u16) -> Result<()> {
+        self.counters.bytes_written += 2;
         self.buf_file.write_u16_le(val)
     }
     #[inline]
     fn write_u32_le(&mut self, val: u32) -> Result<()> {
+        self.counters.bytes_written += 4;
         self.buf_file.write_u32_le(val)
     }
     #[inline]
     fn write_u64_le(&mut self, val: u64) -> Result<()> {
+        self.counters.bytes_written += 8;
         self.buf_file.write_u64_le(val)
     }
     #[inline]
     fn write_u64_le_slice(&mut self, val_slice: &[u64]) -> Result<()> {
+        self.counters.bytes_written += 8 * val_slice.len() as u64;
         self.buf_file.write_u64_le_slice(val_slice)
     }
     #[inline]
     fn write_u64_le_slice2(&mut self, val_slice1: &[u64], val_slice2: &[u64]) -> Result<()> {
+        self.counters.bytes_written += 8 * (val_slice1.len() + val_slice2.len()) as u64;
         self.buf_file.write_u64_le_slice2(val_slice1, val_slice2)
     }
     #[inline]
     fn write_all_small(&mut self, buf: &[u8]) -> Result<()> {
+        self.counters.bytes_written += buf.len() as u64;
         self.buf_file.write_all_small(buf)
     }
     #[inline]
     fn write_zero(&mut self, size: u32) -> Result<()> {
+        self.counters.bytes_written += size as u64;
         self.buf_file.write_zero(size)
     }
 }
@@ -629,21 +660,21 @@ impl VarFile {
 impl ReadVu64 for VarFile {
     #[inline]
     fn read_one_byte(&mut self) -> Result<u8> {
-        self.buf_file.read_u8()
+        self.read_u8()
     }
     #[inline]
     fn read_exact_max8byte(&mut self, buf: &mut [u8]) -> Result<()> {
         debug_assert!(buf.len() <= 8, "buf.len(): {} <= 8", buf.len());
-        self.buf_file.read_exact_small(buf)
+        self.read_exact_small(buf)
     }
     /// reads `vu64` bytes and decods it to `u64`
     fn read_and_decode_vu64(&mut self) -> Result<u64> {
         /*
         let mut buf = [0u8; vu64::MAX_BYTES-1];
-        let byte_1st = self.buf_file.read_u8()?;
+        let byte_1st = self.read_u8()?;
         let len = vu64::decoded_len(byte_1st);
         if len > 1 {
-            self.buf_file.read_exact_small(&mut buf[..len as usize - 1])?;
+            self.read_exact_small(&mut buf[..len as usize - 1])?;
         }
         match vu64::decode_with_first_and_follow(len, byte_1st, &buf[..len as usize - 1]) {
             Ok(i) => Ok(i),
@@ -654,9 +685,9 @@ impl ReadVu64 for VarFile {
         }
         */
         /*
-        let byte_1st = self.buf_file.read_u8()?;
+        let byte_1st = self.read_u8()?;
         let len = vu64::decoded_len(byte_1st);
-        let maybe_slice = self.buf_file.read_exact_maybeslice(len as usize - 1)?;
+        let maybe_slice = self.read_exact_maybeslice(len as usize - 1)?;
         match vu64::decode_with_first_and_follow(len, byte_1st, &maybe_slice) {
             Ok(i) => Ok(i),
             Err(err) => Err(std::io::Error::new(
@@ -665,7 +696,7 @@ impl ReadVu64 for VarFile {
             )),
         }
         */
-        let byte_1st = self.buf_file.read_u8()?;
+        let byte_1st = self.read_u8()?;
         if byte_1st < 128 {
             Ok(byte_1st as u64)
         } else {
@@ -673,10 +704,10 @@ impl ReadVu64 for VarFile {
             let follow_len = len as usize - 1;
             let max_8_bytes = match follow_len {
                 0 => 0,
-                1 => self.buf_file.read_u8()? as u64,
-                2 => self.buf_file.read_u16_le()? as u64,
-                //4 => self.buf_file.read_u32_le()? as u64,
-                _ => self.buf_file.read_max_8_bytes(follow_len)?,
+                1 => self.read_u8()? as u64,
+                2 => self.read_u16_le()? as u64,
+                //4 => self.read_u32_le()? as u64,
+                _ => self.read_max_8_bytes(follow_len)?,
             };
             match vu64::decode_with_first_and_follow_le(len, byte_1st, max_8_bytes) {
                 Ok(i) => Ok(i),
@@ -854,7 +885,7 @@ impl VarFile {
         offset: PieceOffset<T>,
     ) -> Result<PieceOffset<T>> {
         self.seek_from_start(offset)?;
-        let byte_1st = self.buf_file.read_u8()?;
+        let byte_1st = self.read_u8()?;
         let piece_size_len = vu64::decoded_len(byte_1st);
         if piece_size_len > 1 {
             self.seek_skip_length(KeyLength::new((piece_size_len - 1).into()))?;
@@ -868,7 +899,7 @@ impl VarFile {
         offset: PieceOffset<T>,
     ) -> Result<PieceOffset<T>> {
         self.seek_from_start(offset)?;
-        let byte_1st = self.buf_file.read_u8()?;
+        let byte_1st = self.read_u8()?;
         let piece_size_len = vu64::decoded_len(byte_1st);
         if piece_size_len > 1 {
             self.seek_skip_length(KeyLength::new((piece_size_len - 1).into()))?;
@@ -925,24 +956,24 @@ mod debug {
                 #[cfg(not(feature = "rabuf_stats"))]
                 {
                     #[cfg(not(feature = "rabuf_print_hits"))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 184);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 216);
                     #[cfg(feature = "rabuf_print_hits")]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 200);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 232);
                 }
                 #[cfg(feature = "rabuf_stats")]
-                assert_eq!(std::mem::size_of::<VarFile>(), 184);
+                assert_eq!(std::mem::size_of::<VarFile>(), 224);
             }
             #[cfg(feature = "rabuf_hash_turbo")]
             {
                 #[cfg(not(feature = "rabuf_stats"))]
                 {
                     #[cfg(not(feature = "rabuf_print_hits"))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 184);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 216);
                     #[cfg(feature = "rabuf_print_hits")]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 208);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 240);
                 }
                 #[cfg(feature = "rabuf_stats")]
-                assert_eq!(std::mem::size_of::<VarFile>(), 184);
+                assert_eq!(std::mem::size_of::<VarFile>(), 224);
             }
         }
         #[cfg(target_pointer_width = "32")]
@@ -954,38 +985,38 @@ mod debug {
                     #[cfg(not(any(target_arch = "arm", target_arch = "mips")))]
                     {
                         #[cfg(not(feature = "rabuf_print_hits"))]
-                        assert_eq!(std::mem::size_of::<VarFile>(), 108);
+                        assert_eq!(std::mem::size_of::<VarFile>(), 140);
                         #[cfg(feature = "rabuf_print_hits")]
-                        assert_eq!(std::mem::size_of::<VarFile>(), 132);
+                        assert_eq!(std::mem::size_of::<VarFile>(), 164);
                     }
                     #[cfg(any(target_arch = "arm", target_arch = "mips"))]
                     {
                         #[cfg(not(feature = "rabuf_print_hits"))]
-                        assert_eq!(std::mem::size_of::<VarFile>(), 120);
+                        assert_eq!(std::mem::size_of::<VarFile>(), 152);
                         #[cfg(feature = "rabuf_print_hits")]
-                        assert_eq!(std::mem::size_of::<VarFile>(), 144);
+                        assert_eq!(std::mem::size_of::<VarFile>(), 176);
                     }
                 }
                 #[cfg(all(feature = "rabuf_stats", feature = "rabuf_lru"))]
                 {
                     #[cfg(not(any(target_arch = "arm", target_arch = "mips")))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 88);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 120);
                     #[cfg(any(target_arch = "arm", target_arch = "mips"))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 96);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 128);
                 }
                 #[cfg(all(feature = "rabuf_stats", not(feature = "rabuf_lru")))]
                 {
                     #[cfg(not(any(target_arch = "arm", target_arch = "mips")))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 116);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 148);
                     #[cfg(any(target_arch = "arm", target_arch = "mips"))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 128);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 160);
                 }
                 #[cfg(all(not(feature = "rabuf_stats"), feature = "rabuf_lru"))]
                 {
                     #[cfg(not(any(target_arch = "arm", target_arch = "mips")))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 80);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 112);
                     #[cfg(any(target_arch = "arm", target_arch = "mips"))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 88);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 120);
                 }
             }
             #[cfg(feature = "rabuf_hash_turbo")]
@@ -995,38 +1026,38 @@ mod debug {
                     #[cfg(not(any(target_arch = "arm", target_arch = "mips")))]
                     {
                         #[cfg(not(feature = "rabuf_print_hits"))]
-                        assert_eq!(std::mem::size_of::<VarFile>(), 112);
+                        assert_eq!(std::mem::size_of::<VarFile>(), 144);
                         #[cfg(feature = "rabuf_print_hits")]
-                        assert_eq!(std::mem::size_of::<VarFile>(), 164);
+                        assert_eq!(std::mem::size_of::<VarFile>(), 196);
                     }
                     #[cfg(any(target_arch = "arm", target_arch = "mips"))]
                     {
                         #[cfg(not(feature = "rabuf_print_hits"))]
-                        assert_eq!(std::mem::size_of::<VarFile>(), 120);
+                        assert_eq!(std::mem::size_of::<VarFile>(), 152);
                         #[cfg(feature = "rabuf_print_hits")]
-                        assert_eq!(std::mem::size_of::<VarFile>(), 176);
+                        assert_eq!(std::mem::size_of::<VarFile>(), 208);
                     }
                 }
                 #[cfg(all(feature = "rabuf_stats", feature = "rabuf_lru"))]
                 {
                     #[cfg(not(any(target_arch = "arm", target_arch = "mips")))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 88);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 120);
                     #[cfg(any(target_arch = "arm", target_arch = "mips"))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 96);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 128);
                 }
                 #[cfg(all(feature = "rabuf_stats", not(feature = "rabuf_lru")))]
                 {
                     #[cfg(not(any(target_arch = "arm", target_arch = "mips")))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 116);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 148);
                     #[cfg(any(target_arch = "arm", target_arch = "mips"))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 128);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 160);
                 }
                 #[cfg(all(not(feature = "rabuf_stats"), feature = "rabuf_lru"))]
                 {
                     #[cfg(not(any(target_arch = "arm", target_arch = "mips")))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 80);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 112);
                     #[cfg(any(target_arch = "arm", target_arch = "mips"))]
-                    assert_eq!(std::mem::size_of::<VarFile>(), 88);
+                    assert_eq!(std::mem::size_of::<VarFile>(), 120);
                 }
             }
         }
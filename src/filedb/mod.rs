@@ -6,12 +6,17 @@ use std::rc::Rc;
 mod dbmap;
 mod inner;
 
+pub use dbmap::DumpFormat;
 pub use dbmap::{DbBytes, DbI64, DbString, DbU64, DbVu64};
 pub use dbmap::{FileDbMap, FileDbMapDbBytes, FileDbMapDbString};
 pub use dbmap::{FileDbMapDbI64, FileDbMapDbU64, FileDbMapDbVu64};
-pub use inner::dbxxx::FileDbXxxInner;
+pub use inner::cdc::{ChangeKind, DbXxxChange, DbXxxChanges};
 pub use inner::dbxxx::{DbXxxIntoIter, DbXxxIter, DbXxxIterMut, DbXxxKeys, DbXxxValues};
+pub use inner::dbxxx::{DbXxxMetrics, FileDbXxxInner};
+pub use inner::dbxxx::{DbXxxPosition, DbXxxPrefix, DbXxxRange};
+pub use inner::entry::{Entry, OccupiedEntry, VacantEntry};
 use inner::semtype::*;
+pub use inner::valio::{DbXxxValueReader, DbXxxValueWriter};
 use inner::FileDbInner;
 
 /// File Database.
@@ -31,6 +36,33 @@ impl FileDb {
     pub fn sync_data(&self) -> Result<()> {
         RefCell::borrow_mut(&self.0).sync_data()
     }
+    /// Returns the buffer statistics of all opened maps, summed per component.
+    ///
+    /// The names are the same as of `CheckFileDbMap::buf_stats()`.
+    #[cfg(feature = "rabuf_stats")]
+    pub fn buf_stats(&self) -> Vec<(String, i64)> {
+        RefCell::borrow(&self.0).buf_stats()
+    }
+    /// Makes a consistent backup of this db into the `dir`.
+    ///
+    /// All maps are flushed, then the `*.key`, `*.val`, `*.htx`, `*.idx` and `*.cdc` files
+    /// are copied with a `MANIFEST` file of their sizes and CRC-32 checksums.
+    /// The files are copied, not hard-linked, because the db modifies them in place.
+    /// The db is not `Send`, so no writes interleave while this runs.
+    ///
+    /// Returns an error of `AlreadyExists` if the `dir` already has a backup.
+    pub fn backup_to<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        RefCell::borrow_mut(&self.0).backup_to(dir.as_ref())
+    }
+    /// Restores the backup in the `backup_dir` into the `path`, and opens it.
+    ///
+    /// The files are verified against the `MANIFEST`, and returns an error of
+    /// `InvalidData` if any file does not match. Returns an error of `AlreadyExists`
+    /// if the `path` already has db files.
+    pub fn restore_from<P: AsRef<Path>, Q: AsRef<Path>>(backup_dir: P, path: Q) -> Result<Self> {
+        inner::backup::restore_files(backup_dir.as_ref(), path.as_ref())?;
+        Self::open(path)
+    }
 }
 
 impl FileDb {
@@ -158,6 +190,12 @@ pub struct FileDbParams {
     pub htx_buf_size: FileBufSizeParam,
     /// hash buckets size at cretation time.
     pub buckets_size: HashBucketsParam,
+    /// creates the ordered index (idx file) for `range()`, `first()` and `last()`.
+    /// Default is false. the existing idx file is always maintained.
+    pub ordered_index: bool,
+    /// creates the change log (cdc file) for `changes_since()`.
+    /// Default is false. the existing cdc file is always maintained.
+    pub change_log: bool,
 }
 
 impl std::default::Default for FileDbParams {
@@ -168,6 +206,8 @@ impl std::default::Default for FileDbParams {
             idx_buf_size: FileBufSizeParam::PerMille(1000),
             htx_buf_size: FileBufSizeParam::PerMille(1000),
             buckets_size: HashBucketsParam::Default,
+            ordered_index: false,
+            change_log: false,
         }
     }
 }
@@ -183,14 +223,18 @@ pub trait CheckFileDbMap {
     fn count_of_free_key_piece(&self) -> Result<CountOfPerSize>;
     /// count of the free key piece
     fn count_of_free_value_piece(&self) -> Result<CountOfPerSize>;
-    /// buffer statistics
+    /// buffer statistics per component, the names are such as `key.bytes_read`.
+    ///
+    /// the components are `key`, `val` and `htx`, and `idx` and `cdc` if they exist.
+    /// each has `bytes_read`, `bytes_written`, `free_list_reuses` and `relocations`,
+    /// counted through the buffer since opening. rabuf does not expose its hits and chunks.
     #[cfg(feature = "rabuf_stats")]
     fn buf_stats(&self) -> Vec<(String, i64)>;
     /// key piece size statistics
     fn key_piece_size_stats(&self) -> Result<RecordSizeStats<Key>>;
     /// value piece size statistics
     fn value_piece_size_stats(&self) -> Result<RecordSizeStats<Value>>;
-    /// keys count statistics
+    /// bucket chain length statistics, the count of buckets per count of keys in the chain
     fn keys_count_stats(&self) -> Result<KeysCountStats>;
     /// key length statistics
     fn key_length_stats(&self) -> Result<LengthStats<Key>>;
@@ -199,6 +243,17 @@ pub trait CheckFileDbMap {
     /// htx filling rate per mill
     //#[cfg(feature = "htx")]
     fn htx_filling_rate_per_mill(&self) -> Result<(u64, u32)>;
+    /// checks the structural invariants of the files.
+    ///
+    /// the broken structures are reported in `VerifyReport::errors`,
+    /// an error is returned only if the files can not be read.
+    /// the default returns the `Unsupported` error.
+    fn verify(&self) -> Result<VerifyReport> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "verify is not supported",
+        ))
+    }
     /*
     /// convert the index node tree to graph string for debug.
     fn graph_string(&self) -> Result<String>;
@@ -262,7 +317,10 @@ impl<T: Copy> std::fmt::Display for RecordSizeStats<T> {
 pub type KeyPieceSizeStats = RecordSizeStats<Key>;
 pub type ValueRecordSizeStats = RecordSizeStats<Value>;
 
-/// piece size statistics.
+/// bucket chain length statistics.
+///
+/// this is the count of buckets per count of keys in the bucket chain,
+/// the empty buckets are counted as the length zero.
 #[derive(Debug, Default)]
 pub struct KeysCountStats(Vec<(KeysCount, u64)>);
 
@@ -280,6 +338,40 @@ impl KeysCountStats {
             }
         }
     }
+    /// returns the count of buckets that have the chain of the length.
+    pub fn buckets_count(&self, chain_length: u16) -> u64 {
+        self.0
+            .iter()
+            .find(|(a, _b)| a.as_value() == chain_length)
+            .map(|(_a, b)| *b)
+            .unwrap_or(0)
+    }
+    /// returns the length of the longest chain.
+    pub fn max_chain_length(&self) -> u16 {
+        self.0
+            .iter()
+            .filter(|(_a, b)| *b > 0)
+            .map(|(a, _b)| a.as_value())
+            .max()
+            .unwrap_or(0)
+    }
+    /// returns the average count of keys compared to find an existing key.
+    ///
+    /// the i-th key in the chain is found by i comparisons.
+    pub fn average_probe_count(&self) -> f64 {
+        let mut keys = 0u64;
+        let mut probes = 0u64;
+        for (a, b) in &self.0 {
+            let len = a.as_value() as u64;
+            keys += len * b;
+            probes += len * (len + 1) / 2 * b;
+        }
+        if keys == 0 {
+            0.0
+        } else {
+            probes as f64 / keys as f64
+        }
+    }
 }
 
 impl std::fmt::Display for KeysCountStats {
@@ -300,6 +392,89 @@ impl std::fmt::Display for KeysCountStats {
     }
 }
 
+/// the report of `CheckFileDbMap::verify()`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// count of the hash buckets.
+    pub buckets: u64,
+    /// count of the keys reachable from the hash buckets.
+    pub reachable_keys: u64,
+    /// the item count in the htx file.
+    pub item_count: u64,
+    /// count of the key pieces, including the free pieces.
+    pub key_pieces: u64,
+    /// count of the free key pieces.
+    pub free_key_pieces: u64,
+    /// count of the value pieces, including the free pieces.
+    pub value_pieces: u64,
+    /// count of the free value pieces.
+    pub free_value_pieces: u64,
+    /// the broken invariants, this is empty if the map is consistent.
+    pub errors: Vec<String>,
+}
+
+impl VerifyReport {
+    /// returns true if no error is found.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl std::fmt::Display for VerifyReport {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_fmt(format_args!(
+            "buckets: {}, reachable keys: {}, item count: {}, ",
+            self.buckets, self.reachable_keys, self.item_count
+        ))?;
+        formatter.write_fmt(format_args!(
+            "key pieces: {} (free: {}), value pieces: {} (free: {}), errors: {}",
+            self.key_pieces,
+            self.free_key_pieces,
+            self.value_pieces,
+            self.free_value_pieces,
+            self.errors.len()
+        ))?;
+        for error in &self.errors {
+            formatter.write_fmt(format_args!("\n{error}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// the report of `FileDbMap::repair()`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RepairReport {
+    /// count of the live keys linked into the rebuilt hash buckets.
+    pub item_count: u64,
+    /// count of the key pieces dropped by the broken key or the invalid value offset.
+    pub dropped_keys: u64,
+    /// count of the key pieces dropped by the same key in the later piece.
+    pub duplicate_keys: u64,
+    /// count of the free key pieces in the rebuilt free lists.
+    pub free_key_pieces: u64,
+    /// count of the free value pieces in the rebuilt free lists.
+    pub free_value_pieces: u64,
+    /// what is dropped or truncated, this is empty if nothing is lost.
+    pub notes: Vec<String>,
+}
+
+impl std::fmt::Display for RepairReport {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_fmt(format_args!(
+            "item count: {}, dropped keys: {}, duplicate keys: {}, ",
+            self.item_count, self.dropped_keys, self.duplicate_keys
+        ))?;
+        formatter.write_fmt(format_args!(
+            "free key pieces: {}, free value pieces: {}",
+            self.free_key_pieces, self.free_value_pieces
+        ))?;
+        for note in &self.notes {
+            formatter.write_fmt(format_args!("\n{note}"))?;
+        }
+        Ok(())
+    }
+}
+
 /// key or value length statistics.
 #[derive(Debug, Default)]
 pub struct LengthStats<T: Default>(Vec<(Length<T>, u64)>);
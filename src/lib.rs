@@ -30,8 +30,12 @@ use std::path::Path;
 
 pub mod filedb;
 
+pub use filedb::DumpFormat;
 pub use filedb::{DbBytes, DbI64, DbString, DbU64, DbVu64};
 pub use filedb::{DbXxxIter, DbXxxIterMut, DbXxxKeys, DbXxxValues};
+pub use filedb::{DbXxxPosition, DbXxxPrefix, DbXxxRange};
+pub use filedb::{DbXxxValueReader, DbXxxValueWriter};
+pub use filedb::{Entry, OccupiedEntry, VacantEntry};
 
 /// Open the file db. This data is stored in file.
 pub fn open_file<P: AsRef<Path>>(path: P) -> Result<filedb::FileDb> {
@@ -41,6 +45,7 @@ pub fn open_file<P: AsRef<Path>>(path: P) -> Result<filedb::FileDb> {
 /// base interface for generic key-value map store interface. this is not include `KT`
 pub trait DbXxxBase {
     /// returns the number of elements in the map.
+    /// the expired keys are counted until they are purged.
     fn len(&self) -> Result<u64>;
 
     /// returns the number of elements in the map.
@@ -75,6 +80,113 @@ pub trait DbXxxObjectSafe<KT: DbMapKeyType>: DbXxxBase {
 
     /// returns true if the map contains a value for the specified key. this key is store raw data.
     fn includes_key_kt(&mut self, key: &KT) -> Result<bool>;
+
+    /// replaces the value with `new` only if the current value is `expected`.
+    /// `None` means that the key does not exist. returns true if it is replaced.
+    ///
+    /// the default is not atomic, it calls `get_kt()` and then `put_kt()` or `del_kt()`.
+    /// an implementation whose map is shared with other handles must override this.
+    fn compare_and_swap_kt(
+        &mut self,
+        key: &KT,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool> {
+        if self.get_kt(key)?.as_deref() != expected {
+            return Ok(false);
+        }
+        match new {
+            Some(value) => self.put_kt(key, value)?,
+            None => {
+                let _ = self.del_kt(key)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// adds `delta` to the value that is a 8-byte little-endian integer,
+    /// and returns the previous value. a missing key is treated as zero.
+    ///
+    /// the default is not atomic, it calls `get_kt()` and then `put_kt()`.
+    /// an implementation whose map is shared with other handles must override this.
+    fn fetch_add_kt(&mut self, key: &KT, delta: i64) -> Result<i64> {
+        let prev = match self.get_kt(key)? {
+            Some(value) => {
+                let bytes: [u8; 8] = value.as_slice().try_into().map_err(|_| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("the value is not a 8-byte integer: {} bytes", value.len()),
+                    )
+                })?;
+                i64::from_le_bytes(bytes)
+            }
+            None => 0,
+        };
+        self.put_kt(key, &prev.wrapping_add(delta).to_le_bytes())?;
+        Ok(prev)
+    }
+
+    /// appends the bytes to the value. a missing key is inserted with the bytes.
+    fn append_kt(&mut self, key: &KT, bytes: &[u8]) -> Result<()> {
+        let mut value = self.get_kt(key)?.unwrap_or_default();
+        value.extend_from_slice(bytes);
+        self.put_kt(key, &value)
+    }
+
+    /// returns the length of the value without reading the value.
+    /// the default reads the value.
+    fn value_len_kt(&mut self, key: &KT) -> Result<Option<u64>> {
+        Ok(self.get_kt(key)?.map(|value| value.len() as u64))
+    }
+
+    /// returns the metadata of the entry without reading the value.
+    /// the default returns the `Unsupported` error, because it has no pieces.
+    fn entry_meta_kt(&mut self, key: &KT) -> Result<Option<EntryMeta>> {
+        let _ = key;
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "entry_meta is not supported",
+        ))
+    }
+
+    /// gets the values of the keys. the keys are looked up in bucket order,
+    /// and the values are returned in the order of the keys.
+    /// the default looks up the keys in the given order.
+    fn bulk_get_kt(&mut self, keys: &[&KT]) -> Result<Vec<Option<Vec<u8>>>> {
+        keys.iter().map(|key| self.get_kt(key)).collect()
+    }
+
+    /// inserts the key-value pairs. the pairs are stored in bucket order,
+    /// and the last one wins if the same keys are given.
+    /// the default stores the pairs in the given order.
+    fn bulk_put_kt(&mut self, bulk: &[(&KT, &[u8])]) -> Result<()> {
+        for (key, value) in bulk {
+            self.put_kt(key, value)?;
+        }
+        Ok(())
+    }
+
+    /// removes the keys. the keys are removed in bucket order,
+    /// and the values are returned in the order of the keys.
+    /// the default removes the keys in the given order.
+    fn bulk_delete_kt(&mut self, keys: &[&KT]) -> Result<Vec<Option<Vec<u8>>>> {
+        keys.iter().map(|key| self.del_kt(key)).collect()
+    }
+}
+
+/// metadata of an entry, see `DbXxx::entry_meta()`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EntryMeta {
+    /// the byte length of the value.
+    pub value_len: u64,
+    /// the offset of the key piece in the key file.
+    pub key_piece_offset: u64,
+    /// the size in bytes of the key piece.
+    pub key_piece_size: u32,
+    /// the offset of the value piece in the value file.
+    pub value_piece_offset: u64,
+    /// the size in bytes of the value piece.
+    pub value_piece_size: u32,
 }
 
 /// generic key-value map store interface. the key type is `KT`.
@@ -102,22 +214,16 @@ pub trait DbXxx<KT: DbMapKeyType>: DbXxxObjectSafe<KT> {
     }
 
     /// gets bulk key-value paires from the db.
+    /// the keys are looked up in the order of the buckets, not in the order of the keys.
+    #[inline]
     fn bulk_get<'a, Q>(&mut self, bulk_keys: &[&'a Q]) -> Result<Vec<Option<Vec<u8>>>>
     where
         KT: From<&'a Q>,
         Q: Ord + ?Sized,
     {
-        let mut result: Vec<(usize, Option<Vec<u8>>)> = Vec::new();
-        let mut vec: Vec<(usize, &Q)> =
-            bulk_keys.iter().enumerate().map(|(i, &a)| (i, a)).collect();
-        vec.sort_unstable_by(|a, b| b.1.cmp(a.1));
-        while let Some(ik) = vec.pop() {
-            let result_value = self.get(ik.1)?;
-            result.push((ik.0, result_value));
-        }
-        result.sort_by(|a, b| a.0.cmp(&(b.0)));
-        let ret: Vec<Option<Vec<u8>>> = result.iter().map(|a| a.1.clone()).collect();
-        Ok(ret)
+        let keys: Vec<KT> = bulk_keys.iter().map(|&a| From::from(a)).collect();
+        let keys: Vec<&KT> = keys.iter().collect();
+        self.bulk_get_kt(&keys)
     }
 
     /// gets bulk key-value paires from the db.
@@ -158,17 +264,17 @@ pub trait DbXxx<KT: DbMapKeyType>: DbXxxObjectSafe<KT> {
     }
 
     /// inserts bulk key-value pairs into the db.
+    /// the pairs are stored in the order of the buckets, and the last one wins
+    /// if the same keys are given.
+    #[inline]
     fn bulk_put<'a, Q>(&mut self, bulk: &[(&'a Q, &[u8])]) -> Result<()>
     where
         KT: From<&'a Q>,
         Q: Ord + ?Sized,
     {
-        let mut vec = bulk.to_vec();
-        vec.sort_by(|a, b| b.0.cmp(a.0));
-        while let Some(kv) = vec.pop() {
-            self.put(kv.0, kv.1)?;
-        }
-        Ok(())
+        let keys: Vec<KT> = bulk.iter().map(|kv| From::from(kv.0)).collect();
+        let bulk_kt: Vec<(&KT, &[u8])> = keys.iter().zip(bulk.iter().map(|kv| kv.1)).collect();
+        self.bulk_put_kt(&bulk_kt)
     }
 
     /// inserts bulk key-value pairs into the db.
@@ -178,12 +284,12 @@ pub trait DbXxx<KT: DbMapKeyType>: DbXxxObjectSafe<KT> {
         KT: From<&'a Q>,
         Q: Ord + ?Sized,
     {
-        let mut vec = bulk.to_vec();
-        vec.sort_unstable_by(|a, b| b.0.cmp(a.0));
-        while let Some(kv) = vec.pop() {
-            self.put(kv.0, kv.1.as_bytes())?;
-        }
-        Ok(())
+        let keys: Vec<KT> = bulk.iter().map(|kv| From::from(kv.0)).collect();
+        let bulk_kt: Vec<(&KT, &[u8])> = keys
+            .iter()
+            .zip(bulk.iter().map(|kv| kv.1.as_bytes()))
+            .collect();
+        self.bulk_put_kt(&bulk_kt)
     }
 
     /// removes a key from the db.
@@ -209,22 +315,16 @@ pub trait DbXxx<KT: DbMapKeyType>: DbXxxObjectSafe<KT> {
     }
 
     /// delete bulk key-value paires from the db.
+    /// the keys are removed in the order of the buckets, not in the order of the keys.
+    #[inline]
     fn bulk_delete<'a, Q>(&mut self, bulk_keys: &[&'a Q]) -> Result<Vec<Option<Vec<u8>>>>
     where
         KT: From<&'a Q>,
         Q: Ord + ?Sized,
     {
-        let mut result: Vec<(usize, Option<Vec<u8>>)> = Vec::new();
-        let mut vec: Vec<(usize, &Q)> =
-            bulk_keys.iter().enumerate().map(|(i, &a)| (i, a)).collect();
-        vec.sort_unstable_by(|a, b| b.1.cmp(a.1));
-        while let Some(ik) = vec.pop() {
-            let result_value = self.delete(ik.1)?;
-            result.push((ik.0, result_value));
-        }
-        result.sort_by(|a, b| a.0.cmp(&(b.0)));
-        let ret: Vec<Option<Vec<u8>>> = result.iter().map(|a| a.1.clone()).collect();
-        Ok(ret)
+        let keys: Vec<KT> = bulk_keys.iter().map(|&a| From::from(a)).collect();
+        let keys: Vec<&KT> = keys.iter().collect();
+        self.bulk_delete_kt(&keys)
     }
 
     /// delete bulk key-value paires from the db.
@@ -254,6 +354,68 @@ pub trait DbXxx<KT: DbMapKeyType>: DbXxxObjectSafe<KT> {
         self.includes_key_kt(&key_kt)
     }
 
+    /// replaces the value with `new` only if the current value is `expected`.
+    /// `None` means that the key does not exist. returns true if it is replaced.
+    #[inline]
+    fn compare_and_swap<'a, Q>(
+        &mut self,
+        key: &'a Q,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        self.compare_and_swap_kt(&key_kt, expected, new)
+    }
+
+    /// adds `delta` to the value that is a 8-byte little-endian integer,
+    /// and returns the previous value. a missing key is treated as zero.
+    #[inline]
+    fn fetch_add<'a, Q>(&mut self, key: &'a Q, delta: i64) -> Result<i64>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        self.fetch_add_kt(&key_kt, delta)
+    }
+
+    /// returns the length of the value without reading the value.
+    #[inline]
+    fn value_len<'a, Q>(&mut self, key: &'a Q) -> Result<Option<u64>>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        self.value_len_kt(&key_kt)
+    }
+
+    /// returns the metadata of the entry without reading the value.
+    #[inline]
+    fn entry_meta<'a, Q>(&mut self, key: &'a Q) -> Result<Option<EntryMeta>>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        self.entry_meta_kt(&key_kt)
+    }
+
+    /// appends the bytes to the value. a missing key is inserted with the bytes.
+    #[inline]
+    fn append<'a, Q>(&mut self, key: &'a Q, bytes: &[u8]) -> Result<()>
+    where
+        KT: From<&'a Q>,
+        Q: Ord + ?Sized,
+    {
+        let key_kt: KT = From::from(key);
+        self.append_kt(&key_kt, bytes)
+    }
+
     /// extends a collection with the contents of an iterator
     #[inline]
     fn put_from_iter<T>(&mut self, iter: T) -> Result<()>
@@ -267,15 +429,35 @@ pub trait DbXxx<KT: DbMapKeyType>: DbXxxObjectSafe<KT> {
     }
 }
 
+/// merge operator for values, this is registered per map.
+///
+/// see `FileDbMap::set_merge_operator()` and `FileDbMap::merge()`.
+pub trait MergeOperator {
+    /// returns the new value that is merged the operand into the existing value.
+    /// `existing` is `None` if the key does not exist.
+    fn merge(&self, key: &[u8], existing: Option<&[u8]>, operand: &[u8]) -> Vec<u8>;
+}
+
 /// key-value db map store interface.
 pub trait DbMap<KT: DbMapKeyType>: DbXxx<KT> {
     /// An iterator visiting all key-value pairs in arbitrary order.
     /// The iterator element type is (&'a K, &'a V).
+    ///
+    /// # Panics
+    ///
+    /// `next()` panics if a key is inserted or deleted through another handle
+    /// of the map while iterating. `try_next()` returns it as the error,
+    /// and `tolerant()` continues the iteration.
     fn iter(&self) -> DbXxxIter<KT>;
 
     /// An iterator visiting all key-value pairs in arbitrary order,
     /// with mutable references to the values.
     /// The iterator element type is (&'a K, &'a mut V).
+    ///
+    /// # Panics
+    ///
+    /// `next()` panics if a key is inserted or deleted through another handle
+    /// of the map while iterating, see `iter()`.
     fn iter_mut(&mut self) -> DbXxxIterMut<KT>;
 
     // Clears the map, removing all key-value pairs. Keeps the allocated memory for reuse.
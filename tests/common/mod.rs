@@ -0,0 +1,39 @@
+//
+// The fixtures shared by the tests
+//
+#![allow(dead_code)]
+use abyssiniandb::filedb::{FileDbMapDbString, FileDbParams, HashBucketsParam};
+
+/// removes the db of the previous run, and returns the same path.
+pub fn fresh_db(db_name: &str) -> &str {
+    let _ = std::fs::remove_dir_all(db_name);
+    db_name
+}
+
+/// the params of the small hash table, the keys are chained in the buckets.
+pub fn small_params() -> FileDbParams {
+    FileDbParams {
+        buckets_size: HashBucketsParam::Capacity(4),
+        ..Default::default()
+    }
+}
+
+/// opens the string map of the db with the params.
+pub fn open_map_with(db_name: &str, map_name: &str, params: FileDbParams) -> FileDbMapDbString {
+    let db = abyssiniandb::open_file(db_name).unwrap();
+    db.db_map_string_with_params(map_name, params).unwrap()
+}
+
+/// opens the string map `some_string_1` with the small params.
+pub fn open_map(db_name: &str) -> FileDbMapDbString {
+    open_map_with(db_name, "some_string_1", small_params())
+}
+
+/// opens the string map `some_string_1` with the small params and the ordered index.
+pub fn open_ordered_map(db_name: &str) -> FileDbMapDbString {
+    let params = FileDbParams {
+        ordered_index: true,
+        ..small_params()
+    };
+    open_map_with(db_name, "some_string_1", params)
+}
@@ -0,0 +1,90 @@
+//
+// Tests for append and merge
+//
+mod common;
+
+mod test_append {
+    use crate::common::{fresh_db, open_map};
+    use abyssiniandb::filedb::FileDbMapDbString;
+    use abyssiniandb::{DbXxx, DbXxxBase, MergeOperator};
+    use std::rc::Rc;
+
+    fn val_file_len(db_map: &mut FileDbMapDbString, db_name: &str) -> u64 {
+        db_map.sync_all().unwrap();
+        let path = format!("{db_name}/some_string_1.val");
+        std::fs::metadata(path).unwrap().len()
+    }
+
+    #[test]
+    fn test_append() {
+        let db_name = fresh_db("target/tmp/test_append/test_append.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        db_map.append("key1", b"a").unwrap();
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"a".to_vec()));
+        assert_eq!(db_map.len().unwrap(), 1);
+        // in place while the piece has the slack.
+        let len0 = val_file_len(&mut db_map, db_name);
+        for _ in 0..10 {
+            db_map.append("key1", b"b").unwrap();
+        }
+        assert_eq!(val_file_len(&mut db_map, db_name), len0);
+        // relocated when the piece is full.
+        let mut expected = b"abbbbbbbbbb".to_vec();
+        for i in 0..200u8 {
+            db_map.append("key1", &[i]).unwrap();
+            expected.push(i);
+        }
+        assert_eq!(db_map.get("key1").unwrap(), Some(expected));
+        assert_eq!(db_map.len().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_append_many_keys() {
+        let db_name = fresh_db("target/tmp/test_append/test_append_many_keys.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        for round in 0..30 {
+            for i in 0..20 {
+                db_map
+                    .append(&format!("key{i:02}"), format!("{round},").as_bytes())
+                    .unwrap();
+            }
+        }
+        let expected: String = (0..30).map(|round| format!("{round},")).collect();
+        for i in 0..20 {
+            assert_eq!(
+                db_map.get_string(&format!("key{i:02}")).unwrap(),
+                Some(expected.clone())
+            );
+        }
+    }
+
+    struct SetUnion;
+
+    impl MergeOperator for SetUnion {
+        fn merge(&self, _key: &[u8], existing: Option<&[u8]>, operand: &[u8]) -> Vec<u8> {
+            let mut set: Vec<u8> = existing.map(|v| v.to_vec()).unwrap_or_default();
+            for b in operand {
+                if !set.contains(b) {
+                    set.push(*b);
+                }
+            }
+            set.sort();
+            set
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let db_name = fresh_db("target/tmp/test_append/test_merge.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        let err = db_map.merge("key1", b"a").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+        //
+        db_map.set_merge_operator(Rc::new(SetUnion));
+        db_map.merge("key1", b"ca").unwrap();
+        db_map.merge("key1", b"bc").unwrap();
+        db_map.merge("key1", b"d").unwrap();
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"abcd".to_vec()));
+        assert_eq!(db_map.len().unwrap(), 1);
+    }
+}
@@ -0,0 +1,103 @@
+//
+// Tests for backup and restore
+//
+mod test_backup {
+    use abyssiniandb::filedb::{FileDb, FileDbParams, HashBucketsParam};
+    use abyssiniandb::{DbXxx, DbXxxBase};
+
+    fn params() -> FileDbParams {
+        FileDbParams {
+            buckets_size: HashBucketsParam::Capacity(4),
+            ..Default::default()
+        }
+    }
+
+    fn fill_db(db: &FileDb) {
+        let mut db_map = db.db_map_string_with_params("some_map1", params()).unwrap();
+        for i in 0..100 {
+            let key = format!("key{i:03}");
+            let value = format!("value{i}");
+            db_map.put(&key, value.as_bytes()).unwrap();
+        }
+        let mut db_map = db.db_map_u64_with_params("some_map2", params()).unwrap();
+        db_map.put(&1, b"one").unwrap();
+        db_map.put(&2, b"two").unwrap();
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let db_name = "target/tmp/test_backup/test_backup_and_restore.abyssiniandb";
+        let backup_name = "target/tmp/test_backup/test_backup_and_restore.backup";
+        let restore_name = "target/tmp/test_backup/test_backup_and_restore.restored";
+        let _ = std::fs::remove_dir_all(db_name);
+        let _ = std::fs::remove_dir_all(backup_name);
+        let _ = std::fs::remove_dir_all(restore_name);
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        fill_db(&db);
+        // the db stays open, the unflushed buffers must be in the backup.
+        db.backup_to(backup_name).unwrap();
+        let manifest = std::fs::read_to_string(format!("{backup_name}/MANIFEST")).unwrap();
+        assert!(manifest.starts_with("# abyssiniandb backup manifest\n"));
+        assert!(manifest.contains("some_map1.key\t"));
+        assert!(manifest.contains("some_map2.val\t"));
+        // modification after the backup is not in the backup.
+        let mut db_map = db.db_map_string("some_map1").unwrap();
+        db_map.put("after", b"backup").unwrap();
+        //
+        let restored = FileDb::restore_from(backup_name, restore_name).unwrap();
+        let mut db_map = restored
+            .db_map_string_with_params("some_map1", params())
+            .unwrap();
+        assert_eq!(db_map.len().unwrap(), 100);
+        assert_eq!(db_map.get("key042").unwrap(), Some(b"value42".to_vec()));
+        assert_eq!(db_map.get("after").unwrap(), None);
+        let mut db_map = restored
+            .db_map_u64_with_params("some_map2", params())
+            .unwrap();
+        assert_eq!(db_map.get(&2).unwrap(), Some(b"two".to_vec()));
+    }
+
+    #[test]
+    fn test_backup_already_exists() {
+        let db_name = "target/tmp/test_backup/test_backup_already_exists.abyssiniandb";
+        let backup_name = "target/tmp/test_backup/test_backup_already_exists.backup";
+        let _ = std::fs::remove_dir_all(db_name);
+        let _ = std::fs::remove_dir_all(backup_name);
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        fill_db(&db);
+        db.backup_to(backup_name).unwrap();
+        let err = db.backup_to(backup_name).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        // the restore does not overwrite the existing db.
+        drop(db);
+        let err = FileDb::restore_from(backup_name, db_name).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn test_restore_corrupted() {
+        let db_name = "target/tmp/test_backup/test_restore_corrupted.abyssiniandb";
+        let backup_name = "target/tmp/test_backup/test_restore_corrupted.backup";
+        let restore_name = "target/tmp/test_backup/test_restore_corrupted.restored";
+        let _ = std::fs::remove_dir_all(db_name);
+        let _ = std::fs::remove_dir_all(backup_name);
+        let _ = std::fs::remove_dir_all(restore_name);
+        {
+            let db = abyssiniandb::open_file(db_name).unwrap();
+            fill_db(&db);
+            db.backup_to(backup_name).unwrap();
+        }
+        // flips a byte of the value file.
+        let val_path = format!("{backup_name}/some_map1.val");
+        let mut bytes = std::fs::read(&val_path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        std::fs::write(&val_path, &bytes).unwrap();
+        //
+        let err = FileDb::restore_from(backup_name, restore_name).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        // nothing is left in the restore target.
+        let count = std::fs::read_dir(restore_name).unwrap().count();
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,75 @@
+//
+// Tests for buffer statistics
+//
+#[cfg(feature = "rabuf_stats")]
+mod test_buf_stats {
+    use abyssiniandb::filedb::{CheckFileDbMap, FileDbParams, HashBucketsParam};
+    use abyssiniandb::DbXxx;
+
+    fn stat(stats: &[(String, i64)], name: &str) -> i64 {
+        stats
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| *v)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_per_component() {
+        let db_name = "target/tmp/test_buf_stats/test_per_component.abyssiniandb";
+        let _ = std::fs::remove_dir_all(db_name);
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db
+            .db_map_string_with_params(
+                "some_map1",
+                FileDbParams {
+                    buckets_size: HashBucketsParam::Capacity(4),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        for i in 0..100 {
+            db_map.put(&format!("key{i}"), b"value").unwrap();
+        }
+        for i in 0..100 {
+            db_map.get(&format!("key{i}")).unwrap();
+        }
+        let stats = db_map.buf_stats();
+        for component in ["key", "val", "htx"] {
+            assert!(stat(&stats, &format!("{component}.bytes_read")) > 0);
+            assert!(stat(&stats, &format!("{component}.bytes_written")) > 0);
+        }
+        let metrics = db_map.metrics();
+        assert_eq!(
+            stat(&stats, "key.bytes_written"),
+            metrics.key_bytes_written as i64
+        );
+        assert_eq!(
+            stat(&stats, "val.bytes_read"),
+            metrics.value_bytes_read as i64
+        );
+        assert_eq!(
+            stat(&stats, "key.relocations") + stat(&stats, "val.relocations"),
+            metrics.relocations as i64
+        );
+        assert!(!stats.iter().any(|(n, _)| n.starts_with("idx.")));
+    }
+
+    #[test]
+    fn test_all_maps() {
+        let db_name = "target/tmp/test_buf_stats/test_all_maps.abyssiniandb";
+        let _ = std::fs::remove_dir_all(db_name);
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map1 = db.db_map_string("some_map1").unwrap();
+        let mut db_map2 = db.db_map_u64("some_map2").unwrap();
+        db_map1.put("key1", b"value1").unwrap();
+        db_map2.put(&1, b"value1").unwrap();
+        let stats1 = db_map1.buf_stats();
+        let stats2 = db_map2.buf_stats();
+        let all = db.buf_stats();
+        assert_eq!(all.len(), stats1.len());
+        for (name, val) in &all {
+            assert_eq!(*val, stat(&stats1, name) + stat(&stats2, name));
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//
+// Tests for the bulk builder
+//
+mod common;
+
+mod test_build {
+    use crate::common::{fresh_db, open_ordered_map};
+    use abyssiniandb::filedb::DbString;
+    use abyssiniandb::{DbMap, DbXxx, DbXxxBase};
+
+    fn pairs(count: usize) -> Vec<(DbString, Vec<u8>)> {
+        (0..count)
+            .map(|i| {
+                let key = DbString::from(format!("key{i:05}").as_str());
+                let value = format!("value{i}").repeat(i % 5).into_bytes();
+                (key, value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build_from_iter() {
+        let db_name = fresh_db("target/tmp/test_build/test_build_from_iter.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        let count = db_map.build_from_iter(pairs(1000)).unwrap();
+        assert_eq!(count, 1000);
+        assert_eq!(db_map.len().unwrap(), 1000);
+        for (key, value) in pairs(1000) {
+            assert_eq!(db_map.get(&key).unwrap(), Some(value));
+        }
+        assert_eq!(db_map.iter().count(), 1000);
+        let keys: Vec<DbString> = db_map.iter_ordered().unwrap().map(|(k, _v)| k).collect();
+        let expected: Vec<DbString> = pairs(1000).into_iter().map(|(k, _v)| k).collect();
+        assert_eq!(keys, expected);
+        // the map is opened normally, and it can be modified
+        drop(db_map);
+        let mut db_map = open_ordered_map(db_name);
+        assert_eq!(db_map.len().unwrap(), 1000);
+        assert_eq!(db_map.get("key00003").unwrap(), Some(b"value3".repeat(3)));
+        db_map.put("key00003", b"new").unwrap();
+        db_map.put("key99999", b"added").unwrap();
+        assert_eq!(
+            db_map.delete("key00004").unwrap(),
+            Some(b"value4".repeat(4))
+        );
+        assert_eq!(db_map.len().unwrap(), 1000);
+        assert_eq!(db_map.get("key00003").unwrap(), Some(b"new".to_vec()));
+        assert_eq!(db_map.get("key99999").unwrap(), Some(b"added".to_vec()));
+        assert_eq!(db_map.get("key00004").unwrap(), None);
+        assert_eq!(db_map.iter().count(), 1000);
+    }
+
+    #[test]
+    fn test_build_same_keys() {
+        let db_name = fresh_db("target/tmp/test_build/test_build_same_keys.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        let mut input = pairs(10);
+        input.push((DbString::from("key00001"), b"last".to_vec()));
+        input.push((DbString::from("key00005"), b"last".to_vec()));
+        let count = db_map.build_from_iter(input).unwrap();
+        assert_eq!(count, 10);
+        assert_eq!(db_map.len().unwrap(), 10);
+        assert_eq!(db_map.get("key00001").unwrap(), Some(b"last".to_vec()));
+        assert_eq!(db_map.get("key00005").unwrap(), Some(b"last".to_vec()));
+        assert_eq!(db_map.iter().count(), 10);
+    }
+
+    #[test]
+    fn test_build_not_empty() {
+        let db_name = fresh_db("target/tmp/test_build/test_build_not_empty.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        assert_eq!(db_map.build_from_iter(Vec::new()).unwrap(), 0);
+        db_map.put("key1", b"value1").unwrap();
+        let err = db_map.build_from_iter(pairs(10)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(db_map.len().unwrap(), 1);
+    }
+}
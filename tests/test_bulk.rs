@@ -0,0 +1,104 @@
+//
+// Tests for bulk operations in bucket order
+//
+mod common;
+
+mod test_bulk {
+    use crate::common::{fresh_db, open_map};
+    use abyssiniandb::{DbXxx, DbXxxBase};
+
+    #[test]
+    fn test_bulk_put_get() {
+        let db_name = fresh_db("target/tmp/test_bulk/test_bulk_put_get.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        let keys: Vec<String> = (0..100).map(|i| format!("key{i:03}")).collect();
+        let values: Vec<String> = (0..100)
+            .map(|i| format!("value{i}").repeat(i % 7))
+            .collect();
+        let bulk: Vec<(&str, &[u8])> = keys
+            .iter()
+            .zip(values.iter())
+            .map(|(k, v)| (k.as_str(), v.as_bytes()))
+            .collect();
+        db_map.bulk_put(&bulk).unwrap();
+        assert_eq!(db_map.len().unwrap(), 100);
+        // the results are in the caller's order
+        let mut query: Vec<&str> = keys.iter().rev().map(|k| k.as_str()).collect();
+        query.push("missing");
+        let result = db_map.bulk_get(&query).unwrap();
+        assert_eq!(result.len(), 101);
+        for (i, opt) in result.iter().take(100).enumerate() {
+            assert_eq!(opt.as_deref(), Some(values[99 - i].as_bytes()));
+        }
+        assert_eq!(result[100], None);
+        // the values are kept after reopening
+        drop(db_map);
+        let mut db_map = open_map(db_name);
+        for (k, v) in keys.iter().zip(values.iter()) {
+            assert_eq!(db_map.get(k).unwrap(), Some(v.as_bytes().to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_bulk_put_same_keys() {
+        let db_name = fresh_db("target/tmp/test_bulk/test_bulk_put_same_keys.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        db_map.put("key1", b"old").unwrap();
+        db_map
+            .bulk_put(&[
+                ("key1", b"a"),
+                ("key2", b"b"),
+                ("key1", b"c"),
+                ("key2", b"d"),
+                ("key3", b"e"),
+            ])
+            .unwrap();
+        assert_eq!(db_map.len().unwrap(), 3);
+        let result = db_map.bulk_get(&["key1", "key2", "key3"]).unwrap();
+        assert_eq!(
+            result,
+            vec![
+                Some(b"c".to_vec()),
+                Some(b"d".to_vec()),
+                Some(b"e".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bulk_delete() {
+        let db_name = fresh_db("target/tmp/test_bulk/test_bulk_delete.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        db_map
+            .bulk_put_string(&[
+                ("key1", "a".to_string()),
+                ("key2", "b".to_string()),
+                ("key3", "c".to_string()),
+            ])
+            .unwrap();
+        let result = db_map
+            .bulk_delete(&["key3", "missing", "key1", "key3"])
+            .unwrap();
+        assert_eq!(
+            result,
+            vec![Some(b"c".to_vec()), None, Some(b"a".to_vec()), None]
+        );
+        assert_eq!(db_map.len().unwrap(), 1);
+        // the freed pieces are reused by the next bulk put
+        db_map
+            .bulk_put(&[("key4", b"x"), ("key5", b"y"), ("key6", b"z")])
+            .unwrap();
+        let result = db_map
+            .bulk_get_string(&["key2", "key4", "key5", "key6"])
+            .unwrap();
+        assert_eq!(
+            result,
+            vec![
+                Some("b".to_string()),
+                Some("x".to_string()),
+                Some("y".to_string()),
+                Some("z".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//
+// Tests for compare_and_swap and fetch_add
+//
+mod common;
+
+mod test_cas {
+    use crate::common::{fresh_db, open_map};
+    use abyssiniandb::{DbXxx, DbXxxBase};
+
+    #[test]
+    fn test_compare_and_swap() {
+        let db_name = fresh_db("target/tmp/test_cas/test_compare_and_swap.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        // insert only if missing
+        assert!(db_map.compare_and_swap("key1", None, Some(b"a")).unwrap());
+        assert!(!db_map.compare_and_swap("key1", None, Some(b"b")).unwrap());
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"a".to_vec()));
+        // replace
+        assert!(!db_map
+            .compare_and_swap("key1", Some(b"x"), Some(b"b"))
+            .unwrap());
+        assert!(db_map
+            .compare_and_swap("key1", Some(b"a"), Some(b"bb"))
+            .unwrap());
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"bb".to_vec()));
+        // delete
+        assert!(!db_map.compare_and_swap("key1", Some(b"a"), None).unwrap());
+        assert!(db_map.compare_and_swap("key1", Some(b"bb"), None).unwrap());
+        assert_eq!(db_map.get("key1").unwrap(), None);
+        assert_eq!(db_map.len().unwrap(), 0);
+        // nothing
+        assert!(db_map.compare_and_swap("key1", None, None).unwrap());
+        assert_eq!(db_map.len().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_fetch_add() {
+        let db_name = fresh_db("target/tmp/test_cas/test_fetch_add.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        let mut db_map2 = db_map.clone();
+        assert_eq!(db_map.fetch_add("counter", 5).unwrap(), 0);
+        assert_eq!(db_map2.fetch_add("counter", 3).unwrap(), 5);
+        assert_eq!(db_map.fetch_add("counter", -10).unwrap(), 8);
+        assert_eq!(
+            db_map.get("counter").unwrap(),
+            Some((-2i64).to_le_bytes().to_vec())
+        );
+        assert_eq!(db_map.len().unwrap(), 1);
+        //
+        db_map.put_string("not_counter", "abc").unwrap();
+        let err = db_map.fetch_add("not_counter", 1).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            db_map.get_string("not_counter").unwrap(),
+            Some("abc".into())
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//
+// Tests for change data capture
+//
+mod common;
+
+mod test_cdc {
+    use crate::common::{fresh_db, open_map_with, small_params};
+    use abyssiniandb::filedb::{ChangeKind, FileDbMapDbString, FileDbParams};
+    use abyssiniandb::{DbMapKeyType, DbXxx, DbXxxBase};
+    use std::time::Duration;
+
+    fn open_map(db_name: &str, change_log: bool) -> FileDbMapDbString {
+        let params = FileDbParams {
+            change_log,
+            ..small_params()
+        };
+        open_map_with(db_name, "some_map1", params)
+    }
+
+    fn changes(db_map: &FileDbMapDbString, seq: u64) -> Vec<(u64, ChangeKind, String)> {
+        db_map
+            .changes_since(seq)
+            .unwrap()
+            .map(|c| c.unwrap())
+            .map(|c| (c.seq, c.kind, key_string(&c.key)))
+            .collect()
+    }
+
+    fn key_string<KT: DbMapKeyType>(key: &KT) -> String {
+        String::from_utf8(key.as_bytes().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_put_and_delete() {
+        let db_name = fresh_db("target/tmp/test_cdc/test_put_and_delete.abyssiniandb");
+        let mut db_map = open_map(db_name, true);
+        assert!(db_map.has_change_log());
+        assert_eq!(db_map.last_change_seq().unwrap(), 0);
+        db_map.put("key1", b"value1").unwrap();
+        db_map.put("key2", b"value2").unwrap();
+        db_map.put("key1", b"value1-2").unwrap();
+        db_map.delete("key2").unwrap();
+        // deleting the missing key is not a change.
+        db_map.delete("key3").unwrap();
+        assert_eq!(db_map.last_change_seq().unwrap(), 4);
+        assert_eq!(
+            changes(&db_map, 0),
+            vec![
+                (1, ChangeKind::Put, "key1".to_string()),
+                (2, ChangeKind::Put, "key2".to_string()),
+                (3, ChangeKind::Put, "key1".to_string()),
+                (4, ChangeKind::Delete, "key2".to_string()),
+            ]
+        );
+        assert_eq!(
+            changes(&db_map, 3),
+            vec![(4, ChangeKind::Delete, "key2".to_string())]
+        );
+        assert!(changes(&db_map, 4).is_empty());
+    }
+
+    #[test]
+    fn test_tail() {
+        let db_name = fresh_db("target/tmp/test_cdc/test_tail.abyssiniandb");
+        let mut db_map = open_map(db_name, true);
+        db_map.put("key1", b"value1").unwrap();
+        let mut iter = db_map.changes_since(0).unwrap();
+        assert_eq!(iter.next().map(|c| c.unwrap().seq), Some(1));
+        assert!(iter.next().is_none());
+        // the iterator can be polled again after the new changes.
+        db_map.put("key2", b"value2").unwrap();
+        db_map.append("key1", b"-appended").unwrap();
+        let change = iter.next().unwrap().unwrap();
+        assert_eq!((change.seq, change.kind), (2, ChangeKind::Put));
+        assert_eq!(key_string(&change.key), "key2");
+        let change = iter.next().unwrap().unwrap();
+        assert_eq!(
+            (change.seq, key_string(&change.key)),
+            (3, "key1".to_string())
+        );
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_other_mutations() {
+        let db_name = fresh_db("target/tmp/test_cdc/test_other_mutations.abyssiniandb");
+        let mut db_map = open_map(db_name, true);
+        db_map
+            .bulk_put(&[("key1", b"value1".as_slice()), ("key2", b"value2")])
+            .unwrap();
+        db_map
+            .put_with_ttl("key3", b"value3", Duration::ZERO)
+            .unwrap();
+        assert_eq!(db_map.purge_expired().unwrap(), 1);
+        assert_eq!(
+            changes(&db_map, 2),
+            vec![
+                (3, ChangeKind::Put, "key3".to_string()),
+                (4, ChangeKind::Delete, "key3".to_string()),
+            ]
+        );
+        let mut keys: Vec<_> = changes(&db_map, 0)
+            .into_iter()
+            .take(2)
+            .map(|(_, _, key)| key)
+            .collect();
+        keys.sort();
+        assert_eq!(keys, vec!["key1".to_string(), "key2".to_string()]);
+    }
+
+    #[test]
+    fn test_truncate() {
+        let db_name = fresh_db("target/tmp/test_cdc/test_truncate.abyssiniandb");
+        let mut db_map = open_map(db_name, true);
+        for i in 0..10 {
+            db_map.put(&format!("key{i}"), b"value").unwrap();
+        }
+        let mut iter = db_map.changes_since(2).unwrap();
+        assert_eq!(iter.next().map(|c| c.unwrap().seq), Some(3));
+        assert_eq!(db_map.truncate_changes(5).unwrap(), 5);
+        assert_eq!(db_map.truncate_changes(5).unwrap(), 0);
+        // the iterator resumes from the first remaining change.
+        assert_eq!(iter.next().map(|c| c.unwrap().seq), Some(6));
+        assert_eq!(changes(&db_map, 5).len(), 5);
+        let err = db_map.changes_since(3).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        // the sequence numbers are not reused.
+        assert_eq!(db_map.truncate_changes(u64::MAX).unwrap(), 5);
+        assert!(changes(&db_map, 10).is_empty());
+        db_map.put("key10", b"value").unwrap();
+        assert_eq!(
+            changes(&db_map, 10),
+            vec![(11, ChangeKind::Put, "key10".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_polling() {
+        let db_name = fresh_db("target/tmp/test_cdc/test_polling.abyssiniandb");
+        let mut db_map = open_map(db_name, true);
+        let mut last = 0;
+        let mut seen = Vec::new();
+        for round in 0..20u64 {
+            for i in 0..=round % 4 {
+                let key = format!("key{}", "x".repeat((round + i) as usize));
+                db_map.put(&key, b"value").unwrap();
+            }
+            let polled = changes(&db_map, last);
+            assert!(!polled.is_empty());
+            for (seq, _kind, _key) in &polled {
+                assert_eq!(*seq, last + 1);
+                last = *seq;
+                seen.push(*seq);
+            }
+            assert!(changes(&db_map, last).is_empty());
+            // the older changes are found again, and they are truncated.
+            if round % 5 == 4 {
+                let first = last - polled.len() as u64;
+                assert_eq!(changes(&db_map, first).len(), polled.len());
+                db_map.truncate_changes(first).unwrap();
+            }
+        }
+        assert_eq!(seen, (1..=last).collect::<Vec<u64>>());
+        assert_eq!(db_map.last_change_seq().unwrap(), last);
+    }
+
+    #[test]
+    fn test_reopen() {
+        let db_name = fresh_db("target/tmp/test_cdc/test_reopen.abyssiniandb");
+        {
+            let mut db_map = open_map(db_name, true);
+            db_map.put("key1", b"value1").unwrap();
+            db_map.put("key2", b"value2").unwrap();
+            db_map.sync_data().unwrap();
+        }
+        {
+            // the existing change log is always maintained.
+            let mut db_map = open_map(db_name, false);
+            assert!(db_map.has_change_log());
+            assert_eq!(db_map.last_change_seq().unwrap(), 2);
+            db_map.delete("key1").unwrap();
+            assert_eq!(
+                changes(&db_map, 1),
+                vec![
+                    (2, ChangeKind::Put, "key2".to_string()),
+                    (3, ChangeKind::Delete, "key1".to_string()),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_reopen_after_crash() {
+        let db_name = fresh_db("target/tmp/test_cdc/test_reopen_after_crash.abyssiniandb");
+        {
+            let mut db_map = open_map(db_name, true);
+            db_map.put("key1", b"value1").unwrap();
+            db_map.sync_data().unwrap();
+            db_map.put("key2", b"value2").unwrap();
+            db_map.truncate_changes(1).unwrap();
+            db_map.put("key3", b"value3").unwrap();
+            // the header is not written after this.
+        }
+        // the broken record at the end, and the new log of the truncating.
+        let path = std::path::Path::new(db_name).join("some_map1.cdc");
+        let mut bytes = std::fs::read(&path).unwrap();
+        let len = bytes.len();
+        bytes.extend_from_slice(&4u64.to_le_bytes());
+        bytes.push(1);
+        std::fs::write(&path, bytes).unwrap();
+        std::fs::write(path.with_extension("cdc.tmp"), b"broken").unwrap();
+        {
+            let mut db_map = open_map(db_name, true);
+            assert_eq!(db_map.last_change_seq().unwrap(), 3);
+            assert_eq!(std::fs::metadata(&path).unwrap().len(), len as u64);
+            assert!(!path.with_extension("cdc.tmp").exists());
+            db_map.delete("key2").unwrap();
+            assert_eq!(
+                changes(&db_map, 1),
+                vec![
+                    (2, ChangeKind::Put, "key2".to_string()),
+                    (3, ChangeKind::Put, "key3".to_string()),
+                    (4, ChangeKind::Delete, "key2".to_string()),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_not_enabled() {
+        let db_name = fresh_db("target/tmp/test_cdc/test_not_enabled.abyssiniandb");
+        let mut db_map = open_map(db_name, false);
+        db_map.put("key1", b"value1").unwrap();
+        assert!(!db_map.has_change_log());
+        let err = db_map.changes_since(0).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+        assert_eq!(db_map.len().unwrap(), 1);
+    }
+}
@@ -0,0 +1,87 @@
+//
+// Tests for bucket chain length statistics
+//
+mod test_chain_stats {
+    use abyssiniandb::filedb::{CheckFileDbMap, FileDbParams, HashBucketsParam};
+    use abyssiniandb::{DbXxx, DbXxxBase};
+
+    #[test]
+    fn test_keys_count_stats() {
+        let db_name = "target/tmp/test_chain_stats/test_keys_count_stats.abyssiniandb";
+        let _ = std::fs::remove_dir_all(db_name);
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db
+            .db_map_string_with_params(
+                "some_map1",
+                FileDbParams {
+                    buckets_size: HashBucketsParam::BucketsSize(4),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let stats = db_map.keys_count_stats().unwrap();
+        assert_eq!(stats.buckets_count(0), 4);
+        assert_eq!(stats.max_chain_length(), 0);
+        assert_eq!(stats.average_probe_count(), 0.0);
+        //
+        for i in 0..20 {
+            db_map.put(&format!("key{i}"), b"value").unwrap();
+        }
+        let stats = db_map.keys_count_stats().unwrap();
+        let max = stats.max_chain_length();
+        // 20 keys in 4 buckets.
+        assert!(max >= 5);
+        let mut buckets = 0;
+        let mut keys = 0;
+        let mut probes = 0;
+        for len in 0..=max {
+            let count = stats.buckets_count(len);
+            buckets += count;
+            keys += len as u64 * count;
+            probes += (len as u64 * (len as u64 + 1) / 2) * count;
+        }
+        assert_eq!(buckets, 4);
+        assert_eq!(keys, db_map.len().unwrap());
+        assert_eq!(stats.average_probe_count(), probes as f64 / keys as f64);
+        assert!(stats.average_probe_count() >= 3.0);
+    }
+
+    // the offsets in the key file are encoded with `vf_vu64`.
+    #[cfg(feature = "vf_vu64")]
+    #[test]
+    fn test_cyclic_chain() {
+        let db_name = "target/tmp/test_chain_stats/test_cyclic_chain.abyssiniandb";
+        let _ = std::fs::remove_dir_all(db_name);
+        {
+            let db = abyssiniandb::open_file(db_name).unwrap();
+            let mut db_map = db
+                .db_map_string_with_params(
+                    "some_map1",
+                    FileDbParams {
+                        buckets_size: HashBucketsParam::BucketsSize(1),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            db_map.put("key_a", b"value").unwrap();
+            db_map.put("key_b", b"value").unwrap();
+            db_map.sync_data().unwrap();
+        }
+        // the head of the chain is linked to itself.
+        let htx = std::fs::read(format!("{db_name}/some_map1.htx")).unwrap();
+        let head = u64::from_le_bytes(htx[128..136].try_into().unwrap());
+        let key_path = format!("{db_name}/some_map1.key");
+        let mut key = std::fs::read(&key_path).unwrap();
+        let pos = key.windows(5).position(|w| w == b"key_b").unwrap();
+        // the key is followed by the value offset and the next offset, in 8 bytes units.
+        assert_eq!(head, pos as u64 - 2);
+        key[pos + 6] = (head / 8) as u8;
+        std::fs::write(&key_path, key).unwrap();
+        //
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let db_map = db.db_map_string("some_map1").unwrap();
+        let stats = db_map.keys_count_stats().unwrap();
+        assert_eq!(stats.max_chain_length(), 3);
+        assert!(!db_map.verify().unwrap().is_ok());
+    }
+}
@@ -0,0 +1,97 @@
+//
+// Tests for the resumable cursor
+//
+mod common;
+
+mod test_cursor {
+    use crate::common::{fresh_db, open_map};
+    use abyssiniandb::filedb::FileDbMapDbString;
+    use abyssiniandb::{DbMap, DbString, DbXxx, DbXxxPosition};
+
+    fn read_page(
+        db_map: &FileDbMapDbString,
+        pos: Option<&DbXxxPosition>,
+        page_size: usize,
+    ) -> (Vec<DbString>, DbXxxPosition) {
+        let mut iter = match pos {
+            Some(pos) => db_map.iter_from(pos).unwrap(),
+            None => db_map.iter(),
+        };
+        let mut keys = Vec::new();
+        for _ in 0..page_size {
+            match iter.next() {
+                Some((k, _)) => keys.push(k),
+                None => break,
+            }
+        }
+        (keys, iter.position())
+    }
+
+    #[test]
+    fn test_cursor_pages() {
+        let db_name = fresh_db("target/tmp/test_cursor/test_cursor_pages.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        for i in 0..100 {
+            db_map.put_string(&format!("key{i:03}"), "v").unwrap();
+        }
+        //
+        let mut all: Vec<DbString> = Vec::new();
+        let (keys, mut pos) = read_page(&db_map, None, 7);
+        all.extend(keys);
+        while !pos.is_end() {
+            // the token is passed through its string form.
+            let token = pos.to_string();
+            let restored: DbXxxPosition = token.parse().unwrap();
+            assert_eq!(restored, pos);
+            let (keys, next_pos) = read_page(&db_map, Some(&restored), 7);
+            all.extend(keys);
+            pos = next_pos;
+        }
+        assert_eq!(all.len(), 100);
+        all.sort();
+        let expected: Vec<DbString> = (0..100).map(|i| format!("key{i:03}").into()).collect();
+        assert_eq!(all, expected);
+    }
+
+    #[test]
+    fn test_cursor_with_changes() {
+        let db_name = fresh_db("target/tmp/test_cursor/test_cursor_with_changes.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        for i in 0..100 {
+            db_map.put_string(&format!("key{i:03}"), "v").unwrap();
+        }
+        let (first, pos) = read_page(&db_map, None, 50);
+        // delete the visited keys except the last, and insert the new keys.
+        for k in first[..first.len() - 1].iter() {
+            db_map.delete(k).unwrap();
+        }
+        for i in 100..120 {
+            db_map.put_string(&format!("key{i:03}"), "v").unwrap();
+        }
+        let bytes = pos.to_bytes();
+        let pos = DbXxxPosition::from_bytes(&bytes).unwrap();
+        let rest: Vec<DbString> = db_map.iter_from(&pos).unwrap().map(|(k, _)| k).collect();
+        // the untouched keys are visited exactly once.
+        for i in 0..100 {
+            let k: DbString = format!("key{i:03}").into();
+            let n = first.iter().filter(|&x| *x == k).count()
+                + rest.iter().filter(|&x| *x == k).count();
+            assert_eq!(n, 1, "{k:?}");
+        }
+    }
+
+    #[test]
+    fn test_cursor_invalid() {
+        let db_name = fresh_db("target/tmp/test_cursor/test_cursor_invalid.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        db_map.put_string("key1", "v").unwrap();
+        let (_, pos) = read_page(&db_map, None, 1);
+        assert!(pos.is_end());
+        assert_eq!(db_map.iter_from(&pos).unwrap().count(), 0);
+        //
+        assert!(DbXxxPosition::from_bytes(&[0u8; 3]).is_err());
+        assert!("xyz".parse::<DbXxxPosition>().is_err());
+        let other = DbXxxPosition::from_bytes(&[1u8; 32]).unwrap();
+        assert!(db_map.iter_from(&other).is_err());
+    }
+}
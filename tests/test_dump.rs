@@ -0,0 +1,149 @@
+//
+// Tests for export and import
+//
+mod common;
+
+mod test_dump {
+    use crate::common::{fresh_db, open_map_with, small_params};
+    use abyssiniandb::filedb::FileDbMapDbString;
+    use abyssiniandb::{DbXxx, DbXxxBase, DumpFormat};
+    use std::time::Duration;
+
+    fn put_samples(db_map: &mut FileDbMapDbString) {
+        db_map.put("key1", b"value1").unwrap();
+        db_map.put("key\ttab", b"line1\nline2\r\n").unwrap();
+        db_map
+            .put("back\\slash", b"\x00\x01\xff\xfe bytes")
+            .unwrap();
+        db_map
+            .put("space key", "\u{3042}\u{3044}".as_bytes())
+            .unwrap();
+        db_map.put("empty", b"").unwrap();
+        db_map
+            .put_with_ttl("expired", b"gone", Duration::ZERO)
+            .unwrap();
+    }
+
+    fn check_samples(db_map: &mut FileDbMapDbString) {
+        assert_eq!(db_map.len().unwrap(), 5);
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(
+            db_map.get("key\ttab").unwrap(),
+            Some(b"line1\nline2\r\n".to_vec())
+        );
+        assert_eq!(
+            db_map.get("back\\slash").unwrap(),
+            Some(b"\x00\x01\xff\xfe bytes".to_vec())
+        );
+        assert_eq!(
+            db_map.get_string("space key").unwrap(),
+            Some("\u{3042}\u{3044}".to_string())
+        );
+        assert_eq!(db_map.get("empty").unwrap(), Some(Vec::new()));
+        assert_eq!(db_map.get("expired").unwrap(), None);
+    }
+
+    fn round_trip(db_name: &str, format: DumpFormat) {
+        let db_name = fresh_db(db_name);
+        let mut db_map = open_map_with(db_name, "src", small_params());
+        put_samples(&mut db_map);
+        let mut dump = Vec::new();
+        assert_eq!(db_map.export(&mut dump, format).unwrap(), 5);
+        //
+        let mut db_map2 = open_map_with(db_name, "dst", small_params());
+        assert_eq!(db_map2.import(dump.as_slice(), format).unwrap(), 5);
+        check_samples(&mut db_map2);
+    }
+
+    #[test]
+    fn test_binary() {
+        round_trip(
+            "target/tmp/test_dump/test_binary.abyssiniandb",
+            DumpFormat::Binary,
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        round_trip(
+            "target/tmp/test_dump/test_text.abyssiniandb",
+            DumpFormat::Text,
+        );
+    }
+
+    #[test]
+    fn test_text_layout() {
+        let db_name = fresh_db("target/tmp/test_dump/test_text_layout.abyssiniandb");
+        let mut db_map = open_map_with(db_name, "src", small_params());
+        db_map.put("key\ttab", b"a\\b\n").unwrap();
+        let mut dump = Vec::new();
+        db_map.export(&mut dump, DumpFormat::Text).unwrap();
+        assert_eq!(
+            String::from_utf8(dump).unwrap(),
+            "# abyssiniandb dump\tstring\\x00\\x00\t1\nkey\\ttab\ta\\\\b\\n\n"
+        );
+    }
+
+    #[test]
+    fn test_import_fixtures() {
+        let db_name = fresh_db("target/tmp/test_dump/test_import_fixtures.abyssiniandb");
+        let mut db_map = open_map_with(db_name, "fruits", small_params());
+        let file = std::fs::File::open("fixtures/test-fruits.txt").unwrap();
+        db_map.import(file, DumpFormat::Text).unwrap();
+        assert_eq!(db_map.len().unwrap(), 5133);
+        assert_eq!(
+            db_map.get_string("10890194").unwrap(),
+            Some("banana".to_string())
+        );
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let db_name = fresh_db("target/tmp/test_dump/test_import_errors.abyssiniandb");
+        let mut db_map = open_map_with(db_name, "src", small_params());
+        // the key type signature does not match
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let db_map_u64 = db.db_map_u64("u64").unwrap();
+        let mut dump = Vec::new();
+        db_map_u64.export(&mut dump, DumpFormat::Binary).unwrap();
+        let err = db_map
+            .import(dump.as_slice(), DumpFormat::Binary)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        // the entry count does not match
+        let text = "# abyssiniandb dump\tstring\\x00\\x00\t2\nkey1\tvalue1\n";
+        let err = db_map
+            .import(text.as_bytes(), DumpFormat::Text)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        // the truncated binary dump
+        db_map.put("key1", b"value1").unwrap();
+        let mut dump = Vec::new();
+        db_map.export(&mut dump, DumpFormat::Binary).unwrap();
+        dump.pop();
+        let mut db_map2 = open_map_with(db_name, "dst", small_params());
+        let err = db_map2
+            .import(dump.as_slice(), DumpFormat::Binary)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        // the value length over the max is rejected before reading it
+        let mut dump = b"abysdbD\0string\0\0".to_vec();
+        dump.extend_from_slice(&1u64.to_le_bytes());
+        dump.extend_from_slice(&4u64.to_le_bytes());
+        dump.extend_from_slice(b"key2");
+        dump.extend_from_slice(&0x8000_0000u64.to_le_bytes());
+        let err = db_map2
+            .import(dump.as_slice(), DumpFormat::Binary)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        // the key length over the max
+        let mut dump = b"abysdbD\0string\0\0".to_vec();
+        dump.extend_from_slice(&1u64.to_le_bytes());
+        dump.extend_from_slice(&u64::MAX.to_le_bytes());
+        let err = db_map2
+            .import(dump.as_slice(), DumpFormat::Binary)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(db_map2.get("key2").unwrap(), None);
+    }
+}
@@ -0,0 +1,77 @@
+//
+// Tests for the entry api
+//
+mod common;
+
+mod test_entry {
+    use crate::common::{fresh_db, open_ordered_map};
+    use abyssiniandb::{DbXxx, DbXxxBase, Entry};
+
+    #[test]
+    fn test_entry_or_insert() {
+        let db_name = fresh_db("target/tmp/test_entry/test_entry_or_insert.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        let v = db_map.entry("key1").unwrap().or_insert(b"a").unwrap();
+        assert_eq!(v, b"a");
+        let v = db_map.entry("key1").unwrap().or_insert(b"b").unwrap();
+        assert_eq!(v, b"a");
+        let v = db_map
+            .entry("key2")
+            .unwrap()
+            .or_insert_with(|| b"c".to_vec())
+            .unwrap();
+        assert_eq!(v, b"c");
+        assert_eq!(db_map.len().unwrap(), 2);
+        assert_eq!(
+            db_map.first().unwrap(),
+            Some(("key1".into(), b"a".to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_entry_and_modify() {
+        let db_name = fresh_db("target/tmp/test_entry/test_entry_and_modify.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        // counting words
+        for word in ["a", "b", "a", "c", "a", "b"] {
+            db_map
+                .entry(word)
+                .unwrap()
+                .and_modify(|v| v[0] += 1)
+                .unwrap()
+                .or_insert(&[1])
+                .unwrap();
+        }
+        assert_eq!(db_map.get("a").unwrap(), Some(vec![3]));
+        assert_eq!(db_map.get("b").unwrap(), Some(vec![2]));
+        assert_eq!(db_map.get("c").unwrap(), Some(vec![1]));
+        assert_eq!(db_map.len().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_entry_occupied_and_vacant() {
+        let db_name = fresh_db("target/tmp/test_entry/test_entry_occupied_and_vacant.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        for i in 0..20 {
+            db_map.put_string(&format!("key{i:02}"), "v").unwrap();
+        }
+        match db_map.entry("key05").unwrap() {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), &"key05".into());
+                assert_eq!(entry.insert(b"w").unwrap(), b"v");
+                assert_eq!(entry.get().unwrap(), b"w");
+                assert_eq!(entry.remove().unwrap(), b"w");
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(db_map.get("key05").unwrap(), None);
+        assert_eq!(db_map.len().unwrap(), 19);
+        match db_map.entry("key05").unwrap() {
+            Entry::Occupied(_) => unreachable!(),
+            Entry::Vacant(entry) => entry.insert(b"x").unwrap(),
+        }
+        assert_eq!(db_map.get("key05").unwrap(), Some(b"x".to_vec()));
+        assert_eq!(db_map.len().unwrap(), 20);
+        assert_eq!(db_map.iter_ordered().unwrap().count(), 20);
+    }
+}
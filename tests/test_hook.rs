@@ -0,0 +1,133 @@
+//
+// Tests for change hooks
+//
+mod common;
+
+mod test_hook {
+    use crate::common::{fresh_db, open_map};
+    use abyssiniandb::filedb::FileDbMapDbString;
+    use abyssiniandb::{DbMapKeyType, DbXxx, DbXxxBase};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    type Events = Rc<RefCell<Vec<String>>>;
+
+    fn text(bytes: Option<&[u8]>) -> String {
+        match bytes {
+            Some(b) => String::from_utf8_lossy(b).to_string(),
+            None => "-".to_string(),
+        }
+    }
+
+    fn register(db_map: &mut FileDbMapDbString) -> Events {
+        let events: Events = Rc::new(RefCell::new(Vec::new()));
+        let ev = events.clone();
+        db_map.on_put(move |key, old, new| {
+            let key = String::from_utf8_lossy(key.as_bytes()).to_string();
+            ev.borrow_mut()
+                .push(format!("put {key} {} {}", text(old), text(Some(new))));
+        });
+        let ev = events.clone();
+        db_map.on_delete(move |key, old| {
+            let key = String::from_utf8_lossy(key.as_bytes()).to_string();
+            ev.borrow_mut().push(format!("del {key} {}", text(old)));
+        });
+        events
+    }
+
+    #[test]
+    fn test_put_and_delete() {
+        let db_name = fresh_db("target/tmp/test_hook/test_put_and_delete.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        let events = register(&mut db_map);
+        db_map.put("key1", b"a").unwrap();
+        db_map.put("key1", b"b").unwrap();
+        db_map.append("key1", b"c").unwrap();
+        db_map.delete("key1").unwrap();
+        // deleting the missing key does not call the hook.
+        db_map.delete("key1").unwrap();
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                "put key1 - a",
+                "put key1 a b",
+                "put key1 b bc",
+                "del key1 bc",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_operation() {
+        let db_name = fresh_db("target/tmp/test_hook/test_failed_operation.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        db_map.put("key1", b"a").unwrap();
+        let events = register(&mut db_map);
+        // the merge operator is not registered, the merge fails.
+        assert!(db_map.merge("key1", b"x").is_err());
+        // the compare and swap fails by the mismatch.
+        let r = db_map
+            .compare_and_swap("key1", Some(b"z"), Some(b"y"))
+            .unwrap();
+        assert!(!r);
+        assert!(events.borrow().is_empty());
+        let r = db_map
+            .compare_and_swap("key1", Some(b"a"), Some(b"y"))
+            .unwrap();
+        assert!(r);
+        assert_eq!(*events.borrow(), vec!["put key1 a y"]);
+    }
+
+    #[test]
+    fn test_expired() {
+        let db_name = fresh_db("target/tmp/test_hook/test_expired.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        let events = register(&mut db_map);
+        db_map.put_with_ttl("key1", b"a", Duration::ZERO).unwrap();
+        db_map.put_with_ttl("key2", b"b", Duration::ZERO).unwrap();
+        // the expired value is not seen as the old value.
+        db_map.put("key1", b"c").unwrap();
+        assert_eq!(db_map.purge_expired().unwrap(), 1);
+        assert_eq!(
+            *events.borrow(),
+            vec!["put key1 - a", "put key2 - b", "put key1 - c", "del key2 -"]
+        );
+    }
+
+    #[test]
+    fn test_derived_map() {
+        let db_name = fresh_db("target/tmp/test_hook/test_derived_map.abyssiniandb");
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db.db_map_string("some_map1").unwrap();
+        let derived = db.db_map_string("some_map2").unwrap();
+        // the hook keeps the value-to-key map.
+        let mut d = derived.clone();
+        db_map.on_put(move |key, old, new| {
+            if let Some(old) = old {
+                d.delete(std::str::from_utf8(old).unwrap()).unwrap();
+            }
+            d.put(std::str::from_utf8(new).unwrap(), key.as_bytes())
+                .unwrap();
+        });
+        let mut d = derived.clone();
+        db_map.on_delete(move |_key, old| {
+            if let Some(old) = old {
+                d.delete(std::str::from_utf8(old).unwrap()).unwrap();
+            }
+        });
+        // the hooks are shared by the handles from the FileDb.
+        let mut db_map2 = db.db_map_string("some_map1").unwrap();
+        db_map2.put("key1", b"v1").unwrap();
+        db_map2.put("key2", b"v2").unwrap();
+        db_map2.put("key1", b"v3").unwrap();
+        db_map2.delete("key2").unwrap();
+        let mut derived = derived;
+        assert_eq!(derived.len().unwrap(), 1);
+        assert_eq!(derived.get("v3").unwrap(), Some(b"key1".to_vec()));
+        //
+        db_map.clear_hooks();
+        db_map.put("key3", b"v4").unwrap();
+        assert_eq!(derived.len().unwrap(), 1);
+    }
+}
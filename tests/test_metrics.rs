@@ -0,0 +1,75 @@
+//
+// Tests for runtime metrics
+//
+mod common;
+
+mod test_metrics {
+    use crate::common::{fresh_db, open_map};
+    use abyssiniandb::{DbMap, DbXxx};
+
+    #[test]
+    fn test_op_counts() {
+        let db_name = fresh_db("target/tmp/test_metrics/test_op_counts.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        assert_eq!(db_map.metrics(), Default::default());
+        db_map.put("key1", b"value1").unwrap();
+        db_map.put("key2", b"value2").unwrap();
+        db_map.put("key1", b"value1-2").unwrap();
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"value1-2".to_vec()));
+        assert_eq!(db_map.get("key3").unwrap(), None);
+        db_map.delete("key2").unwrap();
+        // deleting the missing key is not counted.
+        db_map.delete("key2").unwrap();
+        assert_eq!(db_map.iter().count(), 1);
+        //
+        let metrics = db_map.metrics();
+        assert_eq!(metrics.put_count, 3);
+        assert_eq!(metrics.get_count, 2);
+        assert_eq!(metrics.delete_count, 1);
+        assert_eq!(metrics.iter_count, 1);
+        // every operation walks on the bucket chain.
+        assert!(metrics.chain_walks >= 7);
+        assert!(metrics.chain_walk_steps >= metrics.chain_walk_max);
+        assert!(metrics.chain_walk_max >= 1);
+    }
+
+    #[test]
+    fn test_file_counters() {
+        let db_name = fresh_db("target/tmp/test_metrics/test_file_counters.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        db_map.reset_metrics();
+        db_map.put("key1", b"value1").unwrap();
+        let metrics = db_map.metrics();
+        assert!(metrics.key_bytes_written > 0);
+        assert!(metrics.value_bytes_written >= 6);
+        // the growing value is moved to the larger piece.
+        db_map.put("key1", &[b'a'; 1000]).unwrap();
+        assert!(db_map.metrics().relocations >= 1);
+        // the freed piece is reused.
+        db_map.put("key2", b"value2").unwrap();
+        assert!(db_map.metrics().free_list_reuses >= 1);
+        //
+        db_map.reset_metrics();
+        let metrics = db_map.metrics();
+        assert_eq!(metrics.value_bytes_read, 0);
+        assert_eq!(db_map.get("key1").unwrap().unwrap().len(), 1000);
+        let metrics = db_map.metrics();
+        assert!(metrics.key_bytes_read > 0);
+        assert!(metrics.value_bytes_read >= 1000);
+        assert_eq!(metrics.key_bytes_written, 0);
+        assert_eq!(metrics.value_bytes_written, 0);
+    }
+
+    #[test]
+    fn test_shared_by_handles() {
+        let db_name = fresh_db("target/tmp/test_metrics/test_shared_by_handles.abyssiniandb");
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db.db_map_string("some_map1").unwrap();
+        let mut db_map2 = db.db_map_string("some_map1").unwrap();
+        db_map.put("key1", b"value1").unwrap();
+        db_map2.put("key2", b"value2").unwrap();
+        assert_eq!(db_map.metrics().put_count, 2);
+        db_map2.reset_metrics();
+        assert_eq!(db_map.metrics().put_count, 0);
+    }
+}
@@ -0,0 +1,137 @@
+//
+// Tests for the modification of the map while iterating
+//
+mod common;
+
+mod test_modify_while_iter {
+    use crate::common::{fresh_db, open_map_with, small_params};
+    use abyssiniandb::filedb::{FileDbMapDbString, FileDbParams};
+    use abyssiniandb::{DbMap, DbString, DbXxx, DbXxxBase};
+
+    fn open_map(db_name: &str, ordered_index: bool) -> FileDbMapDbString {
+        let params = FileDbParams {
+            ordered_index,
+            ..small_params()
+        };
+        let mut db_map = open_map_with(db_name, "some_string_1", params);
+        for i in 0..50 {
+            db_map.put_string(&format!("key{i:02}"), "v").unwrap();
+        }
+        db_map
+    }
+
+    #[test]
+    #[should_panic(expected = "the map was modified while iterating")]
+    fn test_iter_fail_fast() {
+        let db_name =
+            fresh_db("target/tmp/test_modify_while_iter/test_iter_fail_fast.abyssiniandb");
+        let db_map = open_map(db_name, false);
+        let mut db_map2 = db_map.clone();
+        let mut iter = db_map.iter();
+        let _ = iter.next();
+        db_map2.put_string("new_key", "v").unwrap();
+        let _ = iter.next();
+    }
+
+    #[test]
+    fn test_iter_try_next() {
+        let db_name = fresh_db("target/tmp/test_modify_while_iter/test_iter_try_next.abyssiniandb");
+        let db_map = open_map(db_name, false);
+        let mut db_map2 = db_map.clone();
+        let mut iter = db_map.iter();
+        assert!(iter.try_next().unwrap().is_some());
+        db_map2.put_string("new_key", "v").unwrap();
+        let err = iter.try_next().unwrap_err();
+        assert_eq!(err.to_string(), "the map was modified while iterating");
+    }
+
+    #[test]
+    fn test_iter_update_value() {
+        let db_name =
+            fresh_db("target/tmp/test_modify_while_iter/test_iter_update_value.abyssiniandb");
+        let db_map = open_map(db_name, false);
+        let mut db_map2 = db_map.clone();
+        // updating the value of the existing key is not the modification.
+        let mut count = 0;
+        for (k, _v) in db_map.iter() {
+            db_map2.put(&k, b"updated").unwrap();
+            count += 1;
+        }
+        assert_eq!(count, 50);
+        assert!(db_map.values().all(|v| v == b"updated"));
+    }
+
+    #[test]
+    fn test_iter_tolerant() {
+        let db_name = fresh_db("target/tmp/test_modify_while_iter/test_iter_tolerant.abyssiniandb");
+        let db_map = open_map(db_name, false);
+        let mut db_map2 = db_map.clone();
+        let mut visited: Vec<DbString> = Vec::new();
+        for (k, _v) in db_map.iter().tolerant() {
+            // delete the next candidates and insert the new keys.
+            if visited.len() == 10 {
+                for i in 0..5 {
+                    db_map2.delete(&format!("key{i:02}")).unwrap();
+                    db_map2.put_string(&format!("new{i:02}"), "v").unwrap();
+                }
+            }
+            visited.push(k);
+        }
+        // the untouched keys are visited exactly once.
+        for i in 5..50 {
+            let k: DbString = format!("key{i:02}").into();
+            assert_eq!(visited.iter().filter(|&x| *x == k).count(), 1, "{k:?}");
+        }
+        assert_eq!(db_map.len().unwrap(), 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "the map was modified while iterating")]
+    fn test_range_fail_fast() {
+        let db_name =
+            fresh_db("target/tmp/test_modify_while_iter/test_range_fail_fast.abyssiniandb");
+        let db_map = open_map(db_name, true);
+        let mut db_map2 = db_map.clone();
+        let mut iter = db_map.iter_ordered().unwrap();
+        let _ = iter.next();
+        db_map2.delete("key40").unwrap();
+        let _ = iter.next();
+    }
+
+    #[test]
+    fn test_range_try_next() {
+        let db_name =
+            fresh_db("target/tmp/test_modify_while_iter/test_range_try_next.abyssiniandb");
+        let db_map = open_map(db_name, true);
+        let mut db_map2 = db_map.clone();
+        let mut iter = db_map.iter_ordered().unwrap();
+        assert!(iter.try_next().unwrap().is_some());
+        db_map2.delete("key40").unwrap();
+        let err = iter.try_next().unwrap_err();
+        assert_eq!(err.to_string(), "the map was modified while iterating");
+    }
+
+    #[test]
+    fn test_range_tolerant() {
+        let db_name =
+            fresh_db("target/tmp/test_modify_while_iter/test_range_tolerant.abyssiniandb");
+        let db_map = open_map(db_name, true);
+        let mut db_map2 = db_map.clone();
+        let mut visited: Vec<String> = Vec::new();
+        for (k, _v) in db_map.iter_ordered().unwrap().tolerant() {
+            let k = String::from_utf8_lossy(&k).to_string();
+            if k == "key10" {
+                db_map2.delete("key05").unwrap();
+                db_map2.delete("key11").unwrap();
+                db_map2.put_string("key10a", "v").unwrap();
+            }
+            visited.push(k);
+        }
+        let mut expected: Vec<String> = (0..50)
+            .filter(|&i| i != 11)
+            .map(|i| format!("key{i:02}"))
+            .collect();
+        expected.insert(11, "key10a".to_string());
+        assert_eq!(visited, expected);
+    }
+}
@@ -0,0 +1,77 @@
+//
+// Tests for the default methods of DbXxxObjectSafe
+//
+mod test_object_safe {
+    use abyssiniandb::{DbString, DbXxx, DbXxxBase, DbXxxObjectSafe};
+    use std::collections::BTreeMap;
+    use std::io::Result;
+
+    /// the map that implements only the required methods.
+    #[derive(Debug, Default)]
+    struct MemMap(BTreeMap<DbString, Vec<u8>>);
+
+    impl DbXxxBase for MemMap {
+        fn len(&self) -> Result<u64> {
+            Ok(self.0.len() as u64)
+        }
+        fn read_fill_buffer(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn sync_all(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn sync_data(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl DbXxxObjectSafe<DbString> for MemMap {
+        fn get_kt(&mut self, key: &DbString) -> Result<Option<Vec<u8>>> {
+            Ok(self.0.get(key).cloned())
+        }
+        fn put_kt(&mut self, key: &DbString, value: &[u8]) -> Result<()> {
+            self.0.insert(key.clone(), value.to_vec());
+            Ok(())
+        }
+        fn del_kt(&mut self, key: &DbString) -> Result<Option<Vec<u8>>> {
+            Ok(self.0.remove(key))
+        }
+        fn includes_key_kt(&mut self, key: &DbString) -> Result<bool> {
+            Ok(self.0.contains_key(key))
+        }
+    }
+
+    impl DbXxx<DbString> for MemMap {}
+
+    #[test]
+    fn test_default_methods() {
+        let mut db_map = MemMap::default();
+        assert!(db_map.compare_and_swap("key1", None, Some(b"a")).unwrap());
+        assert!(!db_map.compare_and_swap("key1", None, Some(b"b")).unwrap());
+        db_map.append("key1", b"bc").unwrap();
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"abc".to_vec()));
+        assert_eq!(db_map.value_len("key1").unwrap(), Some(3));
+        assert!(db_map.entry_meta("key1").is_err());
+        assert!(db_map.fetch_add("key1", 1).is_err());
+        assert_eq!(db_map.fetch_add("key2", 5).unwrap(), 0);
+        assert_eq!(db_map.fetch_add("key2", 2).unwrap(), 5);
+        assert!(db_map.compare_and_swap("key1", Some(b"abc"), None).unwrap());
+        assert_eq!(db_map.get("key1").unwrap(), None);
+        //
+        db_map
+            .bulk_put(&[("key3", b"c".as_slice()), ("key4", b"d".as_slice())])
+            .unwrap();
+        assert_eq!(
+            db_map.bulk_get(&["key4", "key5", "key3"]).unwrap(),
+            vec![Some(b"d".to_vec()), None, Some(b"c".to_vec())]
+        );
+        assert_eq!(
+            db_map.bulk_delete(&["key3", "key5"]).unwrap(),
+            vec![Some(b"c".to_vec()), None]
+        );
+        assert_eq!(db_map.len().unwrap(), 2);
+    }
+}
@@ -0,0 +1,269 @@
+//
+// Tests for the ordered index: range, first, last
+//
+mod common;
+
+mod test_range {
+    use crate::common::{fresh_db, open_map, open_ordered_map, small_params};
+    use abyssiniandb::filedb::{FileDbMapDbString, FileDbParams};
+    use abyssiniandb::{DbI64, DbString, DbU64, DbVu64, DbXxx, DbXxxBase};
+
+    #[test]
+    fn test_range_basic() {
+        let db_name = fresh_db("target/tmp/test_range/test_range_basic.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        assert!(db_map.has_ordered_index());
+        assert_eq!(db_map.first().unwrap(), None);
+        assert_eq!(db_map.last().unwrap(), None);
+        //
+        for i in [5, 3, 9, 1, 7, 0, 8, 2, 6, 4] {
+            db_map
+                .put_string(&format!("key{i:02}"), &format!("value{i}"))
+                .unwrap();
+        }
+        let keys: Vec<DbString> = db_map.iter_ordered().unwrap().map(|(k, _)| k).collect();
+        let expected: Vec<DbString> = (0..10).map(|i| format!("key{i:02}").into()).collect();
+        assert_eq!(keys, expected);
+        //
+        let start: DbString = "key03".into();
+        let end: DbString = "key06".into();
+        let vals: Vec<Vec<u8>> = db_map
+            .range(start.clone()..end.clone())
+            .unwrap()
+            .map(|(_, v)| v)
+            .collect();
+        assert_eq!(
+            vals,
+            vec![b"value3".to_vec(), b"value4".to_vec(), b"value5".to_vec()]
+        );
+        assert_eq!(db_map.range(start..=end).unwrap().count(), 4);
+        //
+        assert_eq!(
+            db_map.first().unwrap(),
+            Some(("key00".into(), b"value0".to_vec()))
+        );
+        assert_eq!(
+            db_map.last().unwrap(),
+            Some(("key09".into(), b"value9".to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_range_many_with_delete() {
+        let db_name = fresh_db("target/tmp/test_range/test_range_many_with_delete.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        #[rustfmt::skip]
+        const LOOP_MAX: u64 = if cfg!(miri) { 50 } else { 1000 };
+        for i in (0..LOOP_MAX).rev() {
+            db_map.put_string(&format!("key{i:04}"), "v").unwrap();
+        }
+        for i in (0..LOOP_MAX).filter(|i| i % 3 == 0) {
+            db_map.delete(&format!("key{i:04}")).unwrap();
+        }
+        let keys: Vec<String> = db_map
+            .iter_ordered()
+            .unwrap()
+            .map(|(k, _)| String::from_utf8_lossy(&k).to_string())
+            .collect();
+        let expected: Vec<String> = (0..LOOP_MAX)
+            .filter(|i| i % 3 != 0)
+            .map(|i| format!("key{i:04}"))
+            .collect();
+        assert_eq!(keys, expected);
+        assert_eq!(db_map.len().unwrap(), expected.len() as u64);
+    }
+
+    #[test]
+    fn test_range_reopen_and_build() {
+        let db_name = fresh_db("target/tmp/test_range/test_range_reopen_and_build.abyssiniandb");
+        {
+            let mut db_map = open_map(db_name);
+            assert!(!db_map.has_ordered_index());
+            assert!(db_map.iter_ordered().is_err());
+            for i in 0..100 {
+                db_map.put_string(&format!("key{i:03}"), "v").unwrap();
+            }
+            db_map.sync_all().unwrap();
+        }
+        {
+            // the index is built from the existing keys.
+            let mut db_map = open_ordered_map(db_name);
+            assert_eq!(db_map.iter_ordered().unwrap().count(), 100);
+            db_map.put_string("key100", "v").unwrap();
+            db_map.sync_all().unwrap();
+        }
+        {
+            // the existing index is maintained without the param.
+            let db_map = open_map(db_name);
+            assert!(db_map.has_ordered_index());
+            assert_eq!(
+                db_map.last().unwrap(),
+                Some(("key100".into(), b"v".to_vec()))
+            );
+            assert_eq!(db_map.iter_ordered().unwrap().count(), 101);
+        }
+    }
+
+    #[test]
+    fn test_range_deep_tree() {
+        let db_name = fresh_db("target/tmp/test_range/test_range_deep_tree.abyssiniandb");
+        #[rustfmt::skip]
+        const LOOP_MAX: u64 = if cfg!(miri) { 100 } else { 3000 };
+        let ordered_keys = |db_map: &FileDbMapDbString| -> Vec<String> {
+            db_map
+                .iter_ordered()
+                .unwrap()
+                .map(|(k, _)| String::from_utf8_lossy(&k).to_string())
+                .collect()
+        };
+        {
+            // the scattered order splits the leaves and the branches in the middle.
+            let mut db_map = open_ordered_map(db_name);
+            for i in 0..LOOP_MAX {
+                let k = i * 7919 % LOOP_MAX;
+                db_map.put_string(&format!("key{k:05}"), "v").unwrap();
+            }
+            let expected: Vec<String> = (0..LOOP_MAX).map(|i| format!("key{i:05}")).collect();
+            assert_eq!(ordered_keys(&db_map), expected);
+            for i in 0..LOOP_MAX {
+                let k = i * 7717 % LOOP_MAX;
+                if k % 4 != 0 {
+                    db_map.delete(&format!("key{k:05}")).unwrap();
+                }
+            }
+            db_map.sync_all().unwrap();
+        }
+        {
+            let mut db_map = open_map(db_name);
+            let expected: Vec<String> = (0..LOOP_MAX)
+                .filter(|i| i % 4 == 0)
+                .map(|i| format!("key{i:05}"))
+                .collect();
+            assert_eq!(ordered_keys(&db_map), expected);
+            let start: DbString = "key00041".into();
+            let end: DbString = "key00100".into();
+            assert_eq!(
+                db_map.range(start..=end).unwrap().next().map(|(k, _)| k),
+                Some("key00044".into())
+            );
+            for i in (0..LOOP_MAX).filter(|i| i % 4 == 0) {
+                db_map.delete(&format!("key{i:05}")).unwrap();
+            }
+            assert_eq!(db_map.first().unwrap(), None);
+            assert_eq!(db_map.last().unwrap(), None);
+            db_map.put_string("key", "v").unwrap();
+            assert_eq!(ordered_keys(&db_map), vec!["key".to_string()]);
+        }
+    }
+
+    #[test]
+    fn test_range_broken_header() {
+        let db_name = fresh_db("target/tmp/test_range/test_range_broken_header.abyssiniandb");
+        {
+            let mut db_map = open_ordered_map(db_name);
+            db_map.put_string("key1", "v").unwrap();
+            db_map.sync_all().unwrap();
+        }
+        let idx_path = format!("{db_name}/some_string_1.idx");
+        let mut bytes = std::fs::read(&idx_path).unwrap();
+        bytes[0] = b'X';
+        std::fs::write(&idx_path, bytes).unwrap();
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let r = db.db_map_string_with_params("some_string_1", Default::default());
+        assert_eq!(r.err().unwrap().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    fn ordered_params() -> FileDbParams {
+        FileDbParams {
+            ordered_index: true,
+            ..small_params()
+        }
+    }
+
+    #[test]
+    fn test_range_u64() {
+        let db_name = fresh_db("target/tmp/test_range/test_range_u64.abyssiniandb");
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db
+            .db_map_u64_with_params("some_u64_1", ordered_params())
+            .unwrap();
+        // 256 is [0, 1, 0, ..] in little-endian bytes, it is less than 1 by the bytes.
+        for i in [256u64, 1, 300, 2, 255, 65536, u64::MAX, 0] {
+            db_map.put(&i, b"v").unwrap();
+        }
+        let keys: Vec<u64> = db_map
+            .iter_ordered()
+            .unwrap()
+            .map(|(k, _)| u64::from(k))
+            .collect();
+        assert_eq!(keys, vec![0, 1, 2, 255, 256, 300, 65536, u64::MAX]);
+        let keys: Vec<u64> = db_map
+            .range(DbU64::from(1u64)..DbU64::from(300u64))
+            .unwrap()
+            .map(|(k, _)| u64::from(k))
+            .collect();
+        assert_eq!(keys, vec![1, 2, 255, 256]);
+        assert_eq!(db_map.first().unwrap().map(|(k, _)| u64::from(k)), Some(0));
+        assert_eq!(
+            db_map.last().unwrap().map(|(k, _)| u64::from(k)),
+            Some(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_range_i64() {
+        let db_name = fresh_db("target/tmp/test_range/test_range_i64.abyssiniandb");
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db
+            .db_map_i64_with_params("some_i64_1", ordered_params())
+            .unwrap();
+        for i in [256i64, -1, 1, i64::MIN, -300, 0, i64::MAX, 300] {
+            db_map.put(&i, b"v").unwrap();
+        }
+        let keys: Vec<i64> = db_map
+            .iter_ordered()
+            .unwrap()
+            .map(|(k, _)| i64::from(k))
+            .collect();
+        assert_eq!(keys, vec![i64::MIN, -300, -1, 0, 1, 256, 300, i64::MAX]);
+        let keys: Vec<i64> = db_map
+            .range(DbI64::from(-300i64)..=DbI64::from(256i64))
+            .unwrap()
+            .map(|(k, _)| i64::from(k))
+            .collect();
+        assert_eq!(keys, vec![-300, -1, 0, 1, 256]);
+        assert_eq!(
+            db_map.first().unwrap().map(|(k, _)| i64::from(k)),
+            Some(i64::MIN)
+        );
+        assert_eq!(
+            db_map.last().unwrap().map(|(k, _)| i64::from(k)),
+            Some(i64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_range_vu64() {
+        let db_name = fresh_db("target/tmp/test_range/test_range_vu64.abyssiniandb");
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db
+            .db_map_vu64_with_params("some_vu64_1", ordered_params())
+            .unwrap();
+        for i in [256u64, 1, 255, 127, 128, 65536, u64::MAX, 0] {
+            db_map.put(&i, b"v").unwrap();
+        }
+        let keys: Vec<u64> = db_map
+            .iter_ordered()
+            .unwrap()
+            .map(|(k, _)| u64::from(k))
+            .collect();
+        assert_eq!(keys, vec![0, 1, 127, 128, 255, 256, 65536, u64::MAX]);
+        let keys: Vec<u64> = db_map
+            .range(DbVu64::from(128u64)..)
+            .unwrap()
+            .map(|(k, _)| u64::from(k))
+            .take(3)
+            .collect();
+        assert_eq!(keys, vec![128, 255, 256]);
+    }
+}
@@ -0,0 +1,130 @@
+//
+// Tests for the repair mode
+//
+mod test_repair {
+    use abyssiniandb::filedb::{CheckFileDbMap, FileDbParams, HashBucketsParam};
+    use abyssiniandb::{DbXxx, DbXxxBase};
+    use std::io::{Seek, SeekFrom, Write};
+
+    fn make_db(db_name: &str) {
+        let _ = std::fs::remove_dir_all(db_name);
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db
+            .db_map_string_with_params(
+                "some_map1",
+                FileDbParams {
+                    buckets_size: HashBucketsParam::Capacity(4),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        for i in 0..20 {
+            db_map.put(&format!("key{i}"), b"value").unwrap();
+        }
+        // the growing value is moved to the larger piece.
+        db_map.put("key5", &[b'a'; 1000]).unwrap();
+        for i in 0..5 {
+            db_map.delete(&format!("key{i}")).unwrap();
+        }
+        db_map.sync_data().unwrap();
+    }
+
+    fn check_values(db_name: &str) {
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db.db_map_string("some_map1").unwrap();
+        let report = db_map.verify().unwrap();
+        assert!(report.is_ok(), "{report}");
+        assert_eq!(db_map.len().unwrap(), 15);
+        for i in 0..5 {
+            assert_eq!(db_map.get(&format!("key{i}")).unwrap(), None);
+        }
+        assert_eq!(db_map.get("key5").unwrap(), Some(vec![b'a'; 1000]));
+        for i in 6..20 {
+            assert_eq!(
+                db_map.get(&format!("key{i}")).unwrap(),
+                Some(b"value".to_vec())
+            );
+        }
+    }
+
+    #[test]
+    fn test_lost_htx() {
+        let db_name = "target/tmp/test_repair/test_lost_htx.abyssiniandb";
+        make_db(db_name);
+        std::fs::remove_file(format!("{db_name}/some_map1.htx")).unwrap();
+        {
+            let db = abyssiniandb::open_file(db_name).unwrap();
+            let mut db_map = db.db_map_string("some_map1").unwrap();
+            assert_eq!(db_map.len().unwrap(), 0);
+            let report = db_map.repair().unwrap();
+            assert_eq!(report.item_count, 15);
+            assert_eq!(report.dropped_keys, 0);
+            assert_eq!(report.duplicate_keys, 0);
+            assert!(report.free_key_pieces > 0);
+            assert!(report.free_value_pieces > 0);
+            assert!(report.notes.is_empty(), "{report}");
+            db_map.sync_data().unwrap();
+        }
+        check_values(db_name);
+    }
+
+    #[test]
+    fn test_broken_htx() {
+        let db_name = "target/tmp/test_repair/test_broken_htx.abyssiniandb";
+        make_db(db_name);
+        {
+            let htx_path = format!("{db_name}/some_map1.htx");
+            let mut file = std::fs::OpenOptions::new()
+                .write(true)
+                .open(htx_path)
+                .unwrap();
+            // the item count and the first bucket.
+            file.seek(SeekFrom::Start(24)).unwrap();
+            file.write_all(&100u64.to_le_bytes()).unwrap();
+            file.seek(SeekFrom::Start(128)).unwrap();
+            file.write_all(&7u64.to_le_bytes()).unwrap();
+        }
+        {
+            let db = abyssiniandb::open_file(db_name).unwrap();
+            let mut db_map = db.db_map_string("some_map1").unwrap();
+            assert!(!db_map.verify().unwrap().is_ok());
+            let report = db_map.repair().unwrap();
+            assert_eq!(report.item_count, 15);
+            assert_eq!(report.dropped_keys, 0);
+            db_map.sync_data().unwrap();
+        }
+        check_values(db_name);
+    }
+
+    #[test]
+    fn test_truncated_value_file() {
+        let db_name = "target/tmp/test_repair/test_truncated_value_file.abyssiniandb";
+        make_db(db_name);
+        {
+            let db = abyssiniandb::open_file(db_name).unwrap();
+            let mut db_map = db.db_map_string("some_map1").unwrap();
+            // no free piece fits, the value is written at the end.
+            db_map.put("key20", &[b'b'; 2000]).unwrap();
+            db_map.sync_data().unwrap();
+        }
+        let val_path = format!("{db_name}/some_map1.val");
+        let len = std::fs::metadata(&val_path).unwrap().len();
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&val_path)
+            .unwrap();
+        // the last value piece is cut off.
+        file.set_len(len - 4).unwrap();
+        drop(file);
+        {
+            let db = abyssiniandb::open_file(db_name).unwrap();
+            let mut db_map = db.db_map_string("some_map1").unwrap();
+            let report = db_map.repair().unwrap();
+            assert_eq!(report.item_count, 15);
+            assert_eq!(report.dropped_keys, 1);
+            assert_eq!(report.notes.len(), 2, "{report}");
+            db_map.sync_data().unwrap();
+        }
+        check_values(db_name);
+    }
+}
@@ -0,0 +1,69 @@
+//
+// Tests for retain, extract_if and drain
+//
+mod common;
+
+mod test_retain {
+    use crate::common::{self, fresh_db};
+    use abyssiniandb::filedb::FileDbMapDbString;
+    use abyssiniandb::{DbMap, DbString, DbXxx, DbXxxBase};
+
+    fn open_map(db_name: &str) -> FileDbMapDbString {
+        let mut db_map = common::open_ordered_map(db_name);
+        for i in 0..100 {
+            db_map
+                .put_string(&format!("key{i:03}"), &format!("{}", i % 10))
+                .unwrap();
+        }
+        db_map
+    }
+
+    #[test]
+    fn test_retain() {
+        let db_name = fresh_db("target/tmp/test_retain/test_retain.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        db_map.retain(|_k, v| v != b"3").unwrap();
+        assert_eq!(db_map.len().unwrap(), 90);
+        assert_eq!(db_map.iter().count(), 90);
+        assert_eq!(db_map.iter_ordered().unwrap().count(), 90);
+        assert_eq!(db_map.get_string("key013").unwrap(), None);
+        assert_eq!(db_map.get_string("key014").unwrap(), Some("4".into()));
+        //
+        let bound: DbString = "key050".into();
+        db_map.retain(|k, _v| *k < bound).unwrap();
+        assert_eq!(db_map.len().unwrap(), 45);
+        assert_eq!(
+            db_map.last().unwrap(),
+            Some(("key049".into(), b"9".to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_extract_if() {
+        let db_name = fresh_db("target/tmp/test_retain/test_extract_if.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        let mut extracted = db_map.extract_if(|_k, v| v == b"7").unwrap();
+        extracted.sort();
+        let expected: Vec<(DbString, Vec<u8>)> = (0..10)
+            .map(|i| (format!("key{:03}", i * 10 + 7).into(), b"7".to_vec()))
+            .collect();
+        assert_eq!(extracted, expected);
+        assert_eq!(db_map.len().unwrap(), 90);
+        assert!(db_map.values().all(|v| v != b"7"));
+    }
+
+    #[test]
+    fn test_drain() {
+        let db_name = fresh_db("target/tmp/test_retain/test_drain.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        let drained = db_map.drain().unwrap();
+        assert_eq!(drained.len(), 100);
+        assert_eq!(db_map.len().unwrap(), 0);
+        assert_eq!(db_map.iter().count(), 0);
+        assert_eq!(db_map.first().unwrap(), None);
+        // the map is usable after draining.
+        db_map.put_string("key000", "a").unwrap();
+        assert_eq!(db_map.get_string("key000").unwrap(), Some("a".into()));
+        assert_eq!(db_map.len().unwrap(), 1);
+    }
+}
@@ -0,0 +1,114 @@
+//
+// Tests for scan_prefix on DbString and DbBytes maps
+//
+mod test_scan_prefix {
+    use abyssiniandb::filedb::{FileDbParams, HashBucketsParam};
+    use abyssiniandb::{DbBytes, DbString, DbXxx};
+
+    fn params(ordered_index: bool) -> FileDbParams {
+        FileDbParams {
+            buckets_size: HashBucketsParam::Capacity(4),
+            ordered_index,
+            ..Default::default()
+        }
+    }
+
+    fn put_hierarchical<T: DbXxx<DbString>>(db_map: &mut T) {
+        for user in 120..=125 {
+            for item in ["settings", "profile", "avatar"] {
+                let key = format!("user/{user}/{item}");
+                db_map.put_string(&key, &format!("{user}-{item}")).unwrap();
+            }
+        }
+        db_map.put_string("user/12", "short").unwrap();
+        db_map.put_string("group/123/settings", "other").unwrap();
+    }
+
+    #[test]
+    fn test_scan_prefix_string_with_index() {
+        let db_name = "target/tmp/test_scan_prefix/test_scan_prefix_string_with_index.abyssiniandb";
+        let _ = std::fs::remove_dir_all(db_name);
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db
+            .db_map_string_with_params("some_string_1", params(true))
+            .unwrap();
+        put_hierarchical(&mut db_map);
+        //
+        let keys: Vec<DbString> = db_map
+            .scan_prefix("user/123/")
+            .unwrap()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                "user/123/avatar".into(),
+                "user/123/profile".into(),
+                "user/123/settings".into(),
+            ]
+        );
+        assert_eq!(db_map.scan_prefix("user/").unwrap().count(), 19);
+        assert_eq!(db_map.scan_prefix("none/").unwrap().count(), 0);
+        assert_eq!(db_map.scan_prefix("").unwrap().count(), 20);
+    }
+
+    #[test]
+    fn test_scan_prefix_string_without_index() {
+        let db_name =
+            "target/tmp/test_scan_prefix/test_scan_prefix_string_without_index.abyssiniandb";
+        let _ = std::fs::remove_dir_all(db_name);
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db
+            .db_map_string_with_params("some_string_1", params(false))
+            .unwrap();
+        put_hierarchical(&mut db_map);
+        //
+        let r = db_map.scan_prefix("user/123/");
+        assert_eq!(r.err().unwrap().kind(), std::io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_scan_prefix_bytes() {
+        let db_name = "target/tmp/test_scan_prefix/test_scan_prefix_bytes.abyssiniandb";
+        let _ = std::fs::remove_dir_all(db_name);
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db
+            .db_map_bytes_with_params("some_bytes_1", params(true))
+            .unwrap();
+        db_map.put(&[1u8, 2, 3], b"a").unwrap();
+        db_map.put(&[1u8, 2, 4], b"b").unwrap();
+        db_map.put(&[1u8, 3], b"c").unwrap();
+        db_map.put(&[0u8, 1, 2], b"d").unwrap();
+        db_map.put(&[1u8, 255], b"e").unwrap();
+        db_map.put(&[1u8, 255, 0], b"f").unwrap();
+        db_map.put(&[2u8], b"g").unwrap();
+        //
+        let vec: Vec<(DbBytes, Vec<u8>)> = db_map.scan_prefix(&[1u8, 2]).unwrap().collect();
+        assert_eq!(
+            vec,
+            vec![
+                ((&[1u8, 2, 3]).into(), b"a".to_vec()),
+                ((&[1u8, 2, 4]).into(), b"b".to_vec()),
+            ]
+        );
+        // the end of the prefix [1, 255] is [2].
+        let vec: Vec<DbBytes> = db_map
+            .scan_prefix(&[1u8, 255])
+            .unwrap()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(vec, vec![(&[1u8, 255]).into(), (&[1u8, 255, 0]).into()]);
+        let vec: Vec<DbBytes> = db_map
+            .scan_prefix(&[255u8])
+            .unwrap()
+            .map(|(k, _)| k)
+            .collect();
+        assert!(vec.is_empty());
+        // the iterator is fused at the end of the prefix.
+        let mut iter = db_map.scan_prefix(&[1u8, 3]).unwrap();
+        assert_eq!(iter.next().map(|(_, v)| v), Some(b"c".to_vec()));
+        assert_eq!(iter.next(), None);
+        db_map.put(&[1u8, 3, 0], b"h").unwrap();
+        assert_eq!(iter.next(), None);
+    }
+}
@@ -0,0 +1,198 @@
+//
+// Tests for time-to-live of keys
+//
+mod common;
+
+mod test_ttl {
+    use crate::common::{fresh_db, open_ordered_map};
+    use abyssiniandb::filedb::DbString;
+    use abyssiniandb::{DbMap, DbXxx, DbXxxBase, Entry};
+    use std::time::Duration;
+
+    const LONG: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn test_put_with_ttl() {
+        let db_name = fresh_db("target/tmp/test_ttl/test_put_with_ttl.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        db_map.put("key1", b"v1").unwrap();
+        db_map.put_with_ttl("key2", b"v2", LONG).unwrap();
+        db_map.put_with_ttl("key3", b"v3", Duration::ZERO).unwrap();
+        //
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"v1".to_vec()));
+        assert_eq!(db_map.get("key2").unwrap(), Some(b"v2".to_vec()));
+        assert_eq!(db_map.get("key3").unwrap(), None);
+        assert!(db_map.includes_key("key2").unwrap());
+        assert!(!db_map.includes_key("key3").unwrap());
+        assert_eq!(db_map.value_len("key3").unwrap(), None);
+        assert!(db_map.get_range("key3", 0, 2).unwrap().is_none());
+        assert!(db_map.value_reader("key3").unwrap().is_none());
+        // the expired key is counted until it is purged
+        assert_eq!(db_map.len().unwrap(), 3);
+        let expected = vec![DbString::from("key1"), DbString::from("key2")];
+        let mut keys: Vec<DbString> = db_map.keys().collect();
+        keys.sort();
+        assert_eq!(keys, expected);
+        let keys: Vec<DbString> = db_map.iter_ordered().unwrap().map(|(k, _v)| k).collect();
+        assert_eq!(keys, expected);
+        assert_eq!(
+            db_map.last().unwrap().map(|(_k, v)| v),
+            Some(b"v2".to_vec())
+        );
+        //
+        assert_eq!(db_map.purge_expired().unwrap(), 1);
+        assert_eq!(db_map.len().unwrap(), 2);
+        assert_eq!(db_map.purge_expired().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_overwrite_expired() {
+        let db_name = fresh_db("target/tmp/test_ttl/test_overwrite_expired.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        // put clears the expiry
+        db_map.put_with_ttl("key1", b"v1", Duration::ZERO).unwrap();
+        db_map.put("key1", b"v2").unwrap();
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"v2".to_vec()));
+        // the expired value is not appended to
+        db_map.put_with_ttl("key2", b"abc", Duration::ZERO).unwrap();
+        db_map.append("key2", b"def").unwrap();
+        assert_eq!(db_map.get("key2").unwrap(), Some(b"def".to_vec()));
+        // the expired value counts as missing
+        db_map.put_with_ttl("key3", b"abc", Duration::ZERO).unwrap();
+        assert!(db_map.compare_and_swap("key3", None, Some(b"new")).unwrap());
+        assert_eq!(db_map.get("key3").unwrap(), Some(b"new".to_vec()));
+        db_map
+            .put_with_ttl("key4", &7i64.to_le_bytes(), Duration::ZERO)
+            .unwrap();
+        assert_eq!(db_map.fetch_add("key4", 5).unwrap(), 0);
+        assert_eq!(
+            db_map.get("key4").unwrap(),
+            Some(5i64.to_le_bytes().to_vec())
+        );
+        // the deleted expired key returns none
+        db_map.put_with_ttl("key5", b"abc", Duration::ZERO).unwrap();
+        assert_eq!(db_map.delete("key5").unwrap(), None);
+        // the entry of the expired key is vacant
+        db_map.put_with_ttl("key6", b"abc", Duration::ZERO).unwrap();
+        let value = db_map.entry("key6").unwrap().or_insert(b"xyz").unwrap();
+        assert_eq!(value, b"xyz".to_vec());
+        assert_eq!(db_map.get("key6").unwrap(), Some(b"xyz".to_vec()));
+        //
+        assert_eq!(db_map.len().unwrap(), 5);
+        assert_eq!(db_map.purge_expired().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_keep_ttl() {
+        let db_name = fresh_db("target/tmp/test_ttl/test_keep_ttl.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        db_map.put_with_ttl("key1", b"abc", LONG).unwrap();
+        db_map.append("key1", b"def").unwrap();
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"abcdef".to_vec()));
+        db_map
+            .put_with_ttl("key2", &1i64.to_le_bytes(), LONG)
+            .unwrap();
+        assert_eq!(db_map.fetch_add("key2", 2).unwrap(), 1);
+        assert_eq!(db_map.fetch_add("key2", 2).unwrap(), 3);
+        // the expiry is kept after reopening
+        drop(db_map);
+        let mut db_map = open_ordered_map(db_name);
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"abcdef".to_vec()));
+        assert_eq!(db_map.purge_expired().unwrap(), 0);
+        // overwriting with ttl
+        db_map.put_with_ttl("key1", b"v", Duration::ZERO).unwrap();
+        assert_eq!(db_map.get("key1").unwrap(), None);
+        let mut extracted = db_map.extract_if(|_k, _v| true).unwrap();
+        extracted.sort();
+        assert_eq!(extracted.len(), 1);
+        assert_eq!(db_map.len().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_too_large_value() {
+        let db_name = fresh_db("target/tmp/test_ttl/test_too_large_value.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        // the top bit of the value length is the expire flag.
+        let err = db_map.value_writer("key1", 0x8000_0000).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        db_map.put("key1", b"abc").unwrap();
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"abc".to_vec()));
+    }
+
+    #[test]
+    fn test_entry_insert_clears_ttl() {
+        let db_name = fresh_db("target/tmp/test_ttl/test_entry_insert_clears_ttl.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        let short = Duration::from_millis(100);
+        db_map.put_with_ttl("key1", b"v1", short).unwrap();
+        db_map.put_with_ttl("key2", b"v2", short).unwrap();
+        match db_map.entry("key1").unwrap() {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.insert(b"w1").unwrap(), b"v1");
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        db_map.put("key2", b"w2").unwrap();
+        std::thread::sleep(short * 3);
+        // the entry insert and the put are the same.
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"w1".to_vec()));
+        assert_eq!(db_map.get("key2").unwrap(), Some(b"w2".to_vec()));
+    }
+
+    #[test]
+    fn test_entry_and_modify_keeps_ttl() {
+        let db_name = fresh_db("target/tmp/test_ttl/test_entry_and_modify_keeps_ttl.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        let short = Duration::from_millis(100);
+        db_map.put_with_ttl("key1", b"v1", short).unwrap();
+        db_map.put_with_ttl("key2", b"v2", short).unwrap();
+        db_map
+            .entry("key1")
+            .unwrap()
+            .and_modify(|v| v.push(b'+'))
+            .unwrap();
+        assert_eq!(db_map.get("key1").unwrap(), Some(b"v1+".to_vec()));
+        db_map.append("key2", b"+").unwrap();
+        std::thread::sleep(short * 3);
+        // the and_modify and the append are the same.
+        assert_eq!(db_map.get("key1").unwrap(), None);
+        assert_eq!(db_map.get("key2").unwrap(), None);
+    }
+
+    #[test]
+    fn test_size_hint() {
+        let db_name = fresh_db("target/tmp/test_ttl/test_size_hint.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        db_map.put("key1", b"v1").unwrap();
+        db_map.put("key2", b"v2").unwrap();
+        // the map without the time-to-live keys has the exact size.
+        assert_eq!(db_map.iter().size_hint(), (2, Some(2)));
+        assert_eq!(db_map.keys().len(), 2);
+        db_map.put_with_ttl("key3", b"v3", Duration::ZERO).unwrap();
+        assert_eq!(db_map.iter().size_hint(), (0, Some(3)));
+        assert_eq!(db_map.keys().count(), 2);
+        // it is kept after the purge and the reopening.
+        assert_eq!(db_map.purge_expired().unwrap(), 1);
+        drop(db_map);
+        let db_map = open_ordered_map(db_name);
+        assert_eq!(db_map.values().size_hint(), (0, Some(2)));
+    }
+
+    #[test]
+    fn test_old_value_file_version() {
+        let db_name = fresh_db("target/tmp/test_ttl/test_old_value_file_version.abyssiniandb");
+        let mut db_map = open_ordered_map(db_name);
+        db_map.put("key1", b"v1").unwrap();
+        db_map.sync_all().unwrap();
+        drop(db_map);
+        // the version 0 of the value file has no expiry time.
+        let path = std::path::Path::new(db_name).join("some_string_1.val");
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[16..24].copy_from_slice(&0u64.to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+        //
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let err = db.db_map_string("some_string_1").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
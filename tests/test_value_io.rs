@@ -0,0 +1,177 @@
+//
+// Tests for partial value reads and streaming value access
+//
+mod common;
+
+mod test_value_io {
+    use crate::common::{fresh_db, open_map};
+    use abyssiniandb::filedb::FileDbMapDbString;
+    use abyssiniandb::{DbXxx, DbXxxBase, DbXxxObjectSafe};
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    fn blob(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn put_blob(db_map: &mut FileDbMapDbString, key: &str, value: &[u8]) {
+        let mut writer = db_map.value_writer(key, value.len() as u64).unwrap();
+        writer.write_all(value).unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_get_range() {
+        let db_name = fresh_db("target/tmp/test_value_io/test_get_range.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        let value = blob(10_000);
+        put_blob(&mut db_map, "key1", &value);
+        assert_eq!(
+            db_map.get_range("key1", 0, 16).unwrap(),
+            Some(value[0..16].to_vec())
+        );
+        assert_eq!(
+            db_map.get_range("key1", 5000, 100).unwrap(),
+            Some(value[5000..5100].to_vec())
+        );
+        // short at the end of the value
+        assert_eq!(
+            db_map.get_range("key1", 9990, 100).unwrap(),
+            Some(value[9990..].to_vec())
+        );
+        assert_eq!(db_map.get_range("key1", 20000, 10).unwrap(), Some(vec![]));
+        assert_eq!(db_map.get_range("key2", 0, 10).unwrap(), None);
+    }
+
+    #[test]
+    fn test_value_reader() {
+        let db_name = fresh_db("target/tmp/test_value_io/test_value_reader.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        let value = blob(10_000);
+        put_blob(&mut db_map, "key1", &value);
+        assert!(db_map.value_reader("key2").unwrap().is_none());
+        //
+        let mut reader = db_map.value_reader("key1").unwrap().unwrap();
+        assert_eq!(reader.len(), 10_000);
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, value[0..4]);
+        reader.seek(SeekFrom::End(-4)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, value[9996..]);
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).unwrap();
+        assert_eq!(all, value);
+        assert!(reader.seek(SeekFrom::Current(-20_000)).is_err());
+        // inserting other keys does not affect the reader.
+        db_map.put("key2", b"other").unwrap();
+        reader.seek(SeekFrom::Start(100)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, value[100..104]);
+        // changing the value makes the reader fail.
+        db_map.put("key1", b"small").unwrap();
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        assert!(reader.read(&mut buf).is_err());
+        // the overwrite of the same length in place makes the reader fail.
+        let mut reader = db_map.value_reader("key1").unwrap().unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"smal");
+        db_map.put("key1", b"SMALL").unwrap();
+        assert!(reader.read(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_value_writer() {
+        let db_name = fresh_db("target/tmp/test_value_io/test_value_writer.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        let value = blob(100_000);
+        db_map.put("key1", b"old").unwrap();
+        //
+        let mut writer = db_map.value_writer("key1", value.len() as u64).unwrap();
+        for chunk in value.chunks(4096) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert!(writer.write_all(b"over").is_err());
+        writer.finish().unwrap();
+        assert_eq!(db_map.get("key1").unwrap(), Some(value.clone()));
+        assert_eq!(db_map.len().unwrap(), 1);
+        //
+        let mut writer = db_map.value_writer("key2", 10).unwrap();
+        writer.write_all(b"01234").unwrap();
+        assert!(writer.finish().is_err());
+        assert_eq!(db_map.get("key2").unwrap(), None);
+        //
+        let mut writer = db_map.value_writer("key2", 10).unwrap();
+        writer.write_all(b"0123456789").unwrap();
+        writer.finish().unwrap();
+        assert_eq!(db_map.get("key2").unwrap(), Some(b"0123456789".to_vec()));
+        assert_eq!(db_map.len().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_get_into() {
+        let db_name = fresh_db("target/tmp/test_value_io/test_get_into.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        for i in 0..100 {
+            db_map
+                .put_string(&format!("key{i:02}"), &format!("value{i}"))
+                .unwrap();
+        }
+        let mut buf = Vec::with_capacity(64);
+        for i in 0..100 {
+            assert!(db_map.get_into(&format!("key{i:02}"), &mut buf).unwrap());
+            assert_eq!(buf, format!("value{i}").as_bytes());
+        }
+        assert!(!db_map.get_into("none", &mut buf).unwrap());
+        assert!(buf.is_empty());
+        // a large value
+        let value = blob(10_000);
+        put_blob(&mut db_map, "blob", &value);
+        assert!(db_map.get_into("blob", &mut buf).unwrap());
+        assert_eq!(buf, value);
+    }
+
+    #[test]
+    fn test_with_value() {
+        let db_name = fresh_db("target/tmp/test_value_io/test_with_value.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        db_map.put_string("key1", "value1").unwrap();
+        let len = db_map.with_value("key1", |v| v.len()).unwrap();
+        assert_eq!(len, Some(6));
+        let head = db_map.with_value("key1", |v| v[0..5].to_vec()).unwrap();
+        assert_eq!(head, Some(b"value".to_vec()));
+        assert_eq!(db_map.with_value("none", |v| v.len()).unwrap(), None);
+        //
+        let value = blob(10_000);
+        put_blob(&mut db_map, "blob", &value);
+        let sum = db_map
+            .with_value("blob", |v| v.iter().map(|&b| b as u64).sum::<u64>())
+            .unwrap();
+        assert_eq!(sum, Some(value.iter().map(|&b| b as u64).sum::<u64>()));
+    }
+
+    #[test]
+    fn test_value_len_and_entry_meta() {
+        let db_name =
+            fresh_db("target/tmp/test_value_io/test_value_len_and_entry_meta.abyssiniandb");
+        let mut db_map = open_map(db_name);
+        db_map.put_string("key1", "value1").unwrap();
+        let value = blob(10_000);
+        put_blob(&mut db_map, "blob", &value);
+        //
+        assert_eq!(db_map.value_len("key1").unwrap(), Some(6));
+        assert_eq!(db_map.value_len("blob").unwrap(), Some(10_000));
+        assert_eq!(db_map.value_len("none").unwrap(), None);
+        //
+        let meta = db_map.entry_meta("blob").unwrap().unwrap();
+        assert_eq!(meta.value_len, 10_000);
+        assert!(meta.value_piece_size as u64 >= meta.value_len);
+        assert!(meta.key_piece_offset > 0);
+        assert!(meta.key_piece_size > 0);
+        assert!(meta.value_piece_offset > 0);
+        assert_eq!(db_map.entry_meta("none").unwrap(), None);
+        // object safe
+        let db_obj: &mut dyn DbXxxObjectSafe<_> = &mut db_map;
+        assert_eq!(db_obj.value_len_kt(&"key1".into()).unwrap(), Some(6));
+    }
+}
@@ -0,0 +1,83 @@
+//
+// Tests for the integrity checker
+//
+mod test_verify {
+    use abyssiniandb::filedb::{CheckFileDbMap, FileDbParams, HashBucketsParam};
+    use abyssiniandb::{DbXxx, DbXxxBase};
+    use std::io::{Seek, SeekFrom, Write};
+
+    fn make_db(db_name: &str) {
+        let _ = std::fs::remove_dir_all(db_name);
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let mut db_map = db
+            .db_map_string_with_params(
+                "some_map1",
+                FileDbParams {
+                    buckets_size: HashBucketsParam::Capacity(4),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        for i in 0..20 {
+            db_map.put(&format!("key{i}"), b"value").unwrap();
+        }
+        for i in 0..5 {
+            db_map.delete(&format!("key{i}")).unwrap();
+        }
+        db_map.sync_data().unwrap();
+    }
+
+    fn overwrite(path: &str, offset: u64, buf: &[u8]) {
+        let mut file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.write_all(buf).unwrap();
+    }
+
+    #[test]
+    fn test_healthy() {
+        let db_name = "target/tmp/test_verify/test_healthy.abyssiniandb";
+        make_db(db_name);
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let db_map = db.db_map_string("some_map1").unwrap();
+        let report = db_map.verify().unwrap();
+        assert!(report.is_ok(), "{report}");
+        assert_eq!(report.reachable_keys, db_map.len().unwrap());
+        assert_eq!(report.item_count, 15);
+        assert_eq!(report.key_pieces - report.free_key_pieces, 15);
+        assert_eq!(report.value_pieces - report.free_value_pieces, 15);
+        assert!(report.free_key_pieces > 0);
+        assert!(report.free_value_pieces > 0);
+    }
+
+    #[test]
+    fn test_broken_item_count() {
+        let db_name = "target/tmp/test_verify/test_broken_item_count.abyssiniandb";
+        make_db(db_name);
+        let htx_path = format!("{db_name}/some_map1.htx");
+        overwrite(&htx_path, 24, &100u64.to_le_bytes());
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let db_map = db.db_map_string("some_map1").unwrap();
+        let report = db_map.verify().unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.item_count, 100);
+        assert_eq!(report.reachable_keys, 15);
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_broken_bucket() {
+        let db_name = "target/tmp/test_verify/test_broken_bucket.abyssiniandb";
+        make_db(db_name);
+        let htx_path = format!("{db_name}/some_map1.htx");
+        // the first bucket points into the middle of the key file header.
+        overwrite(&htx_path, 128, &7u64.to_le_bytes());
+        let db = abyssiniandb::open_file(db_name).unwrap();
+        let db_map = db.db_map_string("some_map1").unwrap();
+        let report = db_map.verify().unwrap();
+        assert!(!report.is_ok());
+        assert!(report
+            .errors
+            .iter()
+            .any(|e| e.contains("7 is not a key piece")));
+    }
+}
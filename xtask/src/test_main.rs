@@ -269,7 +269,7 @@ where
         "db_map.depth_of_node_tree(): {}",
         db_map.depth_of_node_tree().unwrap()
     );
-    #[cfg(feature = "buf_stats")]
+    #[cfg(feature = "rabuf_stats")]
     println!("db_map.buf_stats(): {:?}", db_map.buf_stats());
     */
     /*
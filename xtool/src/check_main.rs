@@ -154,11 +154,19 @@ fn _print_check_db_map(db_map: &dyn CheckFileDbMap, check_cnf: CheckC) {
         );
         println!("db_map.is_balanced(): {}", db_map.is_balanced().unwrap());
         println!("db_map.is_dense(): {}", db_map.is_dense().unwrap());
-        #[cfg(feature = "buf_stats")]
+        #[cfg(feature = "rabuf_stats")]
         println!("db_map.buf_stats(): {:?}", db_map.buf_stats());
-        //
-        println!("keys_count_stats(): {}", db_map.keys_count_stats().unwrap());
         */
+        {
+            let stats = db_map.keys_count_stats().unwrap();
+            println!("keys_count_stats(): {stats}");
+            println!(
+                "max_chain_length(): {}, average_probe_count(): {:.2}",
+                stats.max_chain_length(),
+                stats.average_probe_count()
+            );
+        }
+        println!("verify(): {}", db_map.verify().unwrap());
         println!(
             "key_piece_size_stats(): {}",
             db_map.key_piece_size_stats().unwrap()
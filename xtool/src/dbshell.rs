@@ -0,0 +1,445 @@
+use abyssiniandb::filedb::{CheckFileDbMap, FileDb, FileDbMap, FileDbParams};
+use abyssiniandb::filedb::{FileDbMapDbBytes, FileDbMapDbString};
+use abyssiniandb::filedb::{FileDbMapDbI64, FileDbMapDbU64, FileDbMapDbVu64};
+use abyssiniandb::{DbMap, DbXxxBase, DbXxxObjectSafe};
+use anyhow::{anyhow, bail};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Editor, Helper};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+mod util;
+use util::{escape, list_maps, map_key_path, read_key_type, stats_lines, unescape};
+use util::{KeyType, ScanIter, ToolKey};
+
+const HELP: &str = "\
+commands:
+  maps                      lists the maps and the key types
+  use <map> [<type>]        switches to the map, the key type is read from the map,
+                            the type is required for the map of u64|vu64
+  get <key>                 prints the value of the key
+  put <key> <value>         inserts the key-value pair
+  del <key>                 deletes the key
+  scan [-n <num>] [<prefix>]
+                            prints the key-value pairs in bucket order, 100 pairs by default,
+                            the prefix is in key order with the ordered index
+  count                     prints the count of the keys
+  stats                     prints the statistics of the map
+  verify                    checks the integrity of the map
+  render [utf8|hex]         shows or sets the rendering of the values
+  help                      prints this help
+  quit                      exits the shell
+
+the key types: string, bytes, i64, u64, vu64.
+the keys and the values of string and bytes are escaped as the dump text
+format: `\\\\`, `\\t`, `\\n`, `\\r` and `\\xHH`. the words can be quoted by `\"`.";
+
+const COMMANDS: [&str; 13] = [
+    "maps", "use", "get", "put", "del", "scan", "count", "stats", "verify", "render", "help",
+    "quit", "exit",
+];
+const KEY_TYPES: [&str; 5] = ["string", "bytes", "i64", "u64", "vu64"];
+const RENDERS: [&str; 2] = ["utf8", "hex"];
+const DEFAULT_SCAN_LIMIT: u64 = 100;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 2 || args[1].starts_with('-') {
+        eprintln!("[usage] {} <db_path>", args[0]);
+        std::process::exit(2);
+    }
+    if let Err(err) = run(PathBuf::from(&args[1])) {
+        eprintln!("dbshell: {err:#}");
+        std::process::exit(2);
+    }
+}
+
+fn run(db_path: PathBuf) -> anyhow::Result<()> {
+    let db = abyssiniandb::open_file(&db_path)?;
+    let mut shell = Shell {
+        db,
+        db_path: db_path.clone(),
+        current: None,
+        opened: HashMap::new(),
+        render: Render::Utf8,
+    };
+    let mut editor: Editor<ShellHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(ShellHelper { db_path }));
+    loop {
+        let prompt = match &shell.current {
+            Some(current) => format!("{}> ", current.name),
+            None => "> ".to_string(),
+        };
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        };
+        let words = match split_words(&line) {
+            Ok(words) => words,
+            Err(err) => {
+                eprintln!("error: {err:#}");
+                continue;
+            }
+        };
+        if words.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line.as_str());
+        match words[0].as_str() {
+            "quit" | "exit" => break,
+            _ => {
+                if let Err(err) = shell.exec(&words[0], &words[1..]) {
+                    eprintln!("error: {err:#}");
+                }
+            }
+        }
+    }
+    shell.db.sync_data()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Render {
+    Utf8,
+    Hex,
+}
+
+/// the map in use, with the key type.
+enum AnyMap {
+    String(FileDbMapDbString),
+    Bytes(FileDbMapDbBytes),
+    I64(FileDbMapDbI64),
+    U64(FileDbMapDbU64),
+    Vu64(FileDbMapDbVu64),
+}
+
+struct CurrentMap {
+    name: String,
+    map: AnyMap,
+}
+
+struct Shell {
+    db: FileDb,
+    db_path: PathBuf,
+    current: Option<CurrentMap>,
+    /// the key types of the maps opened in this session.
+    opened: HashMap<String, KeyType>,
+    render: Render,
+}
+
+impl Shell {
+    fn exec(&mut self, cmd: &str, args: &[String]) -> anyhow::Result<()> {
+        match cmd {
+            "help" => println!("{HELP}"),
+            "maps" => self.cmd_maps()?,
+            "use" => self.cmd_use(args)?,
+            "render" => self.cmd_render(args)?,
+            "get" | "put" | "del" | "scan" | "count" | "stats" | "verify" => {
+                let render = self.render;
+                let current = self
+                    .current
+                    .as_mut()
+                    .ok_or_else(|| anyhow!("no map in use, try `use <map>`"))?;
+                match &mut current.map {
+                    AnyMap::String(m) => exec_map(m, cmd, args, render)?,
+                    AnyMap::Bytes(m) => exec_map(m, cmd, args, render)?,
+                    AnyMap::I64(m) => exec_map(m, cmd, args, render)?,
+                    AnyMap::U64(m) => exec_map(m, cmd, args, render)?,
+                    AnyMap::Vu64(m) => exec_map(m, cmd, args, render)?,
+                }
+            }
+            _ => bail!("unknown command: {cmd}, try `help`"),
+        }
+        Ok(())
+    }
+    fn cmd_maps(&self) -> anyhow::Result<()> {
+        for name in list_maps(&self.db_path)? {
+            let key_type = read_key_type(&map_key_path(&self.db_path, &name))
+                .unwrap_or_else(|_| "unknown".to_string());
+            let mark = match &self.current {
+                Some(current) if current.name == name => "*",
+                _ => " ",
+            };
+            println!("{mark} {name}\t{key_type}");
+        }
+        Ok(())
+    }
+    fn cmd_use(&mut self, args: &[String]) -> anyhow::Result<()> {
+        let (name, key_type) = match args {
+            [name] => (name, None),
+            [name, key_type] => (
+                name,
+                Some(
+                    KeyType::from_name(key_type)
+                        .ok_or_else(|| anyhow!("unknown key type: {key_type}"))?,
+                ),
+            ),
+            _ => bail!("usage: use <map> [<type>]"),
+        };
+        let key_path = map_key_path(&self.db_path, name);
+        let key_type = if key_path.is_file() {
+            // the mismatched key type is checked here, the library panics on it.
+            let file_key_type = read_key_type(&key_path)?;
+            let key_type = match (key_type, self.opened.get(name.as_str())) {
+                (Some(key_type), _) => key_type,
+                (None, Some(opened)) => *opened,
+                // `u64` and `vu64` have the same signature, the type is not guessed.
+                (None, None) if file_key_type.contains('|') => bail!(
+                    "the key type of the map is {file_key_type}, \
+                     `use {name} <type>` selects one of them"
+                ),
+                (None, None) => KeyType::from_name(&file_key_type)
+                    .ok_or_else(|| anyhow!("unknown key type of the map: {name}"))?,
+            };
+            if !file_key_type.split('|').any(|t| t == key_type.name()) {
+                bail!(
+                    "the key type of the map is {file_key_type}, not {}: {name}",
+                    key_type.name()
+                );
+            }
+            key_type
+        } else {
+            let key_type = key_type.ok_or_else(|| {
+                anyhow!("the map is not found: {name}, `use {name} <type>` creates it")
+            })?;
+            println!("creates the map: {name}");
+            key_type
+        };
+        // the same files must not be opened by the other key type.
+        if let Some(opened) = self.opened.get(name.as_str()) {
+            if *opened != key_type {
+                bail!(
+                    "the map is opened as {} in this shell: {name}",
+                    opened.name()
+                );
+            }
+        }
+        let params = FileDbParams::default();
+        let map = match key_type {
+            KeyType::String => AnyMap::String(self.db.db_map_string_with_params(name, params)?),
+            KeyType::Bytes => AnyMap::Bytes(self.db.db_map_bytes_with_params(name, params)?),
+            KeyType::I64 => AnyMap::I64(self.db.db_map_i64_with_params(name, params)?),
+            KeyType::U64 => AnyMap::U64(self.db.db_map_u64_with_params(name, params)?),
+            KeyType::Vu64 => AnyMap::Vu64(self.db.db_map_vu64_with_params(name, params)?),
+        };
+        self.opened.insert(name.clone(), key_type);
+        self.current = Some(CurrentMap {
+            name: name.clone(),
+            map,
+        });
+        Ok(())
+    }
+    fn cmd_render(&mut self, args: &[String]) -> anyhow::Result<()> {
+        match args.first().map(|s| s.as_str()) {
+            None => (),
+            Some("utf8") => self.render = Render::Utf8,
+            Some("hex") => self.render = Render::Hex,
+            Some(s) => bail!("unknown rendering: {s}"),
+        }
+        let name = match self.render {
+            Render::Utf8 => "utf8",
+            Render::Hex => "hex",
+        };
+        println!("render: {name}");
+        Ok(())
+    }
+}
+
+fn exec_map<KT>(
+    db_map: &mut FileDbMap<KT>,
+    cmd: &str,
+    args: &[String],
+    render: Render,
+) -> anyhow::Result<()>
+where
+    KT: ToolKey + std::fmt::Display,
+{
+    match (cmd, args) {
+        ("get", [key]) => {
+            let key = KT::parse_key(key)?;
+            match db_map.get_kt(&key)? {
+                Some(value) => println!("{}", render_value(&value, render)),
+                None => println!("(not found)"),
+            }
+        }
+        ("put", [key, value]) => {
+            let key = KT::parse_key(key)?;
+            db_map.put_kt(&key, &unescape(value)?)?;
+            db_map.sync_data()?;
+        }
+        ("del", [key]) => {
+            let key = KT::parse_key(key)?;
+            match db_map.del_kt(&key)? {
+                Some(_) => db_map.sync_data()?,
+                None => println!("(not found)"),
+            }
+        }
+        ("scan", args) => {
+            let (limit, prefix) = parse_scan_args(args)?;
+            let iter: ScanIter<KT> = match prefix {
+                Some(_) if KT::is_numeric() => bail!("the prefix is not for the numeric keys"),
+                Some(prefix) => KT::scan_prefix(db_map, &unescape(prefix)?)?,
+                None => Box::new(db_map.iter()),
+            };
+            for (count, (key, value)) in iter.enumerate() {
+                if count as u64 >= limit {
+                    println!("(more than {limit} pairs, try `scan -n <num>`)");
+                    break;
+                }
+                match render {
+                    Render::Utf8 => println!("{}\t{}", key.key_text(), escape(&value)),
+                    Render::Hex => println!("{}\n{}", key.key_text(), hex_dump(&value)),
+                }
+            }
+        }
+        ("count", []) => println!("{}", db_map.len()?),
+        ("stats", []) => {
+            for line in stats_lines(db_map, db_map.len()?)? {
+                println!("{line}");
+            }
+        }
+        ("verify", []) => {
+            println!("{}", db_map.verify()?);
+        }
+        _ => bail!("invalid arguments of the command: {cmd}, try `help`"),
+    }
+    Ok(())
+}
+
+/// parses `[-n <num>] [<prefix>]`.
+fn parse_scan_args(args: &[String]) -> anyhow::Result<(u64, Option<&String>)> {
+    match args {
+        [] => Ok((DEFAULT_SCAN_LIMIT, None)),
+        [prefix] => Ok((DEFAULT_SCAN_LIMIT, Some(prefix))),
+        [opt, num] if opt == "-n" => Ok((num.parse()?, None)),
+        [opt, num, prefix] if opt == "-n" => Ok((num.parse()?, Some(prefix))),
+        _ => bail!("usage: scan [-n <num>] [<prefix>]"),
+    }
+}
+
+fn render_value(value: &[u8], render: Render) -> String {
+    match render {
+        Render::Utf8 => escape(value),
+        Render::Hex => hex_dump(value),
+    }
+}
+
+/// returns the lines of the offset, the hex bytes and the printable characters.
+fn hex_dump(value: &[u8]) -> String {
+    if value.is_empty() {
+        return "(empty)".to_string();
+    }
+    let mut lines = Vec::new();
+    for (i, chunk) in value.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+        let text: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        lines.push(format!("{:08x}  {:<47}  |{text}|", i * 16, hex.join(" ")));
+    }
+    lines.join("\n")
+}
+
+/// splits the line into the words, the word can be quoted by `"`.
+fn split_words(line: &str) -> anyhow::Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let c = match chars.next() {
+            Some(c) => c,
+            None => break,
+        };
+        let mut word = String::new();
+        if c == '"' {
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => word.push(c),
+                    None => bail!("the quote does not end"),
+                }
+            }
+        } else {
+            word.push(c);
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                word.push(c);
+            }
+        }
+        words.push(word);
+    }
+    Ok(words)
+}
+
+//
+// completion
+//
+struct ShellHelper {
+    db_path: PathBuf,
+}
+
+impl ShellHelper {
+    /// returns the candidates of the word at the position.
+    fn candidates(&self, words: &[&str], idx: usize) -> Vec<String> {
+        let list = |ary: &[&str]| ary.iter().map(|s| s.to_string()).collect();
+        match (idx, words.first().copied()) {
+            (0, _) => list(&COMMANDS),
+            (1, Some("use")) => map_names(&self.db_path),
+            (2, Some("use")) => list(&KEY_TYPES),
+            (1, Some("render")) => list(&RENDERS),
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn map_names(db_path: &Path) -> Vec<String> {
+    list_maps(db_path).unwrap_or_default()
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let head = &line[..pos];
+        let start = head.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let words: Vec<&str> = head[..start].split_whitespace().collect();
+        let word = &head[start..];
+        let pairs = self
+            .candidates(&words, words.len())
+            .into_iter()
+            .filter(|s| s.starts_with(word))
+            .map(|s| Pair {
+                display: s.clone(),
+                replacement: s,
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
@@ -0,0 +1,520 @@
+use abyssiniandb::filedb::{CheckFileDbMap, FileDbMap, FileDbParams};
+use abyssiniandb::{DbMap, DbXxxBase, DbXxxObjectSafe, DumpFormat};
+use anyhow::{anyhow, bail, Context};
+use std::io::Write;
+use std::path::PathBuf;
+
+mod util;
+use util::{escape, list_maps, map_key_path, read_key_type, stats_lines, unescape};
+use util::{KeyType, ScanIter, ToolKey};
+
+const USAGE: &str = "\
+[usage] dbtool [options] <db_path> ls
+        dbtool [options] <db_path> <command> <map> [args]
+
+commands:
+  ls                    lists the maps and the key types
+  get <key>             prints the value of the key
+  put <key> <value>     inserts the key-value pair
+  del <key>             deletes the key
+  scan [<prefix>]       prints the key-value pairs in bucket order,
+                        the prefix is in key order with the ordered index
+  count                 prints the count of the keys
+  stats                 prints the statistics of the map
+  verify                checks the integrity of the map
+  repair                rebuilds the hash buckets and the free lists
+  dump [<file>]         exports the map into the file or the stdout
+  load [<file>]         imports the map from the file or the stdin
+
+options:
+  -t, --type <type>     key type: string, bytes, i64, u64, vu64 (default: string)
+  -j, --json            prints in JSON
+  -b, --binary          binary format for dump and load (default: text)
+  -n, --limit <num>     limits the count of the pairs of scan
+
+the keys and the values of string and bytes are escaped as the dump text
+format: `\\\\`, `\\t`, `\\n`, `\\r` and `\\xHH`.
+exit status: 0 on success, 1 if the key is not found or the map is broken, 2 on error.";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let conf = match CmdConf::parse(&args) {
+        Ok(conf) => conf,
+        Err(err) => {
+            eprintln!("dbtool: {err}");
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    match run(&conf) {
+        Ok(true) => (),
+        Ok(false) => std::process::exit(1),
+        Err(err) if is_broken_pipe(&err) => (),
+        Err(err) => {
+            eprintln!("dbtool: {err:#}");
+            std::process::exit(2);
+        }
+    }
+}
+
+/// writes the line into the stdout, and returns the error instead of the panic of `println!()`.
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        writeln!(std::io::stdout(), $($arg)*)?
+    };
+}
+
+/// the closed pipe is not an error, such as `dbtool db scan map | head`.
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain().any(|e| {
+        matches!(
+            e.downcast_ref::<std::io::Error>().map(|e| e.kind()),
+            Some(std::io::ErrorKind::BrokenPipe)
+        )
+    })
+}
+
+#[derive(Debug, Default)]
+struct CmdConf {
+    key_type: Option<KeyType>,
+    json: bool,
+    binary: bool,
+    limit: Option<u64>,
+    db_path: PathBuf,
+    command: String,
+    map: String,
+    args: Vec<String>,
+}
+
+impl CmdConf {
+    fn parse(args: &[String]) -> anyhow::Result<Self> {
+        let mut conf = CmdConf::default();
+        let mut free = Vec::new();
+        let mut it = args.iter();
+        while let Some(arg) = it.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                "-t" | "--type" => {
+                    let val = it.next().ok_or_else(|| anyhow!("missing the key type"))?;
+                    conf.key_type = Some(
+                        KeyType::from_name(val)
+                            .ok_or_else(|| anyhow!("unknown key type: {val}"))?,
+                    );
+                }
+                "-j" | "--json" => conf.json = true,
+                "-b" | "--binary" => conf.binary = true,
+                "-n" | "--limit" => {
+                    let val = it.next().ok_or_else(|| anyhow!("missing the limit"))?;
+                    conf.limit = Some(val.parse().with_context(|| format!("limit: {val}"))?);
+                }
+                // a single "-" is the stdin or the stdout.
+                s if s.starts_with('-') && s.len() > 1 && !s[1..].starts_with(char::is_numeric) => {
+                    bail!("unknown option: {s}")
+                }
+                _ => free.push(arg.clone()),
+            }
+        }
+        let mut free = free.into_iter();
+        conf.db_path = free
+            .next()
+            .ok_or_else(|| anyhow!("missing the db path"))?
+            .into();
+        conf.command = free.next().ok_or_else(|| anyhow!("missing the command"))?;
+        if conf.command != "ls" {
+            conf.map = free.next().ok_or_else(|| anyhow!("missing the map name"))?;
+        }
+        conf.args = free.collect();
+        let max_args = match conf.command.as_str() {
+            "ls" | "count" | "stats" | "verify" | "repair" => 0,
+            "get" | "del" | "scan" | "dump" | "load" => 1,
+            "put" => 2,
+            cmd => bail!("unknown command: {cmd}"),
+        };
+        let min_args = match conf.command.as_str() {
+            "get" | "del" => 1,
+            "put" => 2,
+            _ => 0,
+        };
+        if conf.args.len() < min_args || conf.args.len() > max_args {
+            bail!("invalid arguments of the command: {}", conf.command);
+        }
+        Ok(conf)
+    }
+}
+
+/// returns false if the key is not found or the map is broken.
+fn run(conf: &CmdConf) -> anyhow::Result<bool> {
+    if conf.command == "ls" {
+        return cmd_ls(conf);
+    }
+    let creates = matches!(conf.command.as_str(), "put" | "load");
+    if !creates && !map_key_path(&conf.db_path, &conf.map).is_file() {
+        bail!(
+            "the map is not found: {}: {}",
+            conf.db_path.display(),
+            conf.map
+        );
+    }
+    let key_path = map_key_path(&conf.db_path, &conf.map);
+    let key_type = conf.key_type.unwrap_or(KeyType::String);
+    if key_path.is_file() {
+        // the mismatched key type is checked here, the library panics on it.
+        let file_key_type = read_key_type(&key_path)?;
+        if !file_key_type.split('|').any(|t| t == key_type.name()) {
+            bail!(
+                "the key type of the map is {file_key_type}, not {}: {}",
+                key_type.name(),
+                conf.map
+            );
+        }
+    }
+    let db = abyssiniandb::open_file(&conf.db_path)
+        .with_context(|| format!("open: {}", conf.db_path.display()))?;
+    let params = FileDbParams::default();
+    let name = conf.map.as_str();
+    let r = match key_type {
+        KeyType::String => run_map(conf, db.db_map_string_with_params(name, params)),
+        KeyType::Bytes => run_map(conf, db.db_map_bytes_with_params(name, params)),
+        KeyType::I64 => run_map(conf, db.db_map_i64_with_params(name, params)),
+        KeyType::U64 => run_map(conf, db.db_map_u64_with_params(name, params)),
+        KeyType::Vu64 => run_map(conf, db.db_map_vu64_with_params(name, params)),
+    }?;
+    db.sync_data()?;
+    Ok(r)
+}
+
+fn run_map<KT>(conf: &CmdConf, db_map: std::io::Result<FileDbMap<KT>>) -> anyhow::Result<bool>
+where
+    KT: ToolKey + std::fmt::Display,
+{
+    let mut db_map = db_map.with_context(|| format!("open the map: {}", conf.map))?;
+    match conf.command.as_str() {
+        "get" => cmd_get(conf, &mut db_map),
+        "put" => cmd_put(conf, &mut db_map),
+        "del" => cmd_del(conf, &mut db_map),
+        "scan" => cmd_scan(conf, &db_map),
+        "count" => cmd_count(conf, &db_map),
+        "stats" => cmd_stats(conf, &db_map),
+        "verify" => cmd_verify(conf, &db_map),
+        "repair" => cmd_repair(conf, &mut db_map),
+        "dump" => cmd_dump(conf, &db_map),
+        "load" => cmd_load(conf, &mut db_map),
+        cmd => bail!("unknown command: {cmd}"),
+    }
+}
+
+//
+// commands
+//
+fn cmd_ls(conf: &CmdConf) -> anyhow::Result<bool> {
+    let mut maps = Vec::new();
+    let names = list_maps(&conf.db_path)
+        .with_context(|| format!("read_dir: {}", conf.db_path.display()))?;
+    for name in names {
+        let key_type = read_key_type(&map_key_path(&conf.db_path, &name))
+            .unwrap_or_else(|_| "unknown".to_string());
+        let file_len = |ext: &str| {
+            std::fs::metadata(conf.db_path.join(format!("{name}.{ext}")))
+                .map(|m| m.len())
+                .unwrap_or(0)
+        };
+        maps.push((name.clone(), key_type, file_len("key"), file_len("val")));
+    }
+    maps.sort();
+    if conf.json {
+        let items: Vec<String> = maps
+            .iter()
+            .map(|(name, key_type, key_len, val_len)| {
+                format!(
+                    "{{\"name\":{},\"key_type\":{},\"key_file_bytes\":{key_len},\"value_file_bytes\":{val_len}}}",
+                    json_str(name),
+                    json_str(key_type)
+                )
+            })
+            .collect();
+        outln!("[{}]", items.join(","));
+    } else {
+        for (name, key_type, key_len, val_len) in &maps {
+            outln!("{name}\t{key_type}\tkey: {key_len} bytes, val: {val_len} bytes");
+        }
+    }
+    Ok(true)
+}
+
+fn cmd_get<KT: ToolKey>(conf: &CmdConf, db_map: &mut FileDbMap<KT>) -> anyhow::Result<bool> {
+    let key = KT::parse_key(&conf.args[0])?;
+    let value = db_map.get_kt(&key)?;
+    if conf.json {
+        let value = match &value {
+            Some(v) => json_str(&escape(v)),
+            None => "null".to_string(),
+        };
+        outln!("{{\"key\":{},\"value\":{value}}}", key_json(&key));
+    } else {
+        match &value {
+            Some(v) => outln!("{}", escape(v)),
+            None => eprintln!("not found: {}", key.key_text()),
+        }
+    }
+    Ok(value.is_some())
+}
+
+fn cmd_put<KT: ToolKey>(conf: &CmdConf, db_map: &mut FileDbMap<KT>) -> anyhow::Result<bool> {
+    let key = KT::parse_key(&conf.args[0])?;
+    let value = unescape(&conf.args[1])?;
+    db_map.put_kt(&key, &value)?;
+    if conf.json {
+        outln!("{{\"key\":{},\"put\":true}}", key_json(&key));
+    }
+    Ok(true)
+}
+
+fn cmd_del<KT: ToolKey>(conf: &CmdConf, db_map: &mut FileDbMap<KT>) -> anyhow::Result<bool> {
+    let key = KT::parse_key(&conf.args[0])?;
+    let old = db_map.del_kt(&key)?;
+    if conf.json {
+        outln!(
+            "{{\"key\":{},\"deleted\":{}}}",
+            key_json(&key),
+            old.is_some()
+        );
+    } else if old.is_none() {
+        eprintln!("not found: {}", key.key_text());
+    }
+    Ok(old.is_some())
+}
+
+fn cmd_scan<KT: ToolKey>(conf: &CmdConf, db_map: &FileDbMap<KT>) -> anyhow::Result<bool> {
+    let iter: ScanIter<KT> = match conf.args.first() {
+        Some(_) if KT::is_numeric() => bail!("the prefix is not for the numeric keys"),
+        Some(prefix) => KT::scan_prefix(db_map, &unescape(prefix)?)?,
+        None => Box::new(db_map.iter()),
+    };
+    let limit = conf.limit.unwrap_or(u64::MAX);
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    if conf.json {
+        out.write_all(b"[")?;
+    }
+    for (count, (key, value)) in iter.enumerate() {
+        if count as u64 >= limit {
+            break;
+        }
+        if conf.json {
+            if count > 0 {
+                out.write_all(b",")?;
+            }
+            write!(
+                out,
+                "{{\"key\":{},\"value\":{}}}",
+                key_json(&key),
+                json_str(&escape(&value))
+            )?;
+        } else {
+            writeln!(out, "{}\t{}", key.key_text(), escape(&value))?;
+        }
+    }
+    if conf.json {
+        out.write_all(b"]\n")?;
+    }
+    out.flush()?;
+    Ok(true)
+}
+
+fn cmd_count<KT: ToolKey>(conf: &CmdConf, db_map: &FileDbMap<KT>) -> anyhow::Result<bool> {
+    let count = db_map.len()?;
+    if conf.json {
+        outln!("{{\"count\":{count}}}");
+    } else {
+        outln!("{count}");
+    }
+    Ok(true)
+}
+
+fn cmd_stats<KT>(conf: &CmdConf, db_map: &FileDbMap<KT>) -> anyhow::Result<bool>
+where
+    KT: ToolKey + std::fmt::Display,
+{
+    let count = db_map.len()?;
+    if !conf.json {
+        for line in stats_lines(db_map, count)? {
+            outln!("{line}");
+        }
+        return Ok(true);
+    }
+    let (_count, per_mill) = db_map.htx_filling_rate_per_mill()?;
+    let keys_count_stats = db_map.keys_count_stats()?;
+    let pairs = |v: &[(u32, u64)]| {
+        let items: Vec<String> = v.iter().map(|(a, b)| format!("[{a},{b}]")).collect();
+        format!("[{}]", items.join(","))
+    };
+    let mut items = vec![
+        format!("\"count\":{count}"),
+        format!("\"htx_filling_rate_per_mill\":{per_mill}"),
+        format!(
+            "\"max_chain_length\":{}",
+            keys_count_stats.max_chain_length()
+        ),
+        format!(
+            "\"average_probe_count\":{}",
+            keys_count_stats.average_probe_count()
+        ),
+        format!(
+            "\"count_of_free_key_piece\":{}",
+            pairs(&db_map.count_of_free_key_piece()?)
+        ),
+        format!(
+            "\"count_of_free_value_piece\":{}",
+            pairs(&db_map.count_of_free_value_piece()?)
+        ),
+    ];
+    let stats = [
+        ("keys_count_stats", keys_count_stats.to_string()),
+        (
+            "key_piece_size_stats",
+            db_map.key_piece_size_stats()?.to_string(),
+        ),
+        (
+            "value_piece_size_stats",
+            db_map.value_piece_size_stats()?.to_string(),
+        ),
+        ("key_length_stats", db_map.key_length_stats()?.to_string()),
+        (
+            "value_length_stats",
+            db_map.value_length_stats()?.to_string(),
+        ),
+    ];
+    for (name, val) in &stats {
+        items.push(format!("\"{name}\":{}", json_str(val)));
+    }
+    outln!("{{{}}}", items.join(","));
+    Ok(true)
+}
+
+fn cmd_verify<KT>(conf: &CmdConf, db_map: &FileDbMap<KT>) -> anyhow::Result<bool>
+where
+    KT: ToolKey + std::fmt::Display,
+{
+    let report = db_map.verify()?;
+    if conf.json {
+        let errors: Vec<String> = report.errors.iter().map(|e| json_str(e)).collect();
+        outln!(
+            "{{\"ok\":{},\"buckets\":{},\"reachable_keys\":{},\"item_count\":{},\
+             \"key_pieces\":{},\"free_key_pieces\":{},\"value_pieces\":{},\
+             \"free_value_pieces\":{},\"errors\":[{}]}}",
+            report.is_ok(),
+            report.buckets,
+            report.reachable_keys,
+            report.item_count,
+            report.key_pieces,
+            report.free_key_pieces,
+            report.value_pieces,
+            report.free_value_pieces,
+            errors.join(",")
+        );
+    } else {
+        outln!("{report}");
+    }
+    Ok(report.is_ok())
+}
+
+fn cmd_repair<KT: ToolKey>(conf: &CmdConf, db_map: &mut FileDbMap<KT>) -> anyhow::Result<bool> {
+    let report = db_map.repair()?;
+    db_map.sync_data()?;
+    if conf.json {
+        let notes: Vec<String> = report.notes.iter().map(|e| json_str(e)).collect();
+        outln!(
+            "{{\"item_count\":{},\"dropped_keys\":{},\"duplicate_keys\":{},\
+             \"free_key_pieces\":{},\"free_value_pieces\":{},\"notes\":[{}]}}",
+            report.item_count,
+            report.dropped_keys,
+            report.duplicate_keys,
+            report.free_key_pieces,
+            report.free_value_pieces,
+            notes.join(",")
+        );
+    } else {
+        outln!("{report}");
+    }
+    Ok(true)
+}
+
+fn dump_format(conf: &CmdConf) -> DumpFormat {
+    if conf.binary {
+        DumpFormat::Binary
+    } else {
+        DumpFormat::Text
+    }
+}
+
+fn cmd_dump<KT: ToolKey>(conf: &CmdConf, db_map: &FileDbMap<KT>) -> anyhow::Result<bool> {
+    let format = dump_format(conf);
+    match conf.args.first().map(|s| s.as_str()) {
+        None | Some("-") => {
+            let stdout = std::io::stdout();
+            db_map.export(stdout.lock(), format)?;
+        }
+        Some(path) => {
+            let file = std::fs::File::create(path).with_context(|| format!("create: {path}"))?;
+            let count = db_map.export(file, format)?;
+            print_count(conf, "dumped", count)?;
+        }
+    }
+    Ok(true)
+}
+
+fn cmd_load<KT: ToolKey>(conf: &CmdConf, db_map: &mut FileDbMap<KT>) -> anyhow::Result<bool> {
+    let format = dump_format(conf);
+    let count = match conf.args.first().map(|s| s.as_str()) {
+        None | Some("-") => {
+            let stdin = std::io::stdin();
+            db_map.import(stdin.lock(), format)?
+        }
+        Some(path) => {
+            let file = std::fs::File::open(path).with_context(|| format!("open: {path}"))?;
+            db_map.import(file, format)?
+        }
+    };
+    print_count(conf, "loaded", count)?;
+    Ok(true)
+}
+
+fn print_count(conf: &CmdConf, name: &str, count: u64) -> anyhow::Result<()> {
+    if conf.json {
+        outln!("{{\"{name}\":{count}}}");
+    } else {
+        outln!("{name}: {count}");
+    }
+    Ok(())
+}
+
+/// returns the JSON literal of the key, the numeric key is the JSON number.
+fn key_json<KT: ToolKey>(key: &KT) -> String {
+    if KT::is_numeric() {
+        key.key_text()
+    } else {
+        json_str(&key.key_text())
+    }
+}
+
+/// returns the JSON string literal.
+fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,288 @@
+//! the helpers shared by the command-line tools.
+use abyssiniandb::filedb::{CheckFileDbMap, FileDbMap};
+use abyssiniandb::{DbBytes, DbI64, DbString, DbU64, DbVu64};
+use abyssiniandb::{DbMap, DbMapKeyType};
+use anyhow::{anyhow, bail, Context};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    String,
+    Bytes,
+    I64,
+    U64,
+    Vu64,
+}
+
+impl KeyType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "string" => Some(KeyType::String),
+            "bytes" => Some(KeyType::Bytes),
+            "i64" => Some(KeyType::I64),
+            "u64" => Some(KeyType::U64),
+            "vu64" => Some(KeyType::Vu64),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyType::String => "string",
+            KeyType::Bytes => "bytes",
+            KeyType::I64 => "i64",
+            KeyType::U64 => "u64",
+            KeyType::Vu64 => "vu64",
+        }
+    }
+}
+
+pub fn map_key_path(db_path: &Path, map: &str) -> PathBuf {
+    db_path.join(format!("{map}.key"))
+}
+
+/// returns the names of the maps in the db directory, in name order.
+pub fn list_maps(db_path: &Path) -> std::io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for dir_entry in std::fs::read_dir(db_path)? {
+        let path = dir_entry?.path();
+        if path.extension() != Some("key".as_ref()) {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// reads the key type from the signature in the header of the key file.
+pub fn read_key_type(path: &Path) -> std::io::Result<String> {
+    let mut header = [0u8; 16];
+    let mut file = std::fs::File::open(path)?;
+    std::io::Read::read_exact(&mut file, &mut header)?;
+    let signature = &header[8..16];
+    let key_type = match signature {
+        b"string\0\0" => "string",
+        b"bytes\0\0\0" => "bytes",
+        b"i64_le\0\0" => "i64",
+        // the same signature.
+        b"u64_le\0\0" => "u64|vu64",
+        _ => "unknown",
+    };
+    Ok(key_type.to_string())
+}
+
+/// returns the lines of the statistics of the map.
+pub fn stats_lines(db_map: &dyn CheckFileDbMap, count: u64) -> std::io::Result<Vec<String>> {
+    let (_count, per_mill) = db_map.htx_filling_rate_per_mill()?;
+    let keys_count_stats = db_map.keys_count_stats()?;
+    Ok(vec![
+        format!("count: {count}"),
+        format!("htx_filling_rate: {:.1}%", per_mill as f64 / 10.0),
+        format!(
+            "max_chain_length: {}, average_probe_count: {:.2}",
+            keys_count_stats.max_chain_length(),
+            keys_count_stats.average_probe_count()
+        ),
+        format!(
+            "count_of_free_key_piece: {:?}",
+            db_map.count_of_free_key_piece()?
+        ),
+        format!(
+            "count_of_free_value_piece: {:?}",
+            db_map.count_of_free_value_piece()?
+        ),
+        format!("keys_count_stats: {keys_count_stats}"),
+        format!("key_piece_size_stats: {}", db_map.key_piece_size_stats()?),
+        format!(
+            "value_piece_size_stats: {}",
+            db_map.value_piece_size_stats()?
+        ),
+        format!("key_length_stats: {}", db_map.key_length_stats()?),
+        format!("value_length_stats: {}", db_map.value_length_stats()?),
+    ])
+}
+
+//
+// key types
+//
+/// the pairs of the scan.
+pub type ScanIter<KT> = Box<dyn Iterator<Item = (KT, Vec<u8>)>>;
+
+pub trait ToolKey: DbMapKeyType {
+    fn parse_key(s: &str) -> anyhow::Result<Self>;
+    fn key_text(&self) -> String;
+    fn is_numeric() -> bool {
+        true
+    }
+    /// returns the pairs whose key starts with the prefix, in key order with the ordered index,
+    /// otherwise in bucket order.
+    fn scan_prefix(db_map: &FileDbMap<Self>, prefix: &[u8]) -> anyhow::Result<ScanIter<Self>> {
+        let _ = (db_map, prefix);
+        bail!("the prefix is not for the numeric keys")
+    }
+}
+
+// the map without the ordered index is filtered in full.
+fn filter_prefix<KT: ToolKey>(
+    db_map: &FileDbMap<KT>,
+    prefix: &[u8],
+) -> anyhow::Result<ScanIter<KT>> {
+    let prefix = prefix.to_vec();
+    let iter = db_map
+        .iter()
+        .filter(move |(key, _)| key.as_bytes().starts_with(&prefix));
+    Ok(Box::new(iter))
+}
+
+impl ToolKey for DbString {
+    fn parse_key(s: &str) -> anyhow::Result<Self> {
+        Ok(DbString::from(unescape(s)?))
+    }
+    fn key_text(&self) -> String {
+        escape(self.as_bytes())
+    }
+    fn is_numeric() -> bool {
+        false
+    }
+    fn scan_prefix(db_map: &FileDbMap<Self>, prefix: &[u8]) -> anyhow::Result<ScanIter<Self>> {
+        if db_map.has_ordered_index() {
+            Ok(Box::new(db_map.scan_prefix(prefix)?))
+        } else {
+            filter_prefix(db_map, prefix)
+        }
+    }
+}
+
+impl ToolKey for DbBytes {
+    fn parse_key(s: &str) -> anyhow::Result<Self> {
+        Ok(DbBytes::from(unescape(s)?))
+    }
+    fn key_text(&self) -> String {
+        escape(self.as_bytes())
+    }
+    fn is_numeric() -> bool {
+        false
+    }
+    fn scan_prefix(db_map: &FileDbMap<Self>, prefix: &[u8]) -> anyhow::Result<ScanIter<Self>> {
+        if db_map.has_ordered_index() {
+            Ok(Box::new(db_map.scan_prefix(prefix)?))
+        } else {
+            filter_prefix(db_map, prefix)
+        }
+    }
+}
+
+impl ToolKey for DbI64 {
+    fn parse_key(s: &str) -> anyhow::Result<Self> {
+        let val: i64 = s.parse().with_context(|| format!("i64 key: {s}"))?;
+        Ok(DbI64::from(val))
+    }
+    fn key_text(&self) -> String {
+        match <[u8; 8]>::try_from(self.as_bytes()) {
+            Ok(b) => i64::from_le_bytes(b).to_string(),
+            Err(_) => escape(self.as_bytes()),
+        }
+    }
+}
+
+impl ToolKey for DbU64 {
+    fn parse_key(s: &str) -> anyhow::Result<Self> {
+        let val: u64 = s.parse().with_context(|| format!("u64 key: {s}"))?;
+        Ok(DbU64::from(val))
+    }
+    fn key_text(&self) -> String {
+        match <[u8; 8]>::try_from(self.as_bytes()) {
+            Ok(b) => u64::from_le_bytes(b).to_string(),
+            Err(_) => escape(self.as_bytes()),
+        }
+    }
+}
+
+impl ToolKey for DbVu64 {
+    fn parse_key(s: &str) -> anyhow::Result<Self> {
+        let val: u64 = s.parse().with_context(|| format!("vu64 key: {s}"))?;
+        Ok(DbVu64::from(val))
+    }
+    fn key_text(&self) -> String {
+        match vu64::decode(self.as_bytes()) {
+            Ok(val) => val.to_string(),
+            Err(_) => escape(self.as_bytes()),
+        }
+    }
+}
+
+//
+// escapes
+//
+/// escapes the bytes as the dump text format.
+pub fn escape(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    let mut rest = bytes;
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(s) => {
+                escape_str_into(s, &mut out);
+                return out;
+            }
+            Err(err) => {
+                let (valid, invalid) = rest.split_at(err.valid_up_to());
+                escape_str_into(std::str::from_utf8(valid).unwrap(), &mut out);
+                let invalid_len = err.error_len().unwrap_or(invalid.len());
+                for &b in &invalid[..invalid_len] {
+                    out.push_str(&format!("\\x{b:02x}"));
+                }
+                rest = &invalid[invalid_len..];
+            }
+        }
+    }
+}
+
+fn escape_str_into(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                out.push_str(&format!("\\x{:02x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+/// unescapes the argument of the dump text format.
+pub fn unescape(s: &str) -> anyhow::Result<Vec<u8>> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b != b'\\' {
+            out.push(b);
+            i += 1;
+            continue;
+        }
+        match bytes.get(i + 1) {
+            Some(b'\\') => out.push(b'\\'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'n') => out.push(b'\n'),
+            Some(b'r') => out.push(b'\r'),
+            Some(b'x') => {
+                let val = bytes
+                    .get((i + 2)..(i + 4))
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| anyhow!("invalid escape: {s}"))?;
+                out.push(val);
+                i += 2;
+            }
+            _ => bail!("invalid escape: {s}"),
+        }
+        i += 2;
+    }
+    Ok(out)
+}